@@ -0,0 +1,42 @@
+use std::process::Command;
+
+// Embed the git commit so `escrow-cli version --verify` can report which
+// source a binary was built from
+fn main() {
+    let commit = git(&["rev-parse", "HEAD"]).unwrap_or_else(|| "unknown".to_string());
+    let dirty = git(&["status", "--porcelain", "--untracked-files=no"])
+        .map(|status| !status.is_empty())
+        .unwrap_or(false);
+    println!(
+        "cargo:rustc-env=ESCROW_CLI_GIT_COMMIT={}{}",
+        commit,
+        if dirty { "-dirty" } else { "" }
+    );
+    // Release assets are named after the target triple
+    println!(
+        "cargo:rustc-env=ESCROW_CLI_TARGET={}",
+        std::env::var("TARGET").unwrap_or_default()
+    );
+    // A new commit moves the branch ref rather than HEAD, and editing a
+    // tracked file only touches the index once it is staged, so watch the
+    // ref and the sources too or the -dirty suffix goes stale
+    if let Some(git_dir) = git(&["rev-parse", "--git-dir"]) {
+        println!("cargo:rerun-if-changed={}/HEAD", git_dir);
+        println!("cargo:rerun-if-changed={}/index", git_dir);
+        println!("cargo:rerun-if-changed={}/packed-refs", git_dir);
+        if let Some(branch) = git(&["symbolic-ref", "-q", "HEAD"]) {
+            println!("cargo:rerun-if-changed={}/{}", git_dir, branch);
+        }
+    }
+    for path in ["src", "build.rs", "Cargo.toml", "Cargo.lock"] {
+        println!("cargo:rerun-if-changed={}", path);
+    }
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,30 @@
+#!/bin/sh
+# Build escrow-cli in a pinned container so that two people building the same
+# commit get byte-identical binaries, then print the hash that
+# `escrow-cli version --verify` reports.
+set -eu
+
+IMAGE="rust:1.76.0-slim-bookworm"
+cd "$(dirname "$0")/.."
+
+if [ ! -f Cargo.lock ]; then
+    echo "Cargo.lock is required for a reproducible build" >&2
+    exit 1
+fi
+if [ -n "$(git status --porcelain --untracked-files=no)" ]; then
+    echo "Warning: the working tree has uncommitted changes" >&2
+fi
+
+docker run --rm \
+    -v "$PWD":/src -w /src \
+    -e SOURCE_DATE_EPOCH="$(git log -1 --format=%ct)" \
+    -e CARGO_TARGET_DIR=/src/target/reproducible \
+    -e RUSTFLAGS="--remap-path-prefix=/src=. --remap-path-prefix=/usr/local/cargo=cargo -C codegen-units=1" \
+    "$IMAGE" \
+    sh -c "apt-get update -qq && apt-get install -y -qq git pkg-config libudev-dev >/dev/null \
+        && git config --global --add safe.directory /src \
+        && cargo build --release --locked"
+
+BINARY=target/reproducible/release/escrow-cli
+echo "Commit:     $(git rev-parse HEAD)"
+echo "Build hash: $(sha256sum "$BINARY" | cut -d' ' -f1)"
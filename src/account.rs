@@ -0,0 +1,224 @@
+use clap::ValueEnum;
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use std::fmt;
+
+use crate::amount::Lamports;
+
+// Escrow account layout: buyer(32) seller(32) arbiter(32) amount(8) state(1) vault_bump(1)
+pub const ESCROW_ACCOUNT_SIZE: usize = 106; // 32+32+32+8+1+1 = 106 bytes
+pub const BUYER_OFFSET: usize = 0;
+pub const SELLER_OFFSET: usize = 32;
+pub const ARBITER_OFFSET: usize = 64;
+pub const AMOUNT_OFFSET: usize = 96;
+pub const STATE_OFFSET: usize = 104;
+pub const VAULT_BUMP_OFFSET: usize = 105;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum EscrowState {
+    Uninitialized,
+    Created,
+    Initialized,
+    Funded,
+    Completed,
+    Cancelled,
+}
+
+impl TryFrom<u8> for EscrowState {
+    type Error = DecodeError;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        match byte {
+            0 => Ok(EscrowState::Uninitialized),
+            1 => Ok(EscrowState::Created),
+            2 => Ok(EscrowState::Initialized),
+            3 => Ok(EscrowState::Funded),
+            4 => Ok(EscrowState::Completed),
+            5 => Ok(EscrowState::Cancelled),
+            _ => Err(DecodeError::InvalidState(byte)),
+        }
+    }
+}
+
+impl fmt::Display for EscrowState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+// Errors produced when decoding raw escrow account data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    TooShort { len: usize },
+    InvalidState(u8),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::TooShort { len } => write!(
+                f,
+                "Invalid account data length: expected at least {} bytes, got {}",
+                ESCROW_ACCOUNT_SIZE, len
+            ),
+            DecodeError::InvalidState(byte) => write!(f, "Invalid state byte: {}", byte),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EscrowAccount {
+    pub buyer: Pubkey,
+    pub seller: Pubkey,
+    pub arbiter: Pubkey,
+    pub amount: Lamports,
+    pub state: EscrowState,
+    pub vault_bump: u8,
+}
+
+impl EscrowAccount {
+    // Decode account data; never panics on short or malformed input
+    pub fn unpack(data: &[u8]) -> Result<Self, DecodeError> {
+        if data.len() < ESCROW_ACCOUNT_SIZE {
+            return Err(DecodeError::TooShort { len: data.len() });
+        }
+        let mut amount = [0u8; 8];
+        amount.copy_from_slice(&data[AMOUNT_OFFSET..AMOUNT_OFFSET + 8]);
+        Ok(EscrowAccount {
+            buyer: read_pubkey(data, BUYER_OFFSET),
+            seller: read_pubkey(data, SELLER_OFFSET),
+            arbiter: read_pubkey(data, ARBITER_OFFSET),
+            amount: Lamports::new(u64::from_le_bytes(amount)),
+            state: EscrowState::try_from(data[STATE_OFFSET])?,
+            vault_bump: data[VAULT_BUMP_OFFSET],
+        })
+    }
+
+    // Encode in the on-chain layout; the inverse of `unpack`
+    pub fn pack(&self) -> [u8; ESCROW_ACCOUNT_SIZE] {
+        let mut data = [0u8; ESCROW_ACCOUNT_SIZE];
+        data[BUYER_OFFSET..BUYER_OFFSET + 32].copy_from_slice(self.buyer.as_ref());
+        data[SELLER_OFFSET..SELLER_OFFSET + 32].copy_from_slice(self.seller.as_ref());
+        data[ARBITER_OFFSET..ARBITER_OFFSET + 32].copy_from_slice(self.arbiter.as_ref());
+        data[AMOUNT_OFFSET..AMOUNT_OFFSET + 8]
+            .copy_from_slice(&self.amount.lamports().to_le_bytes());
+        data[STATE_OFFSET] = self.state as u8;
+        data[VAULT_BUMP_OFFSET] = self.vault_bump;
+        data
+    }
+
+    // Buyer, arbiter and (once joined) seller
+    pub fn parties(&self) -> Vec<Pubkey> {
+        [self.buyer, self.seller, self.arbiter]
+            .into_iter()
+            .filter(|party| *party != Pubkey::default())
+            .collect()
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "buyer": self.buyer.to_string(),
+            "seller": self.seller.to_string(),
+            "arbiter": self.arbiter.to_string(),
+            "amount": self.amount.lamports(),
+            "state": self.state.to_string(),
+            "vault_bump": self.vault_bump,
+        })
+    }
+}
+
+// Byte range fetched for a state-only read: the state and the bump after
+// it, so an account shorter than the layout still comes back short
+pub const STATE_SLICE: (usize, usize) = (STATE_OFFSET, ESCROW_ACCOUNT_SIZE - STATE_OFFSET);
+
+// Decode only the state byte from a STATE_SLICE read, for callers that don't
+// need the full account
+pub fn unpack_state(slice: &[u8]) -> Result<EscrowState, DecodeError> {
+    if slice.len() < STATE_SLICE.1 {
+        return Err(DecodeError::TooShort { len: STATE_OFFSET + slice.len() });
+    }
+    EscrowState::try_from(slice[0])
+}
+
+// Caller guarantees `offset + 32 <= data.len()`
+fn read_pubkey(data: &[u8], offset: usize) -> Pubkey {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&data[offset..offset + 32]);
+    Pubkey::new_from_array(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn pubkey() -> impl Strategy<Value = Pubkey> {
+        any::<[u8; 32]>().prop_map(Pubkey::new_from_array)
+    }
+
+    fn state() -> impl Strategy<Value = EscrowState> {
+        (0u8..=5).prop_map(|byte| EscrowState::try_from(byte).unwrap())
+    }
+
+    fn escrow() -> impl Strategy<Value = EscrowAccount> {
+        (
+            pubkey(),
+            pubkey(),
+            pubkey(),
+            any::<u64>(),
+            state(),
+            any::<u8>(),
+        )
+            .prop_map(|(buyer, seller, arbiter, amount, state, vault_bump)| {
+                EscrowAccount {
+                    buyer,
+                    seller,
+                    arbiter,
+                    amount: Lamports::new(amount),
+                    state,
+                    vault_bump,
+                }
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn unpack_never_panics(data in proptest::collection::vec(any::<u8>(), 0..300)) {
+            let _ = EscrowAccount::unpack(&data);
+        }
+
+        #[test]
+        fn unpack_inverts_pack(escrow in escrow()) {
+            prop_assert_eq!(EscrowAccount::unpack(&escrow.pack()), Ok(escrow));
+        }
+
+        #[test]
+        fn unpack_ignores_trailing_bytes(
+            escrow in escrow(),
+            extra in proptest::collection::vec(any::<u8>(), 0..64),
+        ) {
+            let mut data = escrow.pack().to_vec();
+            data.extend(extra);
+            prop_assert_eq!(EscrowAccount::unpack(&data), Ok(escrow));
+        }
+
+        #[test]
+        fn unpack_rejects_short_data(
+            data in proptest::collection::vec(any::<u8>(), 0..ESCROW_ACCOUNT_SIZE),
+        ) {
+            prop_assert_eq!(
+                EscrowAccount::unpack(&data),
+                Err(DecodeError::TooShort { len: data.len() })
+            );
+        }
+
+        #[test]
+        fn unpack_rejects_unknown_states(escrow in escrow(), byte in 6u8..) {
+            let mut data = escrow.pack();
+            data[STATE_OFFSET] = byte;
+            prop_assert_eq!(EscrowAccount::unpack(&data), Err(DecodeError::InvalidState(byte)));
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::account::{EscrowAccount, EscrowState};
+use crate::{note, Context};
+
+// A participant's part in an escrow
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Buyer,
+    Seller,
+    Arbiter,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+// An instruction the escrow program accepts on an existing escrow
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    JoinOffer,
+    Fund,
+    Confirm,
+    ArbiterConfirm,
+    ArbiterCancel,
+    MutualCancel,
+    Close,
+}
+
+impl Action {
+    // The command performing this action, with its required arguments
+    pub fn usage(&self, escrow: &EscrowAccount, escrow_pubkey: &Pubkey) -> String {
+        match self {
+            Action::JoinOffer => format!("join-offer -s <SELLER_KEYPAIR> {}", escrow_pubkey),
+            Action::Fund => format!("fund -b <BUYER_KEYPAIR> {}", escrow_pubkey),
+            Action::Confirm => format!("confirm -s <SELLER_KEYPAIR> {}", escrow_pubkey),
+            Action::ArbiterConfirm => format!(
+                "arbiter-confirm -a <ARBITER_KEYPAIR> {} -s {}",
+                escrow_pubkey, escrow.seller
+            ),
+            Action::ArbiterCancel => format!(
+                "arbiter-cancel -a <ARBITER_KEYPAIR> {} -b {}",
+                escrow_pubkey, escrow.buyer
+            ),
+            Action::MutualCancel => format!(
+                "mutual-cancel -b <BUYER_KEYPAIR> -s <SELLER_KEYPAIR> {}",
+                escrow_pubkey
+            ),
+            Action::Close => format!("close -c <KEYPAIR> {}", escrow_pubkey),
+        }
+    }
+}
+
+// The actions the program lets `role` take on an escrow in `state`. This
+// mirrors the program's own checks: join_offer needs Created, fund needs
+// Initialized, the settling instructions need Funded, mutual_cancel
+// Initialized or Funded (signed by buyer and seller together), and close a
+// settled escrow.
+pub fn allowed_actions(state: EscrowState, role: Role) -> Vec<Action> {
+    use EscrowState::*;
+    match (state, role) {
+        (Created, Role::Seller) => vec![Action::JoinOffer],
+        (Initialized, Role::Buyer) => vec![Action::Fund, Action::MutualCancel],
+        (Initialized, Role::Seller) => vec![Action::MutualCancel],
+        (Funded, Role::Seller) => vec![Action::Confirm, Action::MutualCancel],
+        (Funded, Role::Buyer) => vec![Action::MutualCancel],
+        (Funded, Role::Arbiter) => vec![Action::ArbiterConfirm, Action::ArbiterCancel],
+        (Completed | Cancelled, _) => vec![Action::Close],
+        _ => vec![],
+    }
+}
+
+// Roles `pubkey` holds in an escrow; a Created escrow has no seller yet, so
+// anyone but the buyer and arbiter may join as one
+fn roles_of(escrow: &EscrowAccount, pubkey: &Pubkey) -> Vec<Role> {
+    let mut roles = Vec::new();
+    if escrow.buyer == *pubkey {
+        roles.push(Role::Buyer);
+    }
+    let open_seat = escrow.state == EscrowState::Created && roles.is_empty();
+    if escrow.seller == *pubkey || (open_seat && escrow.arbiter != *pubkey) {
+        roles.push(Role::Seller);
+    }
+    if escrow.arbiter == *pubkey {
+        roles.push(Role::Arbiter);
+    }
+    roles
+}
+
+// Print what can be done with an escrow next, for one role, for the roles a
+// pubkey holds, or for everyone
+pub fn next(
+    ctx: &Context,
+    escrow_account: &str,
+    role: Option<Role>,
+    pubkey: Option<&str>,
+) -> Result<()> {
+    let escrow_pubkey = Pubkey::from_str(escrow_account)?;
+    let escrow = ctx.get_escrow(&escrow_pubkey)?;
+    let roles = match (role, pubkey) {
+        (Some(role), _) => vec![role],
+        (None, Some(pubkey)) => {
+            let pubkey =
+                Pubkey::from_str(pubkey).map_err(|_| anyhow!("Invalid pubkey '{}'", pubkey))?;
+            let roles = roles_of(&escrow, &pubkey);
+            if roles.is_empty() {
+                return Err(anyhow!(
+                    "{} is not a party to escrow {}",
+                    pubkey,
+                    escrow_pubkey
+                ));
+            }
+            roles
+        }
+        (None, None) => vec![Role::Buyer, Role::Seller, Role::Arbiter],
+    };
+    println!("Escrow {} is {}", escrow_pubkey, escrow.state);
+    note::show(ctx, &escrow_pubkey, &escrow)?;
+    for role in roles {
+        let actions = allowed_actions(escrow.state, role);
+        if actions.is_empty() {
+            println!("{}: nothing to do", role);
+            continue;
+        }
+        println!("{}:", role);
+        for action in actions {
+            println!("  escrow-cli {}", action.usage(&escrow, &escrow_pubkey));
+        }
+    }
+    Ok(())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amount::Lamports;
+    use EscrowState::*;
+
+    // The program's rules, written out for every state and role
+    const TABLE: [(EscrowState, Role, &[Action]); 18] = [
+        (Uninitialized, Role::Buyer, &[]),
+        (Uninitialized, Role::Seller, &[]),
+        (Uninitialized, Role::Arbiter, &[]),
+        (Created, Role::Buyer, &[]),
+        (Created, Role::Seller, &[Action::JoinOffer]),
+        (Created, Role::Arbiter, &[]),
+        (
+            Initialized,
+            Role::Buyer,
+            &[Action::Fund, Action::MutualCancel],
+        ),
+        (Initialized, Role::Seller, &[Action::MutualCancel]),
+        (Initialized, Role::Arbiter, &[]),
+        (Funded, Role::Buyer, &[Action::MutualCancel]),
+        (
+            Funded,
+            Role::Seller,
+            &[Action::Confirm, Action::MutualCancel],
+        ),
+        (
+            Funded,
+            Role::Arbiter,
+            &[Action::ArbiterConfirm, Action::ArbiterCancel],
+        ),
+        (Completed, Role::Buyer, &[Action::Close]),
+        (Completed, Role::Seller, &[Action::Close]),
+        (Completed, Role::Arbiter, &[Action::Close]),
+        (Cancelled, Role::Buyer, &[Action::Close]),
+        (Cancelled, Role::Seller, &[Action::Close]),
+        (Cancelled, Role::Arbiter, &[Action::Close]),
+    ];
+
+    #[test]
+    fn table_covers_every_state_and_role() {
+        for state in EscrowState::value_variants() {
+            for role in Role::value_variants() {
+                let rows = TABLE
+                    .iter()
+                    .filter(|(s, r, _)| s == state && r == role)
+                    .count();
+                assert_eq!(rows, 1, "{} as {}", state, role);
+            }
+        }
+    }
+
+    #[test]
+    fn allowed_actions_match_the_table() {
+        for (state, role, expected) in TABLE {
+            assert_eq!(
+                allowed_actions(state, role),
+                expected,
+                "{} as {}",
+                state,
+                role
+            );
+        }
+    }
+
+    #[test]
+    fn actions_are_signed_by_their_role() {
+        for state in EscrowState::value_variants() {
+            for role in Role::value_variants() {
+                for action in allowed_actions(*state, *role) {
+                    let signers: &[Role] = match action {
+                        Action::JoinOffer | Action::Confirm => &[Role::Seller],
+                        Action::Fund => &[Role::Buyer],
+                        Action::ArbiterConfirm | Action::ArbiterCancel => &[Role::Arbiter],
+                        Action::MutualCancel => &[Role::Buyer, Role::Seller],
+                        Action::Close => &[Role::Buyer, Role::Seller, Role::Arbiter],
+                    };
+                    assert!(
+                        signers.contains(role),
+                        "{:?} for {} in {}",
+                        action,
+                        role,
+                        state
+                    );
+                }
+            }
+        }
+    }
+
+    fn escrow(state: EscrowState, seller: Pubkey) -> EscrowAccount {
+        EscrowAccount {
+            buyer: Pubkey::new_from_array([1; 32]),
+            seller,
+            arbiter: Pubkey::new_from_array([3; 32]),
+            amount: Lamports::new(1),
+            state,
+            vault_bump: 255,
+        }
+    }
+
+    #[test]
+    fn roles_of_parties() {
+        let seller = Pubkey::new_from_array([2; 32]);
+        let stranger = Pubkey::new_from_array([9; 32]);
+        for state in EscrowState::value_variants() {
+            let joined = escrow(*state, seller);
+            assert_eq!(roles_of(&joined, &joined.buyer), [Role::Buyer]);
+            assert_eq!(roles_of(&joined, &seller), [Role::Seller]);
+            assert_eq!(roles_of(&joined, &joined.arbiter), [Role::Arbiter]);
+        }
+        // Before anyone joins, any outsider may take the seller's seat
+        let open = escrow(Created, Pubkey::default());
+        assert_eq!(roles_of(&open, &stranger), [Role::Seller]);
+        assert_eq!(roles_of(&open, &open.buyer), [Role::Buyer]);
+        assert_eq!(roles_of(&open, &open.arbiter), [Role::Arbiter]);
+        let funded = escrow(Funded, seller);
+        assert!(roles_of(&funded, &stranger).is_empty());
+    }
+}
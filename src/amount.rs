@@ -0,0 +1,266 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+pub const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+const SOL_DECIMALS: usize = 9;
+
+// --raw: print amounts as plain digits, without grouping, for scripts
+static RAW: AtomicBool = AtomicBool::new(false);
+
+pub fn set_raw() {
+    RAW.store(true, Ordering::SeqCst);
+}
+
+// An amount of SOL in lamports. The count is private so arithmetic goes
+// through the checked helpers and totals over many escrows can't silently
+// wrap; displays in SOL like `format_sol`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lamports(u64);
+
+impl Lamports {
+    pub const ZERO: Lamports = Lamports(0);
+
+    pub const fn new(lamports: u64) -> Lamports {
+        Lamports(lamports)
+    }
+
+    // The raw count, for encoding and for APIs that take u64 lamports
+    pub const fn lamports(self) -> u64 {
+        self.0
+    }
+
+    pub fn checked_add(self, other: Lamports) -> Result<Lamports, AmountError> {
+        self.0.checked_add(other.0).map(Lamports).ok_or(AmountError::Overflow)
+    }
+
+    pub fn checked_sub(self, other: Lamports) -> Result<Lamports, AmountError> {
+        self.0.checked_sub(other.0).map(Lamports).ok_or(AmountError::Overflow)
+    }
+
+    pub fn checked_sum(
+        amounts: impl IntoIterator<Item = Lamports>,
+    ) -> Result<Lamports, AmountError> {
+        amounts
+            .into_iter()
+            .try_fold(Lamports::ZERO, |total, amount| total.checked_add(amount))
+    }
+}
+
+impl fmt::Display for Lamports {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&format_sol(self.0))
+    }
+}
+
+impl FromStr for Lamports {
+    type Err = AmountError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        parse_amount(input)
+    }
+}
+
+// Errors produced when parsing a user-supplied amount
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AmountError {
+    Empty,
+    Invalid(String),
+    TooManyDecimals,
+    Overflow,
+}
+
+impl fmt::Display for AmountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AmountError::Empty => write!(f, "Amount is empty"),
+            AmountError::Invalid(input) => write!(
+                f,
+                "Invalid amount '{}': expected lamports (e.g. 1000) or SOL (e.g. 1.5SOL)",
+                input
+            ),
+            AmountError::TooManyDecimals => {
+                write!(f, "SOL amounts support at most {} decimal places", SOL_DECIMALS)
+            }
+            AmountError::Overflow => write!(f, "Amount does not fit into u64 lamports"),
+        }
+    }
+}
+
+impl std::error::Error for AmountError {}
+
+// Parse "1000", "1000lamports", "1.5SOL" or "0.25 sol" into lamports
+pub fn parse_amount(input: &str) -> Result<Lamports, AmountError> {
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        return Err(AmountError::Empty);
+    }
+    let lower = trimmed.to_ascii_lowercase();
+    if let Some(sol) = lower.strip_suffix("sol") {
+        return parse_sol(sol.trim_end(), trimmed).map(Lamports);
+    }
+    let lamports = lower
+        .strip_suffix("lamports")
+        .map(str::trim_end)
+        .unwrap_or(&lower);
+    parse_digits(lamports, trimmed).map(Lamports)
+}
+
+fn parse_sol(value: &str, original: &str) -> Result<u64, AmountError> {
+    let (whole, fraction) = match value.split_once('.') {
+        Some((whole, fraction)) => (whole, fraction),
+        None => (value, ""),
+    };
+    if whole.is_empty() && fraction.is_empty() {
+        return Err(AmountError::Invalid(original.to_string()));
+    }
+    if fraction.len() > SOL_DECIMALS {
+        return Err(AmountError::TooManyDecimals);
+    }
+    let whole = if whole.is_empty() {
+        0
+    } else {
+        parse_digits(whole, original)?
+    };
+    let fraction = if fraction.is_empty() {
+        0
+    } else {
+        let digits = parse_digits(fraction, original)?;
+        // Scale "5" in "1.5" up to 500_000_000 lamports
+        digits * 10u64.pow((SOL_DECIMALS - fraction.len()) as u32)
+    };
+    whole
+        .checked_mul(LAMPORTS_PER_SOL)
+        .and_then(|lamports| lamports.checked_add(fraction))
+        .ok_or(AmountError::Overflow)
+}
+
+fn parse_digits(value: &str, original: &str) -> Result<u64, AmountError> {
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(AmountError::Invalid(original.to_string()));
+    }
+    value.parse::<u64>().map_err(|_| AmountError::Overflow)
+}
+
+// Render lamports as a SOL string without going through floating point,
+// grouped by the locale's separators unless --raw
+pub fn format_sol(lamports: u64) -> String {
+    let whole = lamports / LAMPORTS_PER_SOL;
+    let fraction = lamports % LAMPORTS_PER_SOL;
+    let separators = Separators::current();
+    let whole = separators.group(whole);
+    if fraction == 0 {
+        return whole;
+    }
+    let fraction = format!("{:09}", fraction);
+    format!(
+        "{}{}{}",
+        whole,
+        separators.decimal,
+        fraction.trim_end_matches('0')
+    )
+}
+
+// Render a lamport count, grouped like `format_sol`
+pub fn format_lamports(lamports: u64) -> String {
+    Separators::current().group(lamports)
+}
+
+struct Separators {
+    // None under --raw
+    thousands: Option<char>,
+    decimal: char,
+}
+
+impl Separators {
+    // From LC_ALL, LC_NUMERIC or LANG, whichever is set first; "1,234.5"
+    // for C, POSIX, English and anything not listed
+    fn current() -> Separators {
+        if RAW.load(Ordering::SeqCst) {
+            return Separators {
+                thousands: None,
+                decimal: '.',
+            };
+        }
+        let locale = ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        let language = locale
+            .split(['_', '.', '@', '-'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let (thousands, decimal) = match language.as_str() {
+            "de" | "es" | "it" | "nl" | "pt" | "id" | "tr" | "da" | "el" => ('.', ','),
+            // U+00A0, so a wrapped line never splits a number
+            "fr" | "ru" | "uk" | "pl" | "cs" | "sk" | "sv" | "fi" | "nb" | "hu" => ('\u{a0}', ','),
+            _ => (',', '.'),
+        };
+        Separators {
+            thousands: Some(thousands),
+            decimal,
+        }
+    }
+
+    fn group(&self, value: u64) -> String {
+        let digits = value.to_string();
+        let Some(separator) = self.thousands else {
+            return digits;
+        };
+        let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                grouped.push(separator);
+            }
+            grouped.push(digit);
+        }
+        grouped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn parse_amount_never_panics(input in "\\PC*") {
+            let _ = parse_amount(&input);
+        }
+
+        #[test]
+        fn lamports_parse_exactly(lamports in any::<u64>()) {
+            prop_assert_eq!(parse_amount(&lamports.to_string()), Ok(Lamports(lamports)));
+            prop_assert_eq!(
+                parse_amount(&format!("{} lamports", lamports)),
+                Ok(Lamports(lamports))
+            );
+        }
+
+        #[test]
+        fn sol_parses_exactly(lamports in any::<u64>()) {
+            let sol = format!(
+                "{}.{:09}SOL",
+                lamports / LAMPORTS_PER_SOL,
+                lamports % LAMPORTS_PER_SOL
+            );
+            prop_assert_eq!(parse_amount(&sol), Ok(Lamports(lamports)));
+        }
+
+        #[test]
+        fn sol_above_u64_overflows(whole in (u64::MAX / LAMPORTS_PER_SOL + 1)..) {
+            prop_assert_eq!(parse_amount(&format!("{}SOL", whole)), Err(AmountError::Overflow));
+        }
+
+        #[test]
+        fn too_many_decimals_rejected(fraction in "[0-9]{10,20}") {
+            prop_assert_eq!(
+                parse_amount(&format!("1.{}SOL", fraction)),
+                Err(AmountError::TooManyDecimals)
+            );
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use solana_sdk::{
+    message::Message,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::account::{EscrowAccount, EscrowState};
+use crate::ledger;
+use crate::memo::memo_instruction;
+use crate::terms::digest_bytes;
+use crate::{simulate_and_send, Context};
+
+// Final state of a settled escrow, kept in the ledger after `close` deletes
+// the account
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ArchiveRecord {
+    // Decoded account: parties, amount, outcome state and vault bump
+    pub account: Value,
+    // SHA-256 of the raw account data
+    pub digest: String,
+    pub slot: u64,
+    pub archived_at: i64,
+    // Memo transaction publishing the digest, when anchored on-chain
+    pub anchor: Option<String>,
+}
+
+// Record a Completed or Cancelled escrow in the ledger. With a keypair, the
+// digest is also posted in a memo so the record can be proven later.
+pub fn archive(
+    ctx: &Context,
+    escrow_account: &str,
+    anchor_keypair: Option<&Keypair>,
+) -> Result<()> {
+    let escrow_pubkey = Pubkey::from_str(escrow_account)?;
+    let response = ctx
+        .client
+        .get_account_with_config(&escrow_pubkey, ctx.account_config())?;
+    let data = response
+        .value
+        .map(|account| account.data)
+        .ok_or_else(|| {
+            anyhow!("Account {} not found; closed escrows can't be archived", escrow_pubkey)
+        })?;
+    let escrow = EscrowAccount::unpack(&data)?;
+    match escrow.state {
+        EscrowState::Completed | EscrowState::Cancelled => {}
+        other_state => {
+            return Err(anyhow!(
+                "Only settled escrows can be archived, current state: {:?}",
+                other_state
+            ))
+        }
+    }
+    let digest = digest_bytes(&data);
+    let anchor = match anchor_keypair {
+        Some(keypair) => anchor_digest(ctx, keypair, &escrow_pubkey, &escrow, &digest)?,
+        None => None,
+    };
+    // A dry run previews the anchor but records nothing
+    if ctx.dry_run {
+        println!("Dry run: escrow {} was not archived", escrow_pubkey);
+        return Ok(());
+    }
+    let record = ArchiveRecord {
+        account: escrow.to_json(),
+        digest,
+        slot: response.context.slot,
+        archived_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64,
+        anchor,
+    };
+    println!("Archived {} escrow {} (SHA-256 {})", escrow.state, escrow_pubkey, record.digest);
+    ledger::update(&ctx.config, |ledger| {
+        ledger.entry_mut(escrow_account).archive = Some(record);
+    })
+}
+
+fn anchor_digest(
+    ctx: &Context,
+    keypair: &Keypair,
+    escrow_pubkey: &Pubkey,
+    escrow: &EscrowAccount,
+    digest: &str,
+) -> Result<Option<String>> {
+    if !escrow.parties().contains(&keypair.pubkey()) {
+        return Err(anyhow!("{} is not a party to this escrow", keypair.pubkey()));
+    }
+    let memo = format!("escrow-archive:v1 {} {}", escrow_pubkey, digest);
+    let memo_ix = memo_instruction(&memo, &[&keypair.pubkey()]);
+    let blockhash = ctx.client.get_latest_blockhash()?;
+    let message = Message::new(&[memo_ix], Some(&keypair.pubkey()));
+    let transaction = Transaction::new(&[keypair], message, blockhash);
+    let Some(signature) = simulate_and_send(ctx, &transaction, escrow_pubkey, "archive")? else {
+        return Ok(None);
+    };
+    println!("Archive digest anchored! Signature: {}", signature);
+    Ok(Some(signature.to_string()))
+}
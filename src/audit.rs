@@ -0,0 +1,108 @@
+use anyhow::{anyhow, Result};
+use solana_sdk::{pubkey::Pubkey, signature::Signer};
+use std::str::FromStr;
+
+use crate::account::{EscrowAccount, EscrowState};
+use crate::amount::{format_sol, Lamports};
+use crate::ledger::{ledger_path, Ledger};
+use crate::search::escrows_with_party;
+use crate::{find_vault_pda, Context};
+
+// Recompute each escrow's vault PDA and check it against the stored bump and
+// the vault's actual balance. Covers the ledger's escrows, or those where
+// `wallet` (keypair or pubkey) is a party.
+pub fn verify_vault_bump(ctx: &Context, wallet: Option<&str>) -> Result<()> {
+    let escrows: Vec<(Pubkey, Option<EscrowAccount>)> = match wallet {
+        Some(wallet) => {
+            let owner = match Pubkey::from_str(wallet) {
+                Ok(pubkey) => pubkey,
+                Err(_) => ctx.read_keypair(wallet, "wallet")?.pubkey(),
+            };
+            let mut found = escrows_with_party(ctx, &owner)?;
+            found.sort_by_key(|(escrow, _)| *escrow);
+            found.dedup_by_key(|(escrow, _)| *escrow);
+            found
+                .into_iter()
+                .map(|(escrow, account)| (escrow, Some(account)))
+                .collect()
+        }
+        None => {
+            let ledger = Ledger::load(&ledger_path(&ctx.config)?)?;
+            let pubkeys = ledger
+                .escrows
+                .keys()
+                .map(|escrow| Pubkey::from_str(escrow))
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut accounts = Vec::with_capacity(pubkeys.len());
+            for chunk in pubkeys.chunks(100) {
+                accounts.extend(ctx.get_multiple_accounts(chunk)?);
+            }
+            pubkeys
+                .into_iter()
+                .zip(accounts)
+                // Closed escrows have nothing left to audit
+                .filter_map(|(escrow, account)| {
+                    let account = account?;
+                    Some((escrow, EscrowAccount::unpack(&account.data).ok()))
+                })
+                .collect()
+        }
+    };
+
+    let mut anomalies = 0;
+    for (escrow_pubkey, escrow) in &escrows {
+        let Some(escrow) = escrow else {
+            println!("{}  ANOMALY: account data does not decode", escrow_pubkey);
+            anomalies += 1;
+            continue;
+        };
+        let (vault, bump) = find_vault_pda(escrow_pubkey, &ctx.program_id);
+        let vault_balance = ctx.client.get_balance(&vault)?;
+        let mut problems = Vec::new();
+        // The bump is only written once the vault is set up
+        if escrow.vault_bump != 0 && escrow.vault_bump != bump {
+            problems.push(format!(
+                "stored bump {} but the canonical bump is {}",
+                escrow.vault_bump, bump
+            ));
+        }
+        match escrow.state {
+            EscrowState::Funded if Lamports::new(vault_balance) < escrow.amount => {
+                problems.push(format!(
+                    "vault holds {} SOL, less than the funded {} SOL",
+                    format_sol(vault_balance),
+                    escrow.amount
+                ))
+            }
+            EscrowState::Created | EscrowState::Initialized if vault_balance > 0 => {
+                problems.push(format!(
+                    "vault holds {} SOL before funding",
+                    format_sol(vault_balance)
+                ))
+            }
+            EscrowState::Completed | EscrowState::Cancelled if vault_balance > 0 => {
+                problems.push(format!(
+                    "vault still holds {} SOL after settlement",
+                    format_sol(vault_balance)
+                ))
+            }
+            _ => {}
+        }
+        if problems.is_empty() {
+            println!(
+                "{}  ok ({}, vault {} bump {})",
+                escrow_pubkey, escrow.state, vault, bump
+            );
+        } else {
+            anomalies += 1;
+            for problem in problems {
+                println!("{}  ANOMALY: {}", escrow_pubkey, problem);
+            }
+        }
+    }
+    println!("Audited {} escrows, {} with anomalies", escrows.len(), anomalies);
+    if anomalies > 0 {
+        return Err(anyhow!("Vault audit found {} anomalous escrows", anomalies));
+    }
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use anyhow::Result;
+use solana_sdk::{pubkey::Pubkey, signature::Signer};
+use std::str::FromStr;
+
+use crate::account::{EscrowAccount, EscrowState};
+use crate::amount::{format_sol, Lamports};
+use crate::search::escrows_with_party;
+use crate::Context;
+
+// Count and total amount of a group of escrows
+#[derive(Default)]
+struct Tally {
+    count: usize,
+    lamports: Lamports,
+}
+
+impl Tally {
+    fn add(&mut self, escrow: &EscrowAccount) -> Result<()> {
+        self.count += 1;
+        self.lamports = self.lamports.checked_add(escrow.amount)?;
+        Ok(())
+    }
+
+    fn line(&self, label: &str) {
+        println!(
+            "  {:<26} {:>3} escrows  {:>14} SOL",
+            label, self.count, self.lamports
+        );
+    }
+}
+
+// Portfolio view for one wallet: its SOL balance plus what it has locked in,
+// owes to, or expects from open escrows. `wallet` is a keypair or a pubkey.
+pub fn balance(ctx: &Context, wallet: &str) -> Result<()> {
+    let owner = match Pubkey::from_str(wallet) {
+        Ok(pubkey) => pubkey,
+        Err(_) => ctx.read_keypair(wallet, "wallet")?.pubkey(),
+    };
+    let mut escrows = escrows_with_party(ctx, &owner)?;
+    // A self-dealt escrow shows up once per matching role
+    escrows.sort_by_key(|(escrow, _)| *escrow);
+    escrows.dedup_by_key(|(escrow, _)| *escrow);
+
+    let mut locked = Tally::default();
+    let mut to_fund = Tally::default();
+    let mut incoming = Tally::default();
+    let mut joined = Tally::default();
+    let mut arbitrating = Tally::default();
+    for (_, escrow) in &escrows {
+        let state = escrow.state;
+        if escrow.buyer == owner {
+            match state {
+                EscrowState::Funded => locked.add(escrow)?,
+                EscrowState::Created | EscrowState::Initialized => to_fund.add(escrow)?,
+                _ => {}
+            }
+        }
+        if escrow.seller == owner {
+            match state {
+                EscrowState::Funded => incoming.add(escrow)?,
+                EscrowState::Initialized => joined.add(escrow)?,
+                _ => {}
+            }
+        }
+        if escrow.arbiter == owner && state == EscrowState::Funded {
+            arbitrating.add(escrow)?;
+        }
+    }
+
+    println!("Wallet: {}", owner);
+    println!("  SOL balance: {} SOL", format_sol(ctx.client.get_balance(&owner)?));
+    println!("As buyer:");
+    locked.line("locked (funded)");
+    to_fund.line("offers awaiting funding");
+    println!("As seller:");
+    incoming.line("pending incoming (funded)");
+    joined.line("joined, not yet funded");
+    println!("As arbiter:");
+    arbitrating.line("funded, awaiting decision");
+    Ok(())
+}
@@ -0,0 +1,183 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::ValueEnum;
+use serde_json::{json, Value};
+use solana_sdk::{message::Message, pubkey::Pubkey, transaction::Transaction};
+use std::str::FromStr;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use crate::account::EscrowState;
+use crate::{confirm_instruction, fund_instruction, Context};
+
+const BLINK_INTERSTITIAL: &str = "https://dial.to/?action=";
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum BlinkStep {
+    /// Buyer deposits the escrow amount
+    Fund,
+    /// Seller confirms and releases the escrow
+    Confirm,
+}
+
+impl BlinkStep {
+    // State the escrow reaches once the step's transaction lands
+    fn completed_state(self) -> EscrowState {
+        match self {
+            BlinkStep::Fund => EscrowState::Funded,
+            BlinkStep::Confirm => EscrowState::Completed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Wallet {
+    Phantom,
+    Solflare,
+}
+
+impl Wallet {
+    // Universal link opening `url` in the wallet's in-app browser. Unlike the
+    // sign* deeplinks this needs no encrypted connect handshake.
+    fn browse_link(self, url: &str, referrer: &str) -> String {
+        let base = match self {
+            Wallet::Phantom => "https://phantom.app/ul/browse/",
+            Wallet::Solflare => "https://solflare.com/ul/v1/browse/",
+        };
+        format!("{}{}?ref={}", base, percent_encode(url), percent_encode(referrer))
+    }
+}
+
+// Emit Solana Actions payloads for a single escrow step: the GET metadata,
+// the POST response carrying the unsigned transaction, and (when the action
+// is hosted somewhere) the solana-action: and blink URLs.
+pub fn blink(
+    ctx: &Context,
+    escrow_account: &str,
+    step: BlinkStep,
+    action_url: Option<&str>,
+    icon: Option<&str>,
+    wallet: Option<Wallet>,
+    wait: Option<u64>,
+) -> Result<()> {
+    let escrow_pubkey = Pubkey::from_str(escrow_account)?;
+    let program_id = ctx.program_id;
+    let escrow = ctx.get_escrow(&escrow_pubkey)?;
+    let amount = escrow.amount;
+    let (required_state, signer, instruction, label, description) = match step {
+        BlinkStep::Fund => (
+            EscrowState::Initialized,
+            escrow.buyer,
+            fund_instruction(&program_id, &escrow.buyer, &escrow_pubkey),
+            format!("Fund {} SOL", amount),
+            format!(
+                "Deposit {} SOL into escrow {}. Funds are held until the seller confirms or the arbiter decides.",
+                amount, escrow_pubkey
+            ),
+        ),
+        BlinkStep::Confirm => (
+            EscrowState::Funded,
+            escrow.seller,
+            confirm_instruction(&program_id, &escrow.seller, &escrow_pubkey),
+            format!("Release {} SOL", amount),
+            format!("Confirm escrow {} and release {} SOL.", escrow_pubkey, amount),
+        ),
+    };
+    if escrow.state != required_state {
+        return Err(anyhow!(
+            "Escrow must be in {:?} state, current state: {:?}",
+            required_state,
+            escrow.state
+        ));
+    }
+    let blockhash = ctx.client
+        .get_latest_blockhash()
+        .map_err(|e| anyhow!("Blockhash error: {}", e))?;
+    let message = Message::new_with_blockhash(&[instruction], Some(&signer), &blockhash);
+    let transaction = Transaction::new_unsigned(message);
+    let encoded = STANDARD.encode(bincode::serialize(&transaction)?);
+
+    let mut action = json!({
+        "type": "action",
+        "title": format!("Escrow {}", escrow_pubkey),
+        "description": description,
+        "label": label,
+    });
+    if let Some(icon) = icon {
+        action["icon"] = json!(icon);
+    }
+    let mut output = json!({
+        "action": action,
+        "post_response": {
+            "transaction": encoded,
+            "message": format!("Sign to {}", label.to_lowercase()),
+        },
+        "signer": signer.to_string(),
+    });
+    if let Some(url) = action_url {
+        let action_link = format!("solana-action:{}", url);
+        let blink = format!("{}{}", BLINK_INTERSTITIAL, percent_encode(&action_link));
+        output["action"]["links"] = json!({ "actions": [{ "label": label, "href": url }] });
+        if let Some(wallet) = wallet {
+            output["wallet_link"] = Value::String(wallet.browse_link(&blink, url));
+        }
+        output["blink"] = Value::String(blink);
+        output["url"] = Value::String(action_link);
+    } else if wallet.is_some() {
+        return Err(anyhow!("--wallet requires --action-url pointing at the hosted action"));
+    }
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    eprintln!("Note: the embedded transaction expires with its blockhash (about a minute).");
+    if let Some(timeout) = wait {
+        wait_for_step(ctx, &escrow_pubkey, step, Duration::from_secs(timeout))?;
+    }
+    Ok(())
+}
+
+// Poll the escrow until the step has landed, then report its signature
+fn wait_for_step(
+    ctx: &Context,
+    escrow_pubkey: &Pubkey,
+    step: BlinkStep,
+    timeout: Duration,
+) -> Result<()> {
+    let target = step.completed_state();
+    let started = Instant::now();
+    eprintln!("Waiting up to {}s for the escrow to become {:?}...", timeout.as_secs(), target);
+    loop {
+        // The counterparty's step never passes through this process's cache
+        let escrow = match ctx.refresh_escrow(escrow_pubkey)? {
+            (_, Some((escrow, _))) => escrow,
+            (_, None) => return Err(anyhow!("Escrow {} was closed", escrow_pubkey)),
+        };
+        if escrow.state == target {
+            let signatures = ctx.client.get_signatures_for_address(escrow_pubkey)?;
+            match signatures.first() {
+                Some(latest) => println!("Escrow is {:?}! Signature: {}", target, latest.signature),
+                None => println!("Escrow is {:?}!", target),
+            }
+            return Ok(());
+        }
+        if started.elapsed() >= timeout {
+            return Err(anyhow!(
+                "Timed out waiting for {:?} state, current state: {:?}",
+                target,
+                escrow.state
+            ));
+        }
+        sleep(POLL_INTERVAL);
+    }
+}
+
+fn percent_encode(input: &str) -> String {
+    input
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
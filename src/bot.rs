@@ -0,0 +1,170 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use solana_sdk::{pubkey::Pubkey, signature::Signer};
+use std::collections::HashSet;
+use std::fs;
+use std::str::FromStr;
+use std::thread::sleep;
+use std::time::Duration;
+
+use crate::account::EscrowAccount;
+use crate::amount::{parse_amount, Lamports};
+use crate::search::open_offers;
+use crate::{hooks, join_offer, Context};
+
+fn default_interval() -> u64 {
+    30
+}
+
+// Which open offers `bot match` joins, from its own TOML file
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct BotConfig {
+    pub seller_keypair: String,
+    // Amounts in lamports or with a SOL suffix
+    pub min_amount: Option<String>,
+    pub max_amount: Option<String>,
+    // Only offers naming one of these arbiters are joined
+    pub arbiters: Vec<String>,
+    // Seconds between scans
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+    // Stop after joining this many offers
+    pub max_joins: Option<usize>,
+}
+
+// BotConfig with its fields parsed
+struct Criteria {
+    min_amount: Option<Lamports>,
+    max_amount: Option<Lamports>,
+    arbiters: HashSet<Pubkey>,
+}
+
+impl BotConfig {
+    pub fn load(path: &str) -> Result<BotConfig> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| anyhow!("Failed to read {}: {}", path, e))?;
+        toml::from_str(&contents).map_err(|e| anyhow!("Invalid bot config {}: {}", path, e))
+    }
+
+    fn criteria(&self) -> Result<Criteria> {
+        let parse = |value: &Option<String>| -> Result<Option<Lamports>> {
+            value
+                .as_deref()
+                .map(|amount| parse_amount(amount).map_err(|e| anyhow!("{}", e)))
+                .transpose()
+        };
+        if self.arbiters.is_empty() {
+            return Err(anyhow!(
+                "The bot config needs at least one arbiter in `arbiters`"
+            ));
+        }
+        let arbiters = self
+            .arbiters
+            .iter()
+            .map(|arbiter| {
+                Pubkey::from_str(arbiter).map_err(|_| anyhow!("Invalid arbiter '{}'", arbiter))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Criteria {
+            min_amount: parse(&self.min_amount)?,
+            max_amount: parse(&self.max_amount)?,
+            arbiters,
+        })
+    }
+}
+
+impl Criteria {
+    fn matches(&self, escrow: &EscrowAccount) -> bool {
+        self.arbiters.contains(&escrow.arbiter)
+            && self.min_amount.map_or(true, |min| escrow.amount >= min)
+            && self.max_amount.map_or(true, |max| escrow.amount <= max)
+    }
+}
+
+// Scan for Created offers matching the bot config, join each as the seller
+// and report it through the notify hook. Runs until `max_joins` is reached,
+// or for a single scan with `once`. A scan that fails on an RPC error is
+// reported and tried again after the interval.
+pub fn run(ctx: &Context, path: &str, once: bool) -> Result<()> {
+    let bot = BotConfig::load(path)?;
+    let criteria = bot.criteria()?;
+    let seller = ctx.read_keypair(&bot.seller_keypair, "seller")?.pubkey();
+    // Offers already tried, joined or not, so a failing one isn't retried
+    // every scan; an unfundable one is looked at again next time
+    let mut seen: HashSet<Pubkey> = HashSet::new();
+    let mut joined = 0;
+    println!(
+        "Matching offers for seller {} every {}s (Ctrl-C to stop)",
+        seller, bot.interval
+    );
+    loop {
+        match scan(ctx, &bot, &criteria, &seller, &mut seen, &mut joined) {
+            Ok(true) => {
+                println!("Joined {} offers, stopping", joined);
+                return Ok(());
+            }
+            Ok(false) => {}
+            Err(e) if once => return Err(e),
+            Err(e) => eprintln!("Warning: scan failed, retrying in {}s: {}", bot.interval, e),
+        }
+        if once {
+            return Ok(());
+        }
+        sleep(Duration::from_secs(bot.interval));
+    }
+}
+
+// One pass over the open offers; true once `max_joins` is reached
+fn scan(
+    ctx: &Context,
+    bot: &BotConfig,
+    criteria: &Criteria,
+    seller: &Pubkey,
+    seen: &mut HashSet<Pubkey>,
+    joined: &mut usize,
+) -> Result<bool> {
+    for (escrow_pubkey, escrow) in open_offers(ctx)? {
+        if escrow.buyer == *seller || !criteria.matches(&escrow) || seen.contains(&escrow_pubkey) {
+            continue;
+        }
+        // Liquidity is only worth committing to offers the buyer can fund
+        let buyer_balance = ctx.client.get_balance(&escrow.buyer)?;
+        if Lamports::new(buyer_balance) < escrow.amount {
+            println!(
+                "Skipping {}: the buyer can't fund {} SOL",
+                escrow_pubkey, escrow.amount
+            );
+            continue;
+        }
+        seen.insert(escrow_pubkey);
+        println!(
+            "Joining {} ({} SOL, arbiter {})",
+            escrow_pubkey, escrow.amount, escrow.arbiter
+        );
+        let result = join_offer(ctx, &bot.seller_keypair, &escrow_pubkey.to_string());
+        if let Err(e) = &result {
+            eprintln!("Failed to join {}: {}", escrow_pubkey, e);
+        }
+        hooks::notify(
+            &ctx.config,
+            &json!({
+                "event": if result.is_ok() { "bot_joined" } else { "bot_join_failed" },
+                "escrow": escrow_pubkey.to_string(),
+                "buyer": escrow.buyer.to_string(),
+                "seller": seller.to_string(),
+                "arbiter": escrow.arbiter.to_string(),
+                "amount": escrow.amount.lamports(),
+                "error": result.as_ref().err().map(|e| e.to_string()),
+            }),
+        );
+        if result.is_ok() {
+            *joined += 1;
+            if bot.max_joins.is_some_and(|max| *joined >= max) {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
@@ -0,0 +1,81 @@
+use anyhow::{anyhow, Result};
+use solana_sdk::{
+    instruction::Instruction, message::Message, pubkey::Pubkey, signature::Signature,
+    signer::signers::Signers, transaction::Transaction,
+};
+
+use crate::{simulate_and_send, Context};
+
+// A command's transaction between its build and execute phases: the message
+// with a recent blockhash, the signatures collected so far, and what it is
+// for. Building needs only the signers' pubkeys, so any signer backend can
+// fill in its part with `sign` before `execute` sends it.
+pub struct PartiallySignedTx {
+    pub transaction: Transaction,
+    pub escrow: Pubkey,
+    // Action name recorded in the ledger and shown in previews, e.g. "fund_escrow"
+    pub action: &'static str,
+}
+
+impl PartiallySignedTx {
+    pub fn new(
+        ctx: &Context,
+        instructions: &[Instruction],
+        payer: &Pubkey,
+        escrow: &Pubkey,
+        action: &'static str,
+    ) -> Result<PartiallySignedTx> {
+        let mut message = Message::new(instructions, Some(payer));
+        message.recent_blockhash = ctx
+            .client
+            .get_latest_blockhash()
+            .map_err(|e| anyhow!("Blockhash error: {}", e))?;
+        Ok(PartiallySignedTx {
+            transaction: Transaction::new_unsigned(message),
+            escrow: *escrow,
+            action,
+        })
+    }
+
+    // Every account that must sign, fee payer first
+    pub fn required_signers(&self) -> &[Pubkey] {
+        let message = &self.transaction.message;
+        &message.account_keys[..message.header.num_required_signatures as usize]
+    }
+
+    // Required signers whose signature is still missing
+    pub fn missing_signers(&self) -> Vec<Pubkey> {
+        self.required_signers()
+            .iter()
+            .zip(&self.transaction.signatures)
+            .filter(|(_, signature)| **signature == Signature::default())
+            .map(|(signer, _)| *signer)
+            .collect()
+    }
+
+    // Add signatures; each signer must be one of the required signers
+    pub fn sign<T: Signers + ?Sized>(&mut self, signers: &T) -> Result<()> {
+        let blockhash = self.transaction.message.recent_blockhash;
+        self.transaction
+            .try_partial_sign(signers, blockhash)
+            .map_err(|e| anyhow!("Failed to sign {}: {}", self.action, e))
+    }
+
+    // Simulate and send once every required signer has signed; `None` after
+    // a dry run
+    pub fn execute(&self, ctx: &Context) -> Result<Option<Signature>> {
+        let missing = self.missing_signers();
+        if !missing.is_empty() {
+            return Err(anyhow!(
+                "{} is missing signatures from {}",
+                self.action,
+                missing
+                    .iter()
+                    .map(Pubkey::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        simulate_and_send(ctx, &self.transaction, &self.escrow, self.action)
+    }
+}
@@ -0,0 +1,69 @@
+use anyhow::Result;
+use solana_sdk::pubkey::Pubkey;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use crate::account::EscrowAccount;
+
+struct Cached {
+    // Slot of the RPC response the account was read at
+    slot: u64,
+    data: Vec<u8>,
+    escrow: EscrowAccount,
+}
+
+// Decoded escrow accounts by address, with the slot each was read at. An
+// entry older than the context's minimum slot is stale: a transaction this
+// process sent since may have changed the account.
+#[derive(Default)]
+pub struct EscrowCache {
+    entries: RefCell<HashMap<Pubkey, Cached>>,
+}
+
+impl EscrowCache {
+    // The cached account, if it was read at or after `min_slot`
+    pub fn get(&self, pubkey: &Pubkey, min_slot: u64) -> Option<EscrowAccount> {
+        self.entries
+            .borrow()
+            .get(pubkey)
+            .filter(|cached| cached.slot >= min_slot)
+            .map(|cached| cached.escrow.clone())
+    }
+
+    // Slot of the newest read, so a poll never accepts an older view from a
+    // lagging node
+    pub fn slot(&self, pubkey: &Pubkey) -> Option<u64> {
+        self.entries.borrow().get(pubkey).map(|cached| cached.slot)
+    }
+
+    // Store a fresh read, decoding only when the bytes changed; returns the
+    // account and whether it differs from the previous read
+    pub fn update(
+        &self,
+        pubkey: &Pubkey,
+        slot: u64,
+        data: Vec<u8>,
+    ) -> Result<(EscrowAccount, bool)> {
+        let mut entries = self.entries.borrow_mut();
+        if let Some(cached) = entries.get_mut(pubkey) {
+            if cached.data == data {
+                cached.slot = cached.slot.max(slot);
+                return Ok((cached.escrow.clone(), false));
+            }
+        }
+        let escrow = EscrowAccount::unpack(&data)?;
+        entries.insert(
+            *pubkey,
+            Cached {
+                slot,
+                data,
+                escrow: escrow.clone(),
+            },
+        );
+        Ok((escrow, true))
+    }
+
+    pub fn remove(&self, pubkey: &Pubkey) {
+        self.entries.borrow_mut().remove(pubkey);
+    }
+}
@@ -0,0 +1,243 @@
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use solana_client::client_error::ClientErrorKind;
+use solana_client::rpc_request::{RpcError, RpcRequest};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread::sleep;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::Context;
+
+// How far behind a "stale" node reports itself
+const STALE_LAG: u64 = 150;
+// JSON-RPC code a node returns when it hasn't reached minContextSlot
+const MIN_CONTEXT_SLOT_NOT_REACHED: i64 = -32016;
+const MAX_REQUEST_BYTES: usize = 4 * 1024 * 1024;
+
+// Faults to inject; rates are per request, from 0 to 1
+#[derive(Debug, Clone, Copy)]
+pub struct Faults {
+    // Up to this much added delay before each request is forwarded
+    pub latency: Duration,
+    // Answer with HTTP 429 instead of forwarding
+    pub rate_limit: f64,
+    // Blank out getSignatureStatuses results, as if the transaction hadn't
+    // been seen yet
+    pub drop_confirmations: f64,
+    // Answer as a node STALE_LAG slots behind
+    pub stale_slots: f64,
+}
+
+// clap value parser for the fault rates
+pub fn parse_rate(input: &str) -> Result<f64, String> {
+    match input.trim().parse::<f64>() {
+        Ok(rate) if (0.0..=1.0).contains(&rate) => Ok(rate),
+        _ => Err(format!(
+            "Invalid rate '{}': expected 0 to 1, e.g. 0.2",
+            input
+        )),
+    }
+}
+
+// xorshift64*; the proxy only needs repeatable coin flips, not security
+struct Rng(u64);
+
+impl Rng {
+    // Uniform in [0, 1)
+    fn sample(&mut self) -> f64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn chance(&mut self, rate: f64) -> bool {
+        self.sample() < rate
+    }
+}
+
+struct Proxy<'a> {
+    ctx: &'a Context,
+    faults: Faults,
+    rng: Rng,
+    // RpcRequest::Custom wants a 'static method name; each distinct name is
+    // leaked once
+    methods: HashMap<String, &'static str>,
+}
+
+// Serve JSON-RPC on `listen`, forwarding to the configured RPC node and
+// injecting `faults`, so retry and confirmation handling can be exercised by
+// pointing another profile's rpc_url at the proxy. Requests are handled one
+// at a time.
+pub fn chaos_proxy(ctx: &Context, listen: &str, faults: Faults, seed: Option<u64>) -> Result<()> {
+    let listener =
+        TcpListener::bind(listen).map_err(|e| anyhow!("Failed to listen on {}: {}", listen, e))?;
+    let seed = match seed {
+        Some(seed) => seed,
+        None => SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos() as u64,
+    };
+    println!(
+        "Chaos proxy on http://{} -> {} (seed {})",
+        listener.local_addr()?,
+        ctx.client.url(),
+        seed
+    );
+    let mut proxy = Proxy {
+        ctx,
+        faults,
+        rng: Rng(seed | 1),
+        methods: HashMap::new(),
+    };
+    for stream in listener.incoming() {
+        let result = stream
+            .map_err(anyhow::Error::from)
+            .and_then(|stream| proxy.serve(stream));
+        if let Err(e) = result {
+            eprintln!("Warning: {}", e);
+        }
+    }
+    Ok(())
+}
+
+impl Proxy<'_> {
+    fn serve(&mut self, mut stream: TcpStream) -> Result<()> {
+        let body = read_request(&mut stream)?;
+        let request: Value = serde_json::from_slice(&body)
+            .map_err(|e| anyhow!("Invalid JSON-RPC request: {}", e))?;
+        sleep(self.faults.latency.mul_f64(self.rng.sample()));
+        if self.rng.chance(self.faults.rate_limit) {
+            println!("{}: 429", describe(&request));
+            let error = json!({
+                "jsonrpc": "2.0",
+                "error": {"code": 429, "message": "Too many requests"},
+                "id": request.get("id").cloned().unwrap_or(Value::Null),
+            });
+            return write_response(&mut stream, "429 Too Many Requests", &error);
+        }
+        let response = match request {
+            Value::Array(batch) => Value::Array(batch.iter().map(|call| self.call(call)).collect()),
+            call => self.call(&call),
+        };
+        write_response(&mut stream, "200 OK", &response)
+    }
+
+    // Forward one JSON-RPC call and return its response object
+    fn call(&mut self, call: &Value) -> Value {
+        let id = call.get("id").cloned().unwrap_or(Value::Null);
+        let method = call["method"].as_str().unwrap_or_default().to_string();
+        let params = call.get("params").cloned().unwrap_or(Value::Null);
+        let min_context_slot = params
+            .as_array()
+            .and_then(|params| params.last())
+            .and_then(|options| options["minContextSlot"].as_u64());
+        let stale = self.rng.chance(self.faults.stale_slots);
+        if let (true, Some(slot)) = (stale, min_context_slot) {
+            println!("{}: minimum context slot not reached", method);
+            return json!({
+                "jsonrpc": "2.0",
+                "error": {
+                    "code": MIN_CONTEXT_SLOT_NOT_REACHED,
+                    "message": "Minimum context slot has not been reached",
+                    "data": {"contextSlot": slot.saturating_sub(STALE_LAG)},
+                },
+                "id": id,
+            });
+        }
+        let name = *self.methods.entry(method.clone()).or_insert_with(|| {
+            let leaked: &'static str = Box::leak(method.clone().into_boxed_str());
+            leaked
+        });
+        let mut result = match self
+            .ctx
+            .client
+            .send::<Value>(RpcRequest::Custom { method: name }, params)
+        {
+            Ok(result) => result,
+            Err(e) => {
+                let (code, message) = match e.kind() {
+                    ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                        code, message, ..
+                    }) => (*code, message.clone()),
+                    kind => (-32603, kind.to_string()),
+                };
+                println!("{}: error {}", method, code);
+                return json!({
+                    "jsonrpc": "2.0",
+                    "error": {"code": code, "message": message},
+                    "id": id,
+                });
+            }
+        };
+        let mut faults = Vec::new();
+        if stale {
+            if let Some(slot) = result.pointer_mut("/context/slot") {
+                *slot = json!(slot.as_u64().unwrap_or_default().saturating_sub(STALE_LAG));
+                faults.push("stale slot");
+            }
+        }
+        if method == "getSignatureStatuses" && self.rng.chance(self.faults.drop_confirmations) {
+            if let Some(Value::Array(statuses)) = result.get_mut("value") {
+                statuses.iter_mut().for_each(|status| *status = Value::Null);
+                faults.push("dropped confirmations");
+            }
+        }
+        if faults.is_empty() {
+            println!("{}", method);
+        } else {
+            println!("{}: {}", method, faults.join(", "));
+        }
+        json!({"jsonrpc": "2.0", "result": result, "id": id})
+    }
+}
+
+fn describe(request: &Value) -> String {
+    match request {
+        Value::Array(batch) => format!("batch of {}", batch.len()),
+        call => call["method"].as_str().unwrap_or("?").to_string(),
+    }
+}
+
+// The body of one HTTP/1.1 request; only Content-Length bodies are accepted
+fn read_request(stream: &mut TcpStream) -> Result<Vec<u8>> {
+    let mut reader = BufReader::new(stream);
+    let mut length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(anyhow!("Connection closed mid-request"));
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid Content-Length '{}'", value.trim()))?;
+            }
+        }
+    }
+    if length > MAX_REQUEST_BYTES {
+        return Err(anyhow!("Request of {} bytes is too large", length));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(body)
+}
+
+fn write_response(stream: &mut TcpStream, status: &str, body: &Value) -> Result<()> {
+    let body = serde_json::to_vec(body)?;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n",
+        status,
+        body.len()
+    )?;
+    stream.write_all(&body)?;
+    Ok(())
+}
@@ -0,0 +1,297 @@
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use serde_json::Value;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::account::{EscrowAccount, EscrowState};
+use crate::amount::Lamports;
+use crate::pipe::read_input;
+use crate::vectors::{account_vector, canonical, instruction_vector, VECTORS_VERSION};
+use crate::{
+    arbiter_instruction, close_instruction, confirm_instruction, create_offer_instruction,
+    find_vault_pda, fund_instruction, join_instruction, mutual_cancel_instruction, ArbiterDecision,
+};
+
+// `test-vectors` output of each release, built into the binary so
+// `--against v0.1.0` works from any directory. v0.1.0 predates the
+// `test-vectors` command; its file was produced outside the CLI from that
+// release's instruction and account encodings.
+const RELEASES: [(&str, &str); 1] = [("v0.1.0", include_str!("../compat/v0.1.0.json"))];
+
+// A vectors file, or the one recorded for a release name
+fn recorded(against: &str) -> Result<(String, Vec<u8>)> {
+    if Path::new(against).is_file() || against == "-" {
+        return Ok((against.to_string(), read_input(against)?));
+    }
+    RELEASES
+        .iter()
+        .find(|(release, _)| *release == against)
+        .map(|(release, contents)| (release.to_string(), contents.as_bytes().to_vec()))
+        .ok_or_else(|| {
+            anyhow!(
+                "No vectors recorded for '{}' (recorded: {}); pass a file written by that \
+                 release's `escrow-cli test-vectors -o FILE` instead",
+                against,
+                RELEASES
+                    .iter()
+                    .map(|(release, _)| *release)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })
+}
+
+// `accounts` or `instructions` entries keyed by what they encode
+fn entries<'a>(vectors: &'a Value, list: &str, key: &str) -> Vec<(String, &'a Value)> {
+    vectors[list]
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .map(|item| (item.pointer(key).map(label).unwrap_or_default(), item))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn label(value: &Value) -> String {
+    value
+        .as_str()
+        .map(str::to_string)
+        .unwrap_or_else(|| value.to_string())
+}
+
+fn pubkey(value: &Value, field: &str) -> Result<Pubkey> {
+    value[field]
+        .as_str()
+        .and_then(|key| Pubkey::from_str(key).ok())
+        .ok_or_else(|| anyhow!("missing or invalid `{}`", field))
+}
+
+fn number(value: &Value, field: &str) -> Result<u64> {
+    value[field]
+        .as_u64()
+        .ok_or_else(|| anyhow!("missing or invalid `{}`", field))
+}
+
+// Build a recorded instruction again from its recorded parameters; None for
+// an instruction this build doesn't know
+fn rebuild_instruction(
+    program_id: &Pubkey,
+    name: &str,
+    params: &Value,
+) -> Result<Option<Instruction>> {
+    let key = |field| pubkey(params, field);
+    let instruction = match name {
+        "create_offer" => create_offer_instruction(
+            program_id,
+            &key("buyer")?,
+            &key("escrow")?,
+            &key("arbiter")?,
+            Lamports::new(number(params, "amount")?),
+        ),
+        "join_offer" => join_instruction(program_id, &key("seller")?, &key("escrow")?),
+        "fund_escrow" => fund_instruction(program_id, &key("buyer")?, &key("escrow")?),
+        "confirm" => confirm_instruction(program_id, &key("seller")?, &key("escrow")?),
+        "arbiter_confirm" => arbiter_instruction(
+            program_id,
+            ArbiterDecision::Release,
+            &key("arbiter")?,
+            &key("escrow")?,
+            &key("seller")?,
+        ),
+        "arbiter_cancel" => arbiter_instruction(
+            program_id,
+            ArbiterDecision::Refund,
+            &key("arbiter")?,
+            &key("escrow")?,
+            &key("buyer")?,
+        ),
+        "close_escrow" => close_instruction(program_id, &key("closer")?, &key("escrow")?),
+        "mutual_cancel" => {
+            mutual_cancel_instruction(program_id, &key("buyer")?, &key("seller")?, &key("escrow")?)
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(instruction))
+}
+
+// The escrow account a recorded `decoded` object describes
+fn rebuild_account(decoded: &Value) -> Result<EscrowAccount> {
+    let state = decoded["state"]
+        .as_str()
+        .and_then(|state| EscrowState::from_str(state, true).ok())
+        .ok_or_else(|| anyhow!("missing or invalid `state`"))?;
+    Ok(EscrowAccount {
+        buyer: pubkey(decoded, "buyer")?,
+        seller: pubkey(decoded, "seller")?,
+        arbiter: pubkey(decoded, "arbiter")?,
+        amount: Lamports::new(number(decoded, "amount")?),
+        state,
+        vault_bump: u8::try_from(number(decoded, "vault_bump")?)?,
+    })
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if text.len() % 2 != 0 {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+// Rebuild every recorded instruction and account from the recorded inputs
+// and program id, and fail on any encoding that no longer matches: an escrow
+// created by that release must still be readable, and its users' peers must
+// still be able to act on it with this build
+pub fn check(against: &str) -> Result<()> {
+    let (source, recorded) = recorded(against)?;
+    let recorded: Value = serde_json::from_slice(&recorded)
+        .map_err(|e| anyhow!("Invalid vectors file {}: {}", source, e))?;
+    if recorded["version"].as_u64() != Some(VECTORS_VERSION as u64) {
+        return Err(anyhow!(
+            "{} has vectors format {}, this build reads format {}",
+            source,
+            recorded["version"],
+            VECTORS_VERSION
+        ));
+    }
+    let program_id = recorded["program_id"]
+        .as_str()
+        .and_then(|id| Pubkey::from_str(id).ok())
+        .ok_or_else(|| anyhow!("{} has no valid program_id", source))?;
+    println!(
+        "Comparing against {} (escrow-cli {})",
+        source,
+        label(&recorded["cli_version"])
+    );
+
+    let mut breaking = Vec::new();
+    let vault = &recorded["vault_pda"];
+    let (address, bump) = find_vault_pda(&pubkey(vault, "escrow")?, &program_id);
+    if vault["address"].as_str() != Some(address.to_string().as_str())
+        || vault["bump"].as_u64() != Some(bump as u64)
+    {
+        breaking.push(format!(
+            "vault PDA: {} bump {} -> {} bump {}",
+            label(&vault["address"]),
+            vault["bump"],
+            address,
+            bump
+        ));
+    }
+
+    let recorded_accounts = entries(&recorded, "accounts", "/decoded/state");
+    for (state, account) in &recorded_accounts {
+        // Writing: the recorded fields must pack to the recorded bytes
+        let packed =
+            rebuild_account(&account["decoded"]).and_then(|escrow| account_vector(&escrow));
+        match packed {
+            Err(e) => breaking.push(format!(
+                "account in state {}: no longer encoded: {}",
+                state, e
+            )),
+            Ok(now) if now["data_hex"] != account["data_hex"] => breaking.push(format!(
+                "account in state {}: {} -> {}",
+                state,
+                label(&account["data_hex"]),
+                label(&now["data_hex"])
+            )),
+            Ok(_) => {}
+        }
+        // Reading: the recorded bytes must decode to the recorded fields
+        let decoded = account["data_hex"]
+            .as_str()
+            .and_then(from_hex)
+            .and_then(|data| EscrowAccount::unpack(&data).ok());
+        match decoded {
+            Some(escrow) if escrow.to_json() == account["decoded"] => {}
+            _ => breaking.push(format!("account in state {}: decodes differently", state)),
+        }
+    }
+
+    let recorded_instructions = entries(&recorded, "instructions", "/instruction");
+    for (name, instruction) in &recorded_instructions {
+        let instruction_program = instruction["program_id"]
+            .as_str()
+            .and_then(|id| Pubkey::from_str(id).ok())
+            .unwrap_or(program_id);
+        let params = &instruction["params"];
+        let now = match rebuild_instruction(&instruction_program, name, params) {
+            Ok(Some(built)) => instruction_vector(name, params.clone(), &built),
+            Ok(None) => {
+                breaking.push(format!("{}: removed", name));
+                continue;
+            }
+            Err(e) => {
+                breaking.push(format!(
+                    "{}: recorded params no longer accepted: {}",
+                    name, e
+                ));
+                continue;
+            }
+        };
+        if now["data_hex"] != instruction["data_hex"] {
+            breaking.push(format!(
+                "{}: data {} -> {}",
+                name,
+                label(&instruction["data_hex"]),
+                label(&now["data_hex"])
+            ));
+        }
+        if now["accounts"] != instruction["accounts"] {
+            breaking.push(format!("{}: account list or flags changed", name));
+        }
+    }
+    for (name, _) in &entries(&canonical(&program_id)?, "instructions", "/instruction") {
+        if !recorded_instructions.iter().any(|(other, _)| other == name) {
+            println!("  new: {}", name);
+        }
+    }
+
+    if breaking.is_empty() {
+        println!(
+            "All {} instructions and {} account states encode as recorded",
+            recorded_instructions.len(),
+            recorded_accounts.len()
+        );
+        return Ok(());
+    }
+    for difference in &breaking {
+        println!("  BREAKING {}", difference);
+    }
+    Err(anyhow!(
+        "{} encoding change(s) since {}",
+        breaking.len(),
+        source
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recorded_releases_still_match() {
+        for (release, _) in RELEASES {
+            check(release).unwrap();
+        }
+    }
+
+    #[test]
+    fn current_vectors_match_themselves() {
+        let program_id = Pubkey::new_unique();
+        let vectors = canonical(&program_id).unwrap();
+        for (name, instruction) in entries(&vectors, "instructions", "/instruction") {
+            let rebuilt = rebuild_instruction(&program_id, &name, &instruction["params"])
+                .unwrap()
+                .unwrap();
+            let now = instruction_vector(&name, instruction["params"].clone(), &rebuilt);
+            assert_eq!(&now, instruction, "{}", name);
+        }
+    }
+}
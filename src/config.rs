@@ -0,0 +1,412 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::account::ESCROW_ACCOUNT_SIZE;
+use crate::hooks::Hooks;
+use crate::rpc;
+use crate::safety::Safety;
+use crate::sandbox::Sandbox;
+use crate::sla::Sla;
+use crate::vacation::Vacation;
+use crate::storage::Storage;
+use crate::totp::Totp;
+
+const CONFIG_ENV: &str = "ESCROW_CLI_CONFIG";
+const PROFILE_ENV: &str = "ESCROW_CLI_PROFILE";
+const REDACTED: &str = "<redacted>";
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    // An http(s) URL, or "auto" to pick among rpc_endpoints
+    pub rpc_url: Option<String>,
+    // Endpoints `rpc probe` measures and `rpc_url = "auto"` chooses from
+    pub rpc_endpoints: Vec<String>,
+    pub program_id: Option<String>,
+    // Directory that bare keypair names (e.g. `buyer.json`) are resolved in
+    pub keystore: Option<String>,
+    pub default_profile: Option<String>,
+    pub profiles: BTreeMap<String, Profile>,
+    // Disable every signing and sending code path
+    pub read_only: bool,
+    pub hooks: Hooks,
+    pub storage: Storage,
+    // JSON file recording costs and metadata per escrow
+    // (defaults to ~/.config/escrow-cli/ledger.json)
+    pub ledger: Option<String>,
+    // Profile whose escrows go to their own copy of `ledger`, e.g.
+    // ledger-mainnet.json; set by apply_profile when the profile has no
+    // `ledger` of its own
+    #[serde(skip)]
+    pub ledger_profile: Option<String>,
+    // Named memo texts arbiters attach to their decisions
+    pub verdicts: BTreeMap<String, String>,
+    // Escrow account size in bytes for program deployments whose layout
+    // extends the base 106 bytes (deadline, fee, milestones, ...)
+    pub account_size: Option<usize>,
+    pub safety: Safety,
+    pub sandbox: Sandbox,
+    // When `info` and `list` flag escrows waiting in one state
+    pub sla: Sla,
+    // Two-factor codes before releasing or cancelling large escrows
+    pub totp: Totp,
+    // Arbiter absence announced by `arbiter-away`
+    pub vacation: Vacation,
+    // `<program>@<genesis hash>` -> upgrade authority (or "immutable") the
+    // user confirmed; written by the trust prompt
+    pub trusted_programs: BTreeMap<String, String>,
+    // Short names for commands, e.g. `co = "create-offer"`; built-in command
+    // names take precedence
+    pub aliases: BTreeMap<String, String>,
+    // Service answering party lookups when the RPC node refuses
+    // getProgramAccounts: GET <url>?program=<id>&party=<pubkey> returning a
+    // JSON array of escrow addresses
+    pub indexer_url: Option<String>,
+    // Enhanced transaction history endpoint (Helius-style) used by `history`;
+    // `{address}` is replaced with the escrow address
+    pub history_api: Option<String>,
+    // Minisign public key release binaries are signed with; `self-update`
+    // refuses to run without it
+    pub release_public_key: Option<String>,
+}
+
+// Named set of overrides, e.g. [profiles.mainnet]
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub rpc_url: Option<String>,
+    pub rpc_endpoints: Vec<String>,
+    pub program_id: Option<String>,
+    pub keystore: Option<String>,
+    pub read_only: bool,
+    pub account_size: Option<usize>,
+    pub indexer_url: Option<String>,
+    pub history_api: Option<String>,
+    // Defaults to `ledger` with the profile name appended
+    pub ledger: Option<String>,
+}
+
+impl Config {
+    // Load config from an explicit path, $ESCROW_CLI_CONFIG, or the default
+    // location. A missing default config is not an error.
+    pub fn load(path: Option<&str>) -> Result<Config> {
+        let explicit = path
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os(CONFIG_ENV).map(PathBuf::from));
+        let path = match explicit {
+            Some(path) => path,
+            None => match default_config_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Config::default()),
+            },
+        };
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| anyhow!("Failed to read config {}: {}", path.display(), e))?;
+        Config::parse(&contents)
+            .map_err(|e| anyhow!("Invalid config {}: {}", path.display(), e))
+    }
+
+    fn parse(contents: &str) -> Result<Config> {
+        let config: Config = toml::from_str(contents)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    // Catch values that deserialize fine but would only fail later at runtime
+    fn validate(&self) -> Result<()> {
+        check_rpc_url("rpc_url", self.rpc_url.as_deref())?;
+        check_rpc_endpoints("rpc_endpoints", &self.rpc_endpoints)?;
+        check_program_id("program_id", self.program_id.as_deref())?;
+        check_account_size("account_size", self.account_size)?;
+        check_rpc_url("indexer_url", self.indexer_url.as_deref())?;
+        check_rpc_url("history_api", self.history_api.as_deref())?;
+        self.safety.validate()?;
+        self.vacation.validate()?;
+        self.sla.validate()?;
+        self.totp.validate()?;
+        self.hooks.validate()?;
+        for (name, profile) in &self.profiles {
+            check_rpc_url(&format!("profiles.{}.rpc_url", name), profile.rpc_url.as_deref())?;
+            check_rpc_endpoints(
+                &format!("profiles.{}.rpc_endpoints", name),
+                &profile.rpc_endpoints,
+            )?;
+            check_program_id(
+                &format!("profiles.{}.program_id", name),
+                profile.program_id.as_deref(),
+            )?;
+            check_account_size(
+                &format!("profiles.{}.account_size", name),
+                profile.account_size,
+            )?;
+            check_rpc_url(
+                &format!("profiles.{}.indexer_url", name),
+                profile.indexer_url.as_deref(),
+            )?;
+            check_rpc_url(
+                &format!("profiles.{}.history_api", name),
+                profile.history_api.as_deref(),
+            )?;
+        }
+        if let Some(name) = &self.default_profile {
+            if !self.profiles.contains_key(name) {
+                return Err(anyhow!(
+                    "default_profile '{}' has no [profiles.{}] table",
+                    name,
+                    name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // Apply the selected profile (--profile, $ESCROW_CLI_PROFILE or
+    // default_profile) on top of the top-level settings
+    pub fn apply_profile(&mut self, name: Option<&str>) -> Result<()> {
+        let name = name
+            .map(str::to_string)
+            .or_else(|| std::env::var(PROFILE_ENV).ok())
+            .or_else(|| self.default_profile.clone());
+        let Some(name) = name else {
+            return Ok(());
+        };
+        let profile = self.profiles.get(&name).cloned().ok_or_else(|| {
+            anyhow!(
+                "Unknown profile '{}', configured profiles: {}",
+                name,
+                self.profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+            )
+        })?;
+        self.rpc_url = profile.rpc_url.or(self.rpc_url.take());
+        if !profile.rpc_endpoints.is_empty() {
+            self.rpc_endpoints = profile.rpc_endpoints;
+        }
+        self.program_id = profile.program_id.or(self.program_id.take());
+        self.keystore = profile.keystore.or(self.keystore.take());
+        self.read_only |= profile.read_only;
+        self.account_size = profile.account_size.or(self.account_size);
+        self.indexer_url = profile.indexer_url.or(self.indexer_url.take());
+        self.history_api = profile.history_api.or(self.history_api.take());
+        match profile.ledger {
+            Some(ledger) => self.ledger = Some(ledger),
+            None => self.ledger_profile = Some(name),
+        }
+        Ok(())
+    }
+
+    // Keypair paths that don't exist as given are looked up in the keystore
+    pub fn resolve_keypair_path(&self, path: &str) -> PathBuf {
+        let direct = expand_home(path);
+        match &self.keystore {
+            Some(keystore) if !direct.exists() => expand_home(keystore).join(path),
+            _ => direct,
+        }
+    }
+}
+
+pub fn check_rpc_url(field: &str, value: Option<&str>) -> Result<()> {
+    match value {
+        Some(rpc::AUTO) if field.ends_with("rpc_url") => Ok(()),
+        Some(url) if !url.starts_with("http://") && !url.starts_with("https://") => Err(anyhow!(
+            "{}: expected an http(s) URL, got '{}'",
+            field,
+            url
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn check_rpc_endpoints(field: &str, endpoints: &[String]) -> Result<()> {
+    for (index, url) in endpoints.iter().enumerate() {
+        check_rpc_url(&format!("{}[{}]", field, index), Some(url))?;
+    }
+    Ok(())
+}
+
+fn check_program_id(field: &str, value: Option<&str>) -> Result<()> {
+    match value {
+        Some(id) if Pubkey::from_str(id).is_err() => {
+            Err(anyhow!("{}: '{}' is not a valid base58 pubkey", field, id))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn check_account_size(field: &str, value: Option<usize>) -> Result<()> {
+    match value {
+        Some(size) if size < ESCROW_ACCOUNT_SIZE => Err(anyhow!(
+            "{}: {} bytes is smaller than the base escrow layout ({} bytes)",
+            field,
+            size,
+            ESCROW_ACCOUNT_SIZE
+        )),
+        _ => Ok(()),
+    }
+}
+
+// Print the config file with tokens and URL credentials redacted
+pub fn show(path: Option<&str>) -> Result<()> {
+    let path = config_path(path)?;
+    if !path.exists() {
+        println!("No config file at {}", path.display());
+        return Ok(());
+    }
+    let contents = fs::read_to_string(&path)?;
+    let mut table: toml::Table = toml::from_str(&contents)
+        .map_err(|e| anyhow!("Invalid config {}: {}", path.display(), e))?;
+    redact_table(&mut table);
+    println!("# {}", path.display());
+    print!("{}", toml::to_string_pretty(&table)?);
+    if let Err(e) = Config::parse(&contents) {
+        eprintln!("Warning: config is invalid: {}", e);
+    }
+    Ok(())
+}
+
+// Set a dotted key (e.g. `rpc_url` or `profiles.work.read_only`) and write the
+// file back, refusing changes that would leave the config invalid. The value
+// is read as a TOML literal when the key accepts one (`true`, `512`,
+// `["https://a", "https://b"]`) and as a plain string otherwise.
+pub fn set(path: Option<&str>, key: &str, value: &str) -> Result<()> {
+    let path = config_path(path)?;
+    let (table, sandbox): (toml::Table, Sandbox) = if path.exists() {
+        let contents = fs::read_to_string(&path)?;
+        let table = toml::from_str(&contents)
+            .map_err(|e| anyhow!("Invalid config {}: {}", path.display(), e))?;
+        // The sandbox in force is the one from before this change
+        let sandbox = Config::parse(&contents)
+            .map(|config| config.sandbox)
+            .unwrap_or_default();
+        (table, sandbox)
+    } else {
+        (toml::Table::new(), Sandbox::default())
+    };
+    sandbox.check_write(&path)?;
+    let literal = toml::from_str::<toml::Table>(&format!("value = {}", value))
+        .ok()
+        .and_then(|mut parsed| parsed.remove("value"));
+    // The literal first, so `5` sets a number; then the text itself, so a
+    // string field can still hold "5"
+    let mut candidates: Vec<toml::Value> = literal.into_iter().collect();
+    candidates.push(toml::Value::String(value.to_string()));
+    let mut error = None;
+    let mut accepted = None;
+    for candidate in candidates {
+        let mut updated = table.clone();
+        insert_dotted(&mut updated, key, candidate)?;
+        let contents = toml::to_string_pretty(&updated)?;
+        match Config::parse(&contents) {
+            Ok(_) => {
+                accepted = Some(contents);
+                break;
+            }
+            Err(e) => {
+                error.get_or_insert(e);
+            }
+        }
+    }
+    let contents = accepted.ok_or_else(|| {
+        anyhow!(
+            "Refusing to set '{}': {}; numbers and lists take TOML syntax, e.g. 512 or \
+             '[\"https://a\", \"https://b\"]'",
+            key,
+            error.map(|e| e.to_string()).unwrap_or_default()
+        )
+    })?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, contents)?;
+    println!("Set {} in {}", key, path.display());
+    Ok(())
+}
+
+fn insert_dotted(table: &mut toml::Table, key: &str, value: toml::Value) -> Result<()> {
+    let segments: Vec<&str> = key.split('.').collect();
+    let (last, parents) = segments
+        .split_last()
+        .ok_or_else(|| anyhow!("Empty config key"))?;
+    let mut current = table;
+    for segment in parents {
+        current = current
+            .entry(segment.to_string())
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| anyhow!("'{}' in '{}' is not a table", segment, key))?;
+    }
+    current.insert(last.to_string(), value);
+    Ok(())
+}
+
+fn redact_table(table: &mut toml::Table) {
+    for (key, value) in table.iter_mut() {
+        match value {
+            toml::Value::Table(inner) => redact_table(inner),
+            toml::Value::String(text) if is_secret_key(key) => *text = REDACTED.to_string(),
+            toml::Value::String(text) => *text = redact_url(text),
+            toml::Value::Array(items) => {
+                for item in items {
+                    if let toml::Value::String(text) = item {
+                        *text = redact_url(text);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn is_secret_key(key: &str) -> bool {
+    ["token", "secret", "password", "api_key"]
+        .iter()
+        .any(|suffix| key.ends_with(suffix))
+}
+
+// Keep scheme, host and port; drop `user:pass@` credentials and the path and
+// query where API keys usually live
+pub fn redact_url(value: &str) -> String {
+    let Some((scheme, rest)) = value.split_once("://") else {
+        return value.to_string();
+    };
+    let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let authority = &rest[..end];
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    match &rest[end..] {
+        "" | "/" => format!("{}://{}{}", scheme, host, &rest[end..]),
+        _ => format!("{}://{}/{}", scheme, host, REDACTED),
+    }
+}
+
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+// Config file in effect: explicit path, $ESCROW_CLI_CONFIG or the default
+pub fn config_path(path: Option<&str>) -> Result<PathBuf> {
+    path.map(PathBuf::from)
+        .or_else(|| std::env::var_os(CONFIG_ENV).map(PathBuf::from))
+        .or_else(default_config_path)
+        .ok_or_else(|| anyhow!("Cannot locate a config directory; pass --config"))
+}
+
+// ~/.config/escrow-cli/config.toml
+pub fn default_config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.toml"))
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("XDG_CONFIG_HOME") {
+        return Some(PathBuf::from(dir).join("escrow-cli"));
+    }
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config").join("escrow-cli"))
+}
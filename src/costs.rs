@@ -0,0 +1,156 @@
+use anyhow::{anyhow, Result};
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::{pubkey::Pubkey, signature::Signature, transaction::Transaction};
+use solana_transaction_status::UiTransactionEncoding;
+use std::str::FromStr;
+
+use crate::amount::{format_sol, Lamports};
+use crate::ledger::{self, CostRecord, Ledger};
+use crate::{get_vault_pda, Context};
+
+// Base fee charged per signature; anything above it is a priority fee
+const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+// Work out, print and record what a confirmed transaction spent. Failures
+// only warn: the transaction itself already landed.
+pub fn record(
+    ctx: &Context,
+    transaction: &Transaction,
+    signature: &Signature,
+    escrow: &Pubkey,
+    action: &str,
+) {
+    let result = summarize(ctx, transaction, signature, escrow, action).and_then(|cost| {
+        print_summary(&cost);
+        ledger::update(&ctx.config, |ledger| {
+            ledger.entry_mut(&escrow.to_string()).costs.push(cost)
+        })
+    });
+    if let Err(e) = result {
+        eprintln!("Warning: cost summary unavailable: {}", e);
+    }
+}
+
+fn summarize(
+    ctx: &Context,
+    transaction: &Transaction,
+    signature: &Signature,
+    escrow: &Pubkey,
+    action: &str,
+) -> Result<CostRecord> {
+    let confirmed = ctx.client.get_transaction_with_config(
+        signature,
+        RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Json),
+            commitment: None,
+            max_supported_transaction_version: Some(0),
+        },
+    )?;
+    let meta = confirmed
+        .transaction
+        .meta
+        .ok_or_else(|| anyhow!("No status metadata for {}", signature))?;
+    let keys = &transaction.message.account_keys;
+    let delta = |account: &Pubkey| -> i64 {
+        let Some(index) = keys.iter().position(|key| key == account) else {
+            return 0;
+        };
+        match (meta.pre_balances.get(index), meta.post_balances.get(index)) {
+            (Some(pre), Some(post)) => *post as i64 - *pre as i64,
+            _ => 0,
+        }
+    };
+    let base_fee =
+        LAMPORTS_PER_SIGNATURE * transaction.message.header.num_required_signatures as u64;
+    Ok(CostRecord {
+        signature: signature.to_string(),
+        action: action.to_string(),
+        fee: meta.fee,
+        priority_fee: meta.fee.saturating_sub(base_fee),
+        rent: delta(escrow),
+        principal: delta(&get_vault_pda(escrow, &ctx.program_id)),
+        block_time: confirmed.block_time,
+    })
+}
+
+fn print_summary(cost: &CostRecord) {
+    println!("Cost summary:");
+    println!("  Transaction fee: {} SOL", format_sol(cost.fee - cost.priority_fee));
+    println!("  Priority fee:    {} SOL", format_sol(cost.priority_fee));
+    if cost.rent != 0 {
+        let label = if cost.rent > 0 { "deposited" } else { "reclaimed" };
+        println!("  Rent {}:  {} SOL", label, format_sol(cost.rent.unsigned_abs()));
+    }
+    if cost.principal != 0 {
+        let label = if cost.principal > 0 { "into vault" } else { "out of vault" };
+        println!(
+            "  Principal {}: {} SOL",
+            label,
+            format_sol(cost.principal.unsigned_abs())
+        );
+    }
+}
+
+// Total operating cost per escrow from the local ledger: fees plus rent that
+// has not been reclaimed yet
+pub fn report(ctx: &Context, escrow_account: Option<&str>, tags: &[String]) -> Result<()> {
+    if let Some(escrow) = escrow_account {
+        Pubkey::from_str(escrow)?;
+    }
+    let ledger = Ledger::load(&ledger::ledger_path(&ctx.config)?)?;
+    let mut shown = 0;
+    for (escrow, entry) in &ledger.escrows {
+        if escrow_account.is_some_and(|wanted| wanted != escrow)
+            || !entry.has_tags(tags)
+            || entry.costs.is_empty()
+        {
+            continue;
+        }
+        let fees = Lamports::checked_sum(entry.costs.iter().map(|cost| Lamports::new(cost.fee)))?;
+        let priority = Lamports::checked_sum(
+            entry
+                .costs
+                .iter()
+                .map(|cost| Lamports::new(cost.priority_fee)),
+        )?;
+        let rent = signed_sum(entry.costs.iter().map(|cost| cost.rent))?;
+        let principal = signed_sum(entry.costs.iter().map(|cost| cost.principal))?;
+        let operating = i64::try_from(fees.lamports())
+            .ok()
+            .and_then(|fees| fees.checked_add(rent.max(0)))
+            .ok_or_else(|| anyhow!("Operating cost of {} overflows", escrow))?;
+        println!("Escrow: {}", escrow);
+        for cost in &entry.costs {
+            println!(
+                "  {:<16} fee {:>12}  rent {:>13}  principal {:>13}  {}",
+                cost.action,
+                format_sol(cost.fee),
+                format_signed(cost.rent),
+                format_signed(cost.principal),
+                cost.signature
+            );
+        }
+        println!("  Fees paid:          {} SOL", fees);
+        println!("  of which priority:  {} SOL", priority);
+        println!("  Rent outstanding:   {} SOL", format_signed(rent));
+        println!("  Principal in vault: {} SOL", format_signed(principal));
+        println!("  Operating cost:     {} SOL", format_signed(operating));
+        shown += 1;
+    }
+    if shown == 0 {
+        println!("No recorded costs");
+    }
+    Ok(())
+}
+
+// Rent and principal move both ways, so they are summed as signed lamports
+fn signed_sum(amounts: impl Iterator<Item = i64>) -> Result<i64> {
+    amounts
+        .try_fold(0i64, |total, amount| total.checked_add(amount))
+        .ok_or_else(|| anyhow!("Recorded amounts overflow"))
+}
+
+fn format_signed(lamports: i64) -> String {
+    let sign = if lamports < 0 { "-" } else { "" };
+    format!("{}{}", sign, format_sol(lamports.unsigned_abs()))
+}
@@ -0,0 +1,220 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Signature, Signer},
+};
+use solana_transaction_status::UiTransactionEncoding;
+use std::str::FromStr;
+
+use crate::amount::format_lamports;
+use crate::pipe;
+use crate::terms::file_digest;
+use crate::Context;
+
+const RECORD_VERSION: u32 = 1;
+
+// An arbiter's resolution of an escrow, signed with the arbiter key so it
+// can be checked without the chain, e.g. in an off-chain legal follow-up
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DecisionRecord {
+    pub version: u32,
+    pub escrow: String,
+    pub arbiter: String,
+    pub buyer: String,
+    pub seller: String,
+    // Lamports
+    pub amount: u64,
+    // "release" (arbiter_confirm) or "refund" (arbiter_cancel)
+    pub verdict: String,
+    // The transaction that carried out the verdict
+    pub transaction: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    // SHA-256 of the written rationale, which stays with the arbiter
+    pub rationale_sha256: Option<String>,
+    pub endorsement: Option<String>,
+}
+
+impl DecisionRecord {
+    // What the arbiter signs: every field but the endorsement, as JSON in
+    // field order
+    fn signed_message(&self) -> Vec<u8> {
+        let fields = serde_json::json!([
+            self.version,
+            self.escrow,
+            self.arbiter,
+            self.buyer,
+            self.seller,
+            self.amount,
+            self.verdict,
+            self.transaction,
+            self.slot,
+            self.block_time,
+            self.rationale_sha256,
+        ]);
+        format!("escrow-decision:v1 {}", fields).into_bytes()
+    }
+}
+
+// Build and sign the decision record for an arbiter_confirm or
+// arbiter_cancel transaction the arbiter sent on this escrow
+pub fn record(
+    ctx: &Context,
+    arbiter_keypair_path: &str,
+    escrow_account: &str,
+    transaction: &str,
+    rationale: Option<&str>,
+    out: &str,
+) -> Result<()> {
+    let keypair = ctx.read_keypair(arbiter_keypair_path, "arbiter")?;
+    let escrow_pubkey = Pubkey::from_str(escrow_account)?;
+    let escrow = ctx.get_escrow(&escrow_pubkey).map_err(|e| {
+        anyhow!(
+            "{} (decision records need the escrow before it is closed)",
+            e
+        )
+    })?;
+    if escrow.arbiter != keypair.pubkey() {
+        return Err(anyhow!(
+            "Escrow {} names arbiter {}, not {}",
+            escrow_pubkey,
+            escrow.arbiter,
+            keypair.pubkey()
+        ));
+    }
+    let signature = Signature::from_str(transaction)
+        .map_err(|_| anyhow!("Invalid transaction signature '{}'", transaction))?;
+    let confirmed = ctx.client.get_transaction_with_config(
+        &signature,
+        RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: Some(ctx.client.commitment()),
+            max_supported_transaction_version: Some(0),
+        },
+    )?;
+    if confirmed
+        .transaction
+        .meta
+        .as_ref()
+        .is_some_and(|meta| meta.err.is_some())
+    {
+        return Err(anyhow!("Transaction {} failed on-chain", signature));
+    }
+    let decoded = confirmed
+        .transaction
+        .transaction
+        .decode()
+        .ok_or_else(|| anyhow!("Failed to decode transaction {}", signature))?;
+    let keys = decoded.message.static_account_keys();
+    let signers = &keys[..decoded.message.header().num_required_signatures as usize];
+    if !signers.contains(&escrow.arbiter) {
+        return Err(anyhow!(
+            "Transaction {} was not signed by the arbiter",
+            signature
+        ));
+    }
+    let verdict = decoded
+        .message
+        .instructions()
+        .iter()
+        .filter(|ix| keys.get(ix.program_id_index as usize) == Some(&ctx.program_id))
+        .filter(|ix| {
+            ix.accounts
+                .get(1)
+                .and_then(|index| keys.get(*index as usize))
+                == Some(&escrow_pubkey)
+        })
+        .find_map(|ix| match ix.data.first() {
+            Some(4) => Some("release"),
+            Some(5) => Some("refund"),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            anyhow!(
+                "Transaction {} has no arbiter_confirm or arbiter_cancel on {}",
+                signature,
+                escrow_pubkey
+            )
+        })?;
+    let mut record = DecisionRecord {
+        version: RECORD_VERSION,
+        escrow: escrow_pubkey.to_string(),
+        arbiter: escrow.arbiter.to_string(),
+        buyer: escrow.buyer.to_string(),
+        seller: escrow.seller.to_string(),
+        amount: escrow.amount.lamports(),
+        verdict: verdict.to_string(),
+        transaction: signature.to_string(),
+        slot: confirmed.slot,
+        block_time: confirmed.block_time,
+        rationale_sha256: rationale.map(file_digest).transpose()?,
+        endorsement: None,
+    };
+    record.endorsement = Some(keypair.sign_message(&record.signed_message()).to_string());
+    let contents = serde_json::to_string_pretty(&record)?;
+    pipe::write_output(&ctx.config.sandbox, out, contents.as_bytes())?;
+    if out != pipe::STDIO {
+        println!("Decision record ({}) signed and saved to {}", verdict, out);
+    }
+    Ok(())
+}
+
+// Check a decision record's endorsement, and the rationale document against
+// its hash when given; needs no RPC access
+pub fn verify(file: &str, rationale: Option<&str>) -> Result<()> {
+    let contents = pipe::read_input(file)?;
+    let record: DecisionRecord = serde_json::from_slice(&contents)
+        .map_err(|e| anyhow!("Invalid decision record {}: {}", file, e))?;
+    if record.version != RECORD_VERSION {
+        return Err(anyhow!(
+            "Unsupported decision record version {} (expected {})",
+            record.version,
+            RECORD_VERSION
+        ));
+    }
+    let arbiter = Pubkey::from_str(&record.arbiter)
+        .map_err(|_| anyhow!("Invalid arbiter pubkey '{}'", record.arbiter))?;
+    let endorsement = record
+        .endorsement
+        .as_deref()
+        .ok_or_else(|| anyhow!("Decision record is not signed"))?;
+    let signature = Signature::from_str(endorsement)
+        .map_err(|_| anyhow!("Invalid endorsement signature '{}'", endorsement))?;
+    if !signature.verify(arbiter.as_ref(), &record.signed_message()) {
+        return Err(anyhow!(
+            "Endorsement was not made by arbiter {} or the record was changed after signing",
+            arbiter
+        ));
+    }
+    match (rationale, &record.rationale_sha256) {
+        (Some(path), Some(expected)) if file_digest(path)? != *expected => {
+            return Err(anyhow!(
+                "{} does not match the record's rationale hash",
+                path
+            ));
+        }
+        (Some(_), None) => return Err(anyhow!("The record commits to no rationale")),
+        _ => {}
+    }
+    println!("Decision record for escrow {}", record.escrow);
+    println!("  Verdict:     {}", record.verdict);
+    println!("  Arbiter:     {}", record.arbiter);
+    println!("  Buyer:       {}", record.buyer);
+    println!("  Seller:      {}", record.seller);
+    println!("  Amount:      {} lamports", format_lamports(record.amount));
+    println!(
+        "  Transaction: {} (slot {})",
+        record.transaction, record.slot
+    );
+    if let Some(digest) = &record.rationale_sha256 {
+        println!("  Rationale:   SHA-256 {}", digest);
+    }
+    println!("Endorsement verified");
+    if rationale.is_some() {
+        println!("Rationale matches");
+    }
+    Ok(())
+}
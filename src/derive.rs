@@ -0,0 +1,47 @@
+use anyhow::{anyhow, Result};
+use solana_sdk::hash::hashv;
+use solana_sdk::signature::{Keypair, Signer};
+
+use crate::pipe;
+use crate::Context;
+
+// Escrow account keypairs derived from the buyer's secret key and an index,
+// so a lost escrow keypair file can be regenerated with `recover`. Changing
+// the domain string would orphan every derived escrow.
+pub fn escrow_keypair(buyer: &Keypair, index: u32) -> Result<Keypair> {
+    let secret = buyer.to_bytes();
+    let seed = hashv(&[
+        b"escrow-cli:escrow-keypair:v1",
+        &secret[..32],
+        &index.to_le_bytes(),
+    ]);
+    Keypair::from_seed(seed.as_ref()).map_err(|e| anyhow!("Failed to derive escrow keypair: {}", e))
+}
+
+// Regenerate the escrow keypair created with `create-offer --escrow-index`,
+// writing it to `out` in the usual JSON byte-array format
+pub fn recover(
+    ctx: &Context,
+    buyer_keypair_path: &str,
+    index: u32,
+    out: Option<&str>,
+) -> Result<()> {
+    let buyer = ctx.read_keypair(buyer_keypair_path, "buyer")?;
+    let escrow = escrow_keypair(&buyer, index)?;
+    let exists = ctx.client.get_account(&escrow.pubkey()).is_ok();
+    println!(
+        "Escrow #{} of buyer {}: {}{}",
+        index,
+        buyer.pubkey(),
+        escrow.pubkey(),
+        if exists { "" } else { " (no account on-chain)" }
+    );
+    if let Some(out) = out {
+        let contents = serde_json::to_string(&escrow.to_bytes().to_vec())?;
+        pipe::write_output(&ctx.config.sandbox, out, contents.as_bytes())?;
+        if out != pipe::STDIO {
+            println!("Escrow keypair written to {}", out);
+        }
+    }
+    Ok(())
+}
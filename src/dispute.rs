@@ -0,0 +1,199 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::account::EscrowAccount;
+use crate::memo::find_memos;
+use crate::storage;
+use crate::terms::{committed_terms, digest_bytes};
+use crate::{get_escrow_info, get_vault_pda, Context};
+
+// Show an escrow together with its committed terms, fetching uploaded
+// documents and checking them against the committed hashes
+pub fn show(ctx: &Context, escrow_account: &str, out: Option<&str>) -> Result<()> {
+    let escrow_pubkey = Pubkey::from_str(escrow_account)?;
+    let escrow = ctx.get_escrow(&escrow_pubkey)?;
+    get_escrow_info(ctx, escrow_account)?;
+    let commitments = committed_terms(ctx, &escrow_pubkey, &escrow)?;
+    if commitments.is_empty() {
+        println!("No terms attached.");
+        return Ok(());
+    }
+    if let Some(dir) = out {
+        ctx.config.sandbox.check_write(Path::new(dir))?;
+        fs::create_dir_all(dir)?;
+    }
+    for (index, commitment) in commitments.iter().enumerate() {
+        println!("Terms #{}:", index + 1);
+        println!("  SHA-256: {}", commitment.digest);
+        println!("  Committed by: {}", commitment.signer);
+        println!("  Signature: {}", commitment.signature);
+        let Some(location) = &commitment.location else {
+            continue;
+        };
+        match storage::fetch(&ctx.config.storage, location) {
+            Ok(contents) => {
+                let verified = digest_bytes(&contents) == commitment.digest;
+                println!(
+                    "  Document: {} ({})",
+                    location,
+                    if verified { "hash verified" } else { "HASH MISMATCH" }
+                );
+                if let Some(dir) = out {
+                    let path = Path::new(dir).join(format!("terms-{}", commitment.digest));
+                    ctx.config.sandbox.check_write(&path)?;
+                    fs::write(&path, contents)?;
+                    println!("  Saved to {}", path.display());
+                }
+            }
+            Err(e) => println!("  Document: {} (fetch failed: {})", location, e),
+        }
+    }
+    Ok(())
+}
+
+// Package the account snapshot, transaction history, memos, committed terms
+// and any local notes into a zip archive for offline review
+pub fn bundle(ctx: &Context, escrow_account: &str, out: &str, notes: &[String]) -> Result<()> {
+    let escrow_pubkey = Pubkey::from_str(escrow_account)?;
+    let program_id = ctx.program_id;
+    let response = ctx
+        .client
+        .get_account_with_commitment(&escrow_pubkey, ctx.client.commitment())?;
+    let account = response
+        .value
+        .ok_or_else(|| anyhow!("Escrow account {} not found", escrow_pubkey))?;
+    let escrow = EscrowAccount::unpack(&account.data)?;
+    let vault_pda = get_vault_pda(&escrow_pubkey, &program_id);
+    let snapshot = json!({
+        "escrow": escrow_pubkey.to_string(),
+        "slot": response.context.slot,
+        "owner": account.owner.to_string(),
+        "lamports": account.lamports,
+        "data_base64": STANDARD.encode(&account.data),
+        "decoded": escrow.to_json(),
+        "vault": vault_pda.to_string(),
+        "vault_lamports": ctx.client.get_balance(&vault_pda)?,
+    });
+
+    let history: Vec<Value> = ctx
+        .client
+        .get_signatures_for_address(&escrow_pubkey)?
+        .iter()
+        .map(|status| {
+            json!({
+                "signature": status.signature,
+                "slot": status.slot,
+                "block_time": status.block_time,
+                "error": status.err.as_ref().map(|e| e.to_string()),
+                "memo": status.memo,
+            })
+        })
+        .collect();
+
+    // Memos on the escrow itself, plus party memos that mention it
+    let escrow_id = escrow_pubkey.to_string();
+    let mut seen = HashSet::new();
+    let mut memos = Vec::new();
+    for address in std::iter::once(escrow_pubkey).chain(escrow.parties()) {
+        for record in find_memos(&ctx.client, &address)? {
+            if address != escrow_pubkey && !record.memo.contains(&escrow_id) {
+                continue;
+            }
+            if !seen.insert((record.signature.clone(), record.memo.clone())) {
+                continue;
+            }
+            memos.push(json!({
+                "signature": record.signature,
+                "memo": record.memo,
+                "block_time": record.block_time,
+                "found_via": address.to_string(),
+            }));
+        }
+    }
+
+    ctx.config.sandbox.check_write(Path::new(out))?;
+    let file = File::create(out).map_err(|e| anyhow!("Failed to create {}: {}", out, e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let mut terms = Vec::new();
+    for commitment in committed_terms(ctx, &escrow_pubkey, &escrow)? {
+        let mut entry = json!({
+            "digest": commitment.digest,
+            "signer": commitment.signer.to_string(),
+            "signature": commitment.signature,
+            "block_time": commitment.block_time,
+            "location": commitment.location,
+        });
+        if let Some(location) = &commitment.location {
+            match storage::fetch(&ctx.config.storage, location) {
+                Ok(contents) => {
+                    let name = format!("terms/{}", commitment.digest);
+                    entry["verified"] = json!(digest_bytes(&contents) == commitment.digest);
+                    entry["file"] = json!(name);
+                    add_file(&mut zip, &name, &contents, options)?;
+                }
+                Err(e) => entry["fetch_error"] = json!(e.to_string()),
+            }
+        }
+        terms.push(entry);
+    }
+
+    let mut note_files = Vec::new();
+    for note in notes {
+        let contents = fs::read(note).map_err(|e| anyhow!("Failed to read {}: {}", note, e))?;
+        let file_name = Path::new(note)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .ok_or_else(|| anyhow!("Invalid note path: {}", note))?;
+        let name = format!("notes/{}", file_name);
+        add_file(&mut zip, &name, &contents, options)?;
+        note_files.push(name);
+    }
+
+    let manifest = json!({
+        "escrow": escrow_id,
+        "generated_at": SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        "cli_version": env!("CARGO_PKG_VERSION"),
+        "program_id": ctx.program_id.to_string(),
+        "files": ["account.json", "history.json", "memos.json", "terms.json"],
+        "notes": note_files,
+    });
+    add_json(&mut zip, "manifest.json", &manifest, options)?;
+    add_json(&mut zip, "account.json", &snapshot, options)?;
+    add_json(&mut zip, "history.json", &json!(history), options)?;
+    add_json(&mut zip, "memos.json", &json!(memos), options)?;
+    add_json(&mut zip, "terms.json", &json!(terms), options)?;
+    zip.finish()?;
+    println!("Dispute bundle written to {}", out);
+    Ok(())
+}
+
+fn add_json(
+    zip: &mut ZipWriter<File>,
+    name: &str,
+    value: &Value,
+    options: FileOptions,
+) -> Result<()> {
+    add_file(zip, name, serde_json::to_string_pretty(value)?.as_bytes(), options)
+}
+
+fn add_file(
+    zip: &mut ZipWriter<File>,
+    name: &str,
+    contents: &[u8],
+    options: FileOptions,
+) -> Result<()> {
+    zip.start_file(name, options)?;
+    zip.write_all(contents)?;
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use std::time::Duration;
+
+// Parse "90", "90s", "30m", "12h" or "3d"; bare numbers are seconds
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let trimmed = input.trim();
+    let (digits, unit) = match trimmed.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => trimmed.split_at(index),
+        None => (trimmed, "s"),
+    };
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "Invalid duration '{}': expected e.g. 90s, 30m, 12h or 3d",
+                input
+            ))
+        }
+    };
+    let value: u64 = digits
+        .parse()
+        .map_err(|_| format!("Invalid duration '{}': expected e.g. 90s, 30m, 12h or 3d", input))?;
+    value
+        .checked_mul(multiplier)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("Duration '{}' is too long", input))
+}
+
+// Render a duration in its largest whole units, e.g. "2d 3h" or "45m"
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (days, hours, minutes) = (secs / 86_400, secs % 86_400 / 3_600, secs % 3_600 / 60);
+    match (days, hours, minutes) {
+        (0, 0, 0) => format!("{}s", secs),
+        (0, 0, m) => format!("{}m", m),
+        (0, h, m) => format!("{}h {}m", h, m),
+        (d, h, _) => format!("{}d {}h", d, h),
+    }
+}
@@ -0,0 +1,124 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::ValueEnum;
+use serde::Serialize;
+use solana_sdk::{signature::Signature, transaction::Transaction};
+
+use crate::Context;
+
+pub const MAINNET: &str = "mainnet-beta";
+// Genesis hashes of the public clusters, as wallets label them
+const CLUSTERS: [(&str, &str); 3] = [
+    (MAINNET, "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dw2N9d"),
+    ("devnet", "EtWTRABZaYq6iMfeYKouRu166VU2xqa1wcaWoxPkrZBG"),
+    ("testnet", "4uhcVJyU9pJkvQyS88uRDiswHXSCkY3zQawwpjk2NsNY"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TxEncoding {
+    /// Base64 of the wire format (solana CLI, most wallets)
+    Base64,
+    /// Base58 of the wire format (Squads and older tooling)
+    Base58,
+    /// Raw wire-format bytes
+    Binary,
+    /// JSON envelope with the base64 transaction and metadata
+    Json,
+}
+
+// Self-describing export for multisig and wallet import screens
+#[derive(Serialize)]
+struct Envelope<'a> {
+    network: String,
+    genesis_hash: String,
+    description: &'a str,
+    // The transaction stops being valid once the chain passes this height
+    last_valid_block_height: Option<u64>,
+    signers: Vec<EnvelopeSigner>,
+    encoding: &'static str,
+    transaction: String,
+}
+
+#[derive(Serialize)]
+struct EnvelopeSigner {
+    pubkey: String,
+    signed: bool,
+}
+
+// The cluster the RPC node serves, by genesis hash: its name ("custom" for
+// unknown ones) and the hash itself
+pub fn cluster(ctx: &Context) -> Result<(String, String)> {
+    let genesis_hash = ctx.client.get_genesis_hash()?.to_string();
+    let name = CLUSTERS
+        .iter()
+        .find(|(_, hash)| *hash == genesis_hash)
+        .map(|(name, _)| name.to_string())
+        .unwrap_or_else(|| "custom".to_string());
+    Ok((name, genesis_hash))
+}
+
+// Serialize an unsigned or partially signed transaction in `encoding`
+pub fn export_transaction(
+    ctx: &Context,
+    transaction: &Transaction,
+    encoding: TxEncoding,
+    description: &str,
+    last_valid_block_height: Option<u64>,
+) -> Result<Vec<u8>> {
+    let wire = bincode::serialize(transaction)?;
+    Ok(match encoding {
+        TxEncoding::Base64 => STANDARD.encode(&wire).into_bytes(),
+        TxEncoding::Base58 => bs58::encode(&wire).into_string().into_bytes(),
+        TxEncoding::Binary => wire,
+        TxEncoding::Json => {
+            let (network, genesis_hash) = cluster(ctx)?;
+            let required = transaction.message.header.num_required_signatures as usize;
+            let signers = transaction.message.account_keys[..required]
+                .iter()
+                .zip(&transaction.signatures)
+                .map(|(pubkey, signature)| EnvelopeSigner {
+                    pubkey: pubkey.to_string(),
+                    signed: *signature != Signature::default(),
+                })
+                .collect();
+            let envelope = Envelope {
+                network,
+                genesis_hash,
+                description,
+                last_valid_block_height,
+                signers,
+                encoding: "base64",
+                transaction: STANDARD.encode(&wire),
+            };
+            serde_json::to_vec_pretty(&envelope)?
+        }
+    })
+}
+
+// Read back a transaction written by `export_transaction` in any encoding
+pub fn import_transaction(contents: &[u8]) -> Result<Transaction> {
+    let invalid = || anyhow!("Not a transaction in any export encoding");
+    let text = std::str::from_utf8(contents).map(str::trim);
+    let wire = match text {
+        Ok(text) if text.starts_with('{') => {
+            let envelope: serde_json::Value = serde_json::from_str(text)?;
+            let transaction = envelope["transaction"].as_str().ok_or_else(invalid)?;
+            STANDARD.decode(transaction)?
+        }
+        // Raw wire bytes may happen to be valid UTF-8
+        Ok(text) => STANDARD
+            .decode(text)
+            .or_else(|_| bs58::decode(text).into_vec())
+            .unwrap_or_else(|_| contents.to_vec()),
+        Err(_) => contents.to_vec(),
+    };
+    bincode::deserialize(&wire).map_err(|_| invalid())
+}
+
+// Binary exports can't be printed alongside other output
+pub fn printable(encoding: TxEncoding) -> Result<()> {
+    if encoding == TxEncoding::Binary {
+        return Err(anyhow!("Binary transactions must be written to a file"));
+    }
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::account::{EscrowState, ARBITER_OFFSET, BUYER_OFFSET, SELLER_OFFSET, STATE_OFFSET};
+
+// getProgramAccounts filters over the escrow layout. Callers combine these
+// instead of writing offsets, so a layout change only touches account.rs.
+
+// Escrow accounts of `size` bytes (see Context::account_size)
+pub fn escrows(size: usize) -> RpcFilterType {
+    RpcFilterType::DataSize(size as u64)
+}
+
+pub fn by_buyer(buyer: &Pubkey) -> RpcFilterType {
+    pubkey_at(BUYER_OFFSET, buyer)
+}
+
+pub fn by_seller(seller: &Pubkey) -> RpcFilterType {
+    pubkey_at(SELLER_OFFSET, seller)
+}
+
+pub fn by_arbiter(arbiter: &Pubkey) -> RpcFilterType {
+    pubkey_at(ARBITER_OFFSET, arbiter)
+}
+
+pub fn by_state(state: EscrowState) -> RpcFilterType {
+    RpcFilterType::Memcmp(Memcmp::new_base58_encoded(STATE_OFFSET, &[state as u8]))
+}
+
+// One filter per party role, for queries that OR over them with a request
+// each
+pub fn by_party(party: &Pubkey) -> [RpcFilterType; 3] {
+    [by_buyer(party), by_seller(party), by_arbiter(party)]
+}
+
+fn pubkey_at(offset: usize, pubkey: &Pubkey) -> RpcFilterType {
+    RpcFilterType::Memcmp(Memcmp::new_base58_encoded(offset, pubkey.as_ref()))
+}
@@ -0,0 +1,67 @@
+use solana_sdk::hash::hashv;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::account::EscrowAccount;
+
+// Words a fingerprint reads out as
+const FINGERPRINT_WORDS: usize = 6;
+
+// One word per byte value, short and distinct when spoken
+const WORDS: [&str; 256] = [
+    "acid", "acorn", "actor", "adult", "agent", "alarm", "album", "alley", "amber", "angle",
+    "ankle", "apple", "apron", "arena", "arrow", "atlas", "attic", "audio", "badge", "bagel",
+    "baker", "bamboo", "banjo", "barn", "basil", "basin", "beach", "beard", "bench", "berry",
+    "bison", "blade", "blaze", "bloom", "board", "boat", "bonus", "boots", "bottle", "brain",
+    "brass", "bread", "brick", "bridge", "broom", "brush", "bucket", "bugle", "cabin", "cable",
+    "cactus", "camel", "candle", "canoe", "canvas", "carpet", "carrot", "castle", "cedar", "chalk",
+    "chair", "cherry", "chess", "chief", "cider", "cliff", "clock", "cloud", "clover", "coach",
+    "cobra", "cocoa", "comet", "coral", "cotton", "couch", "crane", "crayon", "crown", "cube",
+    "daisy", "dance", "delta", "desert", "diary", "dingo", "diver", "dock", "donkey", "dragon",
+    "drum", "eagle", "easel", "echo", "elbow", "elder", "ember", "engine", "falcon", "fence",
+    "ferry", "fiddle", "field", "flute", "forest", "fossil", "fox", "frost", "fudge", "galaxy",
+    "garden", "garlic", "gecko", "geyser", "ginger", "globe", "goat", "grape", "gravel", "guitar",
+    "hammer", "harbor", "hazel", "helmet", "heron", "hollow", "honey", "hornet", "hotel", "husky",
+    "igloo", "island", "ivory", "jacket", "jaguar", "jelly", "jungle", "kayak", "kettle", "kiwi",
+    "koala", "ladder", "lagoon", "lantern", "laser", "lemon", "lentil", "lily", "lizard", "llama",
+    "locket", "lotus", "magnet", "mango", "maple", "marble", "meadow", "melon", "meteor", "mint",
+    "mirror", "mitten", "monkey", "moose", "mosaic", "motor", "muffin", "museum", "nectar",
+    "needle", "nickel", "noodle", "oasis", "ocean", "olive", "onion", "opera", "orbit", "orchid",
+    "otter", "oven", "owl", "paddle", "palace", "panda", "parrot", "peach", "pebble", "pencil",
+    "pepper", "piano", "pigeon", "pillow", "pirate", "planet", "plum", "pocket", "pony", "poppy",
+    "puzzle", "quartz", "quill", "rabbit", "radar", "radish", "raven", "reef", "ribbon", "river",
+    "robin", "rocket", "saddle", "salmon", "sandal", "satin", "scarf", "shadow", "shell", "shrimp",
+    "silver", "sketch", "sled", "socket", "spider", "spoon", "squid", "stable", "statue", "stone",
+    "summit", "swan", "table", "tiger", "timber", "toast", "tomato", "torch", "tower", "tulip",
+    "tunnel", "turtle", "valley", "velvet", "violin", "wagon", "walnut", "walrus", "whale",
+    "whistle", "willow", "window", "wizard", "wolf", "yacht", "zebra", "zipper",
+];
+
+// Words both parties read out to each other to check they are looking at the
+// same escrow: a hash of its address, parties and amount. It changes when the
+// seller joins, so compare it after that and before funding.
+pub fn fingerprint(escrow_pubkey: &Pubkey, escrow: &EscrowAccount) -> String {
+    let digest = hashv(&[
+        b"escrow-fingerprint:v1",
+        escrow_pubkey.as_ref(),
+        escrow.buyer.as_ref(),
+        escrow.seller.as_ref(),
+        escrow.arbiter.as_ref(),
+        &escrow.amount.lamports().to_le_bytes(),
+    ]);
+    digest.as_ref()[..FINGERPRINT_WORDS]
+        .iter()
+        .map(|byte| WORDS[*byte as usize])
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+// Compare a fingerprint typed back by the user, ignoring case and separators
+pub fn matches(expected: &str, fingerprint: &str) -> bool {
+    let words = |text: &str| -> Vec<String> {
+        text.split(|c: char| !c.is_ascii_alphabetic())
+            .filter(|word| !word.is_empty())
+            .map(str::to_ascii_lowercase)
+            .collect()
+    };
+    words(expected) == words(fingerprint)
+}
@@ -0,0 +1,290 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use solana_client::{
+    rpc_client::GetConfirmedSignaturesForAddress2Config, rpc_config::RpcTransactionConfig,
+};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::{TransactionConfirmationStatus, UiTransactionEncoding};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::config_dir;
+use crate::duration::format_duration;
+use crate::network;
+use crate::Context;
+
+// getSignaturesForAddress returns at most this many signatures per call
+const PAGE_SIZE: usize = 1000;
+
+// One transaction that touched the escrow
+#[derive(Deserialize, Serialize)]
+struct HistoryEntry {
+    signature: String,
+    slot: u64,
+    block_time: Option<i64>,
+    // Escrow instructions in the transaction, e.g. ["fund_escrow"]
+    actions: Vec<String>,
+    failed: bool,
+    // Not finalized yet, so it could still be rolled back; never cached
+    #[serde(skip)]
+    pending: bool,
+}
+
+// An escrow's finalized history as last fetched, newest first. Later calls
+// only fetch signatures newer than the first entry.
+#[derive(Deserialize)]
+struct HistoryCache {
+    program_id: String,
+    entries: Vec<HistoryEntry>,
+}
+
+// Every transaction touching the escrow, newest first, with the escrow
+// instructions it carried. With `history_api` configured (a Helius-style
+// enhanced transactions endpoint) one request returns a page of parsed
+// transactions; otherwise each signature is fetched from the RPC node.
+// Finalized history is cached per escrow; `refresh` drops the cache first.
+pub fn history(
+    ctx: &Context,
+    escrow_account: &str,
+    limit: Option<usize>,
+    refresh: bool,
+) -> Result<()> {
+    let escrow_pubkey = Pubkey::from_str(escrow_account)?;
+    let limit = limit.unwrap_or(usize::MAX);
+    let path = cache_path(&escrow_pubkey);
+    let mut cached = match &path {
+        Some(path) if !refresh => load_cache(path, &ctx.program_id),
+        _ => Vec::new(),
+    };
+    let until = cached.first().map(|entry| entry.signature.clone());
+    // Without a cache only `limit` entries are needed; with one, every entry
+    // newer than it
+    let fetch_limit = if until.is_some() { usize::MAX } else { limit };
+    let mut entries = match &ctx.config.history_api {
+        Some(url) => enhanced_history(ctx, url, &escrow_pubkey, until.as_deref(), fetch_limit)?,
+        None => rpc_history(ctx, &escrow_pubkey, until.as_deref(), fetch_limit)?,
+    };
+    // A fetch that stopped at the limit may have left older entries behind
+    let complete = entries.len() < fetch_limit;
+    entries.append(&mut cached);
+    // Only the finalized run below the newest pending entry is kept, so the
+    // cache has no gaps and nothing a fork could still drop
+    let settled = entries
+        .iter()
+        .rposition(|entry| entry.pending)
+        .map_or(0, |i| i + 1);
+    if let (Some(path), true) = (&path, complete) {
+        if let Err(e) = save_cache(ctx, path, &entries[settled..]) {
+            eprintln!("Warning: failed to cache history: {}", e);
+        }
+    }
+    entries.truncate(limit);
+    if entries.is_empty() {
+        println!("No transactions found for {}", escrow_pubkey);
+        return Ok(());
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    for entry in &entries {
+        let age = match entry.block_time {
+            Some(time) => format!(
+                "{} ago",
+                format_duration(Duration::from_secs((now - time).max(0) as u64))
+            ),
+            None => "unknown".to_string(),
+        };
+        let actions = if entry.actions.is_empty() {
+            "-".to_string()
+        } else {
+            entry.actions.join(", ")
+        };
+        println!(
+            "{:>12}  slot {:<10} {}  {}{}",
+            age,
+            entry.slot,
+            entry.signature,
+            actions,
+            if entry.failed { " (failed)" } else { "" }
+        );
+    }
+    Ok(())
+}
+
+// ~/.config/escrow-cli/history/<escrow>.json
+fn cache_path(escrow: &Pubkey) -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("history").join(format!("{}.json", escrow)))
+}
+
+// Cached entries, or none when the cache is missing, unreadable or was
+// written for another program
+fn load_cache(path: &Path, program_id: &Pubkey) -> Vec<HistoryEntry> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| serde_json::from_str::<HistoryCache>(&contents).ok())
+        .filter(|cache| cache.program_id == program_id.to_string())
+        .map(|cache| cache.entries)
+        .unwrap_or_default()
+}
+
+fn save_cache(ctx: &Context, path: &Path, entries: &[HistoryEntry]) -> Result<()> {
+    ctx.config.sandbox.check_write(path)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let cache = serde_json::json!({
+        "program_id": ctx.program_id.to_string(),
+        "entries": entries,
+    });
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string(&cache)?)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+// Walk getSignaturesForAddress page by page back to `until` (exclusive),
+// fetching each transaction
+fn rpc_history(
+    ctx: &Context,
+    escrow: &Pubkey,
+    until: Option<&str>,
+    limit: usize,
+) -> Result<Vec<HistoryEntry>> {
+    let until = until.map(Signature::from_str).transpose()?;
+    let mut entries = Vec::new();
+    let mut before = None;
+    while entries.len() < limit {
+        let page = ctx.client.get_signatures_for_address_with_config(
+            escrow,
+            GetConfirmedSignaturesForAddress2Config {
+                before,
+                until,
+                limit: Some(PAGE_SIZE.min(limit - entries.len())),
+                ..GetConfirmedSignaturesForAddress2Config::default()
+            },
+        )?;
+        let full = page.len() == PAGE_SIZE;
+        for status in page {
+            let signature = Signature::from_str(&status.signature)?;
+            before = Some(signature);
+            entries.push(HistoryEntry {
+                actions: transaction_actions(ctx, &signature)?,
+                signature: status.signature,
+                slot: status.slot,
+                block_time: status.block_time,
+                failed: status.err.is_some(),
+                pending: status.confirmation_status
+                    != Some(TransactionConfirmationStatus::Finalized),
+            });
+        }
+        if !full {
+            break;
+        }
+    }
+    Ok(entries)
+}
+
+fn transaction_actions(ctx: &Context, signature: &Signature) -> Result<Vec<String>> {
+    let transaction = ctx.client.get_transaction_with_config(
+        signature,
+        RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: None,
+            max_supported_transaction_version: Some(0),
+        },
+    )?;
+    let decoded = transaction
+        .transaction
+        .transaction
+        .decode()
+        .ok_or_else(|| anyhow!("Failed to decode transaction {}", signature))?;
+    let keys = decoded.message.static_account_keys();
+    Ok(decoded
+        .message
+        .instructions()
+        .iter()
+        .filter(|ix| keys.get(ix.program_id_index as usize) == Some(&ctx.program_id))
+        .map(|ix| action_name(ix.data.first().copied()).to_string())
+        .collect())
+}
+
+// Page through an enhanced transactions endpoint back to `until`
+// (exclusive). `url` may contain `{address}`, e.g.
+// https://api.helius.xyz/v0/addresses/{address}/transactions?api-key=...
+fn enhanced_history(
+    ctx: &Context,
+    url: &str,
+    escrow: &Pubkey,
+    until: Option<&str>,
+    limit: usize,
+) -> Result<Vec<HistoryEntry>> {
+    let endpoint = url.replace("{address}", &escrow.to_string());
+    // The API doesn't say what is finalized; anything above the finalized
+    // slot is treated as pending
+    let finalized_slot = ctx
+        .client
+        .get_slot_with_commitment(CommitmentConfig::finalized())?;
+    let mut entries = Vec::new();
+    let mut before: Option<String> = None;
+    while entries.len() < limit {
+        let query: Vec<(&str, String)> = before
+            .iter()
+            .map(|signature| ("before", signature.clone()))
+            .chain(until.map(|signature| ("until", signature.to_string())))
+            .collect();
+        let page: Vec<Value> = network::get_json("History API", &endpoint, &query)?;
+        if page.is_empty() {
+            break;
+        }
+        for transaction in page {
+            let signature = transaction["signature"]
+                .as_str()
+                .ok_or_else(|| anyhow!("History API returned a transaction without a signature"))?
+                .to_string();
+            before = Some(signature.clone());
+            let slot = transaction["slot"].as_u64().unwrap_or_default();
+            entries.push(HistoryEntry {
+                actions: enhanced_actions(&transaction, &ctx.program_id),
+                signature,
+                slot,
+                block_time: transaction["timestamp"].as_i64(),
+                failed: !transaction["transactionError"].is_null(),
+                pending: slot > finalized_slot,
+            });
+        }
+    }
+    entries.truncate(limit);
+    Ok(entries)
+}
+
+// Top-level instructions of the escrow program; their data is base58
+fn enhanced_actions(transaction: &Value, program_id: &Pubkey) -> Vec<String> {
+    let program_id = program_id.to_string();
+    transaction["instructions"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter(|ix| ix["programId"].as_str() == Some(program_id.as_str()))
+        .map(|ix| {
+            let data = ix["data"].as_str().unwrap_or_default();
+            let tag = bs58::decode(data).into_vec().ok().and_then(|data| data.first().copied());
+            action_name(tag).to_string()
+        })
+        .collect()
+}
+
+fn action_name(tag: Option<u8>) -> &'static str {
+    match tag {
+        Some(0) => "create_offer",
+        Some(1) => "join_offer",
+        Some(2) => "fund_escrow",
+        Some(3) => "confirm_escrow",
+        Some(4) => "arbiter_confirm",
+        Some(5) => "arbiter_cancel",
+        Some(6) => "close_escrow",
+        Some(8) => "mutual_cancel",
+        _ => "unknown",
+    }
+}
@@ -0,0 +1,300 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Signature, Signer},
+    transaction::Transaction,
+};
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::actions::Role;
+use crate::config::{expand_home, Config};
+use crate::network::{self, Body};
+
+// Commands (run with `sh -c`) or http(s) URLs invoked around each transaction.
+// Both receive the transaction details as JSON: on stdin for commands, as the
+// POST body for URLs. With `signing_keypair`, every payload is signed so
+// receivers can check where it came from (see `webhook verify`).
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hooks {
+    pub pre_send: Option<String>,
+    pub post_confirm: Option<String>,
+    // Receives reminders meant for the escrow parties, e.g. cancel nudges
+    pub notify: Option<String>,
+    // Further notify targets, each for a subset of notifications
+    pub routes: Vec<Route>,
+    // Ed25519 keypair that signs `<timestamp>.<body>` of each payload
+    pub signing_keypair: Option<String>,
+}
+
+// A notify target for one role, party, escrow or set of events, e.g. the
+// buyer's Telegram bot or the arbiter's mail script. Every filter left out
+// matches everything.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Route {
+    pub hook: String,
+    // Only notifications naming this role, delivered with "role" set
+    pub role: Option<Role>,
+    // Only when that role is this pubkey
+    pub party: Option<String>,
+    pub escrow: Option<String>,
+    #[serde(default)]
+    pub events: Vec<String>,
+    // Message added to the payload as "text"; `{field}` is replaced with
+    // the payload's field, e.g. "Escrow {escrow}: {event}"
+    pub template: Option<String>,
+}
+
+impl Hooks {
+    pub fn validate(&self) -> Result<()> {
+        for (index, route) in self.routes.iter().enumerate() {
+            let check = |key: &str, value: &Option<String>| -> Result<()> {
+                match value {
+                    Some(value) if Pubkey::from_str(value).is_err() => Err(anyhow!(
+                        "hooks.routes[{}].{}: '{}' is not a pubkey",
+                        index,
+                        key,
+                        value
+                    )),
+                    _ => Ok(()),
+                }
+            };
+            check("party", &route.party)?;
+            check("escrow", &route.escrow)?;
+            if route.party.is_some() && route.role.is_none() {
+                return Err(anyhow!("hooks.routes[{}]: party needs a role", index));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Route {
+    // The payload as this route delivers it, or None when it doesn't match
+    fn deliver(&self, payload: &Value) -> Option<Value> {
+        let field = |key: &str| payload.get(key).and_then(Value::as_str);
+        if self
+            .escrow
+            .as_deref()
+            .is_some_and(|escrow| field("escrow") != Some(escrow))
+        {
+            return None;
+        }
+        if !self.events.is_empty()
+            && !field("event").is_some_and(|event| self.events.iter().any(|e| e == event))
+        {
+            return None;
+        }
+        let mut delivered = payload.clone();
+        if let Some(role) = self.role {
+            let party = field(&role.to_string().to_lowercase())?;
+            if self
+                .party
+                .as_deref()
+                .is_some_and(|expected| expected != party)
+            {
+                return None;
+            }
+            delivered["role"] = json!(role);
+        }
+        if let Some(template) = &self.template {
+            delivered["text"] = json!(render(template, &delivered));
+        }
+        Some(delivered)
+    }
+}
+
+// Replace each `{field}` with the payload's value; strings go in unquoted
+fn render(template: &str, payload: &Value) -> String {
+    let mut text = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        text.push_str(&rest[..start]);
+        let key = &rest[start + 1..start + end];
+        match payload.get(key) {
+            Some(Value::String(value)) => text.push_str(value),
+            Some(value) => text.push_str(&value.to_string()),
+            None => text.push_str(&rest[start..=start + end]),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    text.push_str(rest);
+    text
+}
+
+// Signature headers (URLs) or environment variables (commands) sent along
+// with a signed payload
+struct PayloadSignature {
+    signer: Pubkey,
+    timestamp: i64,
+    signature: Signature,
+}
+
+// Runs after a successful simulation; a failing hook aborts the send
+pub fn pre_send(config: &Config, transaction: &Transaction) -> Result<()> {
+    match &config.hooks.pre_send {
+        Some(hook) => run_hook(
+            config,
+            "pre_send",
+            hook,
+            &json!({
+                "event": "pre_send",
+                "transaction": transaction_details(transaction),
+            }),
+        ),
+        None => Ok(()),
+    }
+}
+
+// Runs after confirmation; failures are reported but don't fail the command
+pub fn post_confirm(config: &Config, transaction: &Transaction, signature: &Signature) {
+    if let Some(hook) = &config.hooks.post_confirm {
+        let payload = json!({
+            "event": "post_confirm",
+            "signature": signature.to_string(),
+            "transaction": transaction_details(transaction),
+        });
+        if let Err(e) = run_hook(config, "post_confirm", hook, &payload) {
+            eprintln!("Warning: {}", e);
+        }
+    }
+}
+
+// Deliver a notification to the notify hook and every matching route;
+// failures are reported but don't fail the command
+pub fn notify(config: &Config, payload: &Value) {
+    if let Some(hook) = &config.hooks.notify {
+        if let Err(e) = run_hook(config, "notify", hook, payload) {
+            eprintln!("Warning: {}", e);
+        }
+    }
+    for (index, route) in config.hooks.routes.iter().enumerate() {
+        let Some(delivered) = route.deliver(payload) else {
+            continue;
+        };
+        let name = format!("routes[{}]", index);
+        if let Err(e) = run_hook(config, &name, &route.hook, &delivered) {
+            eprintln!("Warning: {}", e);
+        }
+    }
+}
+
+pub fn transaction_details(transaction: &Transaction) -> Value {
+    let message = &transaction.message;
+    json!({
+        "signature": transaction.signatures.first().map(|s| s.to_string()),
+        "fee_payer": message.account_keys.first().map(|k| k.to_string()),
+        "recent_blockhash": message.recent_blockhash.to_string(),
+        "accounts": message
+            .account_keys
+            .iter()
+            .map(|k| k.to_string())
+            .collect::<Vec<_>>(),
+        "program_ids": message
+            .program_ids()
+            .iter()
+            .map(|k| k.to_string())
+            .collect::<Vec<_>>(),
+    })
+}
+
+// The message a payload signature covers; the timestamp stops old payloads
+// from being replayed as new ones
+fn signed_message(timestamp: i64, body: &str) -> String {
+    format!("{}.{}", timestamp, body)
+}
+
+fn sign_payload(config: &Config, body: &str) -> Result<Option<PayloadSignature>> {
+    let Some(path) = &config.hooks.signing_keypair else {
+        return Ok(None);
+    };
+    let keypair = config.sandbox.read_keypair(
+        &expand_home(path),
+        config.keystore.as_deref(),
+        &format!("hook signing keypair {}", path),
+    )?;
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    Ok(Some(PayloadSignature {
+        signer: keypair.pubkey(),
+        timestamp,
+        signature: keypair.sign_message(signed_message(timestamp, body).as_bytes()),
+    }))
+}
+
+// Check a received payload against its signature headers; `max_age` bounds
+// how old the timestamp may be, in seconds
+pub fn verify_payload(
+    body: &str,
+    signer: &str,
+    timestamp: i64,
+    signature: &str,
+    max_age: u64,
+) -> Result<()> {
+    let signer = Pubkey::from_str(signer).map_err(|_| anyhow!("Invalid signer '{}'", signer))?;
+    let signature =
+        Signature::from_str(signature).map_err(|_| anyhow!("Invalid signature '{}'", signature))?;
+    if !signature.verify(signer.as_ref(), signed_message(timestamp, body).as_bytes()) {
+        return Err(anyhow!("Signature does not match the payload and signer"));
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    if now.abs_diff(timestamp) > max_age {
+        return Err(anyhow!(
+            "Payload timestamp {} is more than {}s from now; possible replay",
+            timestamp,
+            max_age
+        ));
+    }
+    Ok(())
+}
+
+fn run_hook(config: &Config, name: &str, hook: &str, payload: &Value) -> Result<()> {
+    let body = payload.to_string();
+    let signed = sign_payload(config, &body)?;
+    if hook.starts_with("http://") || hook.starts_with("https://") {
+        let headers = match &signed {
+            Some(signed) => vec![
+                ("X-Escrow-Signer", signed.signer.to_string()),
+                ("X-Escrow-Timestamp", signed.timestamp.to_string()),
+                ("X-Escrow-Signature", signed.signature.to_string()),
+            ],
+            None => Vec::new(),
+        };
+        let what = format!("{} hook", name);
+        network::post(&what, hook, &headers, Body::JsonText(body))?;
+        return Ok(());
+    }
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(hook).env("ESCROW_CLI_HOOK", name);
+    if let Some(signed) = &signed {
+        command
+            .env("ESCROW_CLI_HOOK_SIGNER", signed.signer.to_string())
+            .env("ESCROW_CLI_HOOK_TIMESTAMP", signed.timestamp.to_string())
+            .env("ESCROW_CLI_HOOK_SIGNATURE", signed.signature.to_string());
+    }
+    let mut child = command
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("Failed to start {} hook: {}", name, e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // Hooks are free to ignore their input
+        if let Err(e) = stdin.write_all(body.as_bytes()) {
+            if e.kind() != ErrorKind::BrokenPipe {
+                return Err(anyhow!("Failed to write {} hook input: {}", name, e));
+            }
+        }
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow!("{} hook exited with {}", name, status));
+    }
+    Ok(())
+}
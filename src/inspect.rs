@@ -0,0 +1,140 @@
+use anyhow::{anyhow, Result};
+use solana_sdk::{
+    compute_budget, instruction::CompiledInstruction, pubkey::Pubkey, sanitize::Sanitize,
+    signature::Signature, system_instruction::SystemInstruction, system_program,
+    transaction::Transaction,
+};
+
+use crate::memo::MEMO_PROGRAM_ID;
+use crate::Context;
+
+// Print every instruction of a transaction that came from someone else, and
+// refuse it unless it only calls the escrow program on `escrow`, compute
+// budget, nonce advances and memos, and its existing signatures verify
+pub fn check_imported(
+    ctx: &Context,
+    transaction: &Transaction,
+    escrow: &Pubkey,
+    allow_unknown_programs: bool,
+) -> Result<()> {
+    // Every index below is only in range once the transaction is sanitized
+    transaction
+        .sanitize()
+        .map_err(|e| anyhow!("Imported transaction is malformed: {}", e))?;
+    let message = &transaction.message;
+    for (index, valid) in transaction.verify_with_results().iter().enumerate() {
+        if !valid && transaction.signatures[index] != Signature::default() {
+            return Err(anyhow!(
+                "Imported transaction has an invalid signature for {}",
+                message.account_keys[index]
+            ));
+        }
+    }
+    println!("Imported transaction:");
+    let mut unknown = Vec::new();
+    for (index, instruction) in message.instructions.iter().enumerate() {
+        let program = *instruction.program_id(&message.account_keys);
+        let described = describe(ctx, message.account_keys.as_slice(), instruction, escrow)?;
+        match &described {
+            Some(text) => println!("  #{} {}", index + 1, text),
+            None => {
+                println!(
+                    "  #{} unknown program {} ({} accounts, {} data bytes)",
+                    index + 1,
+                    program,
+                    instruction.accounts.len(),
+                    instruction.data.len()
+                );
+                unknown.push(program);
+            }
+        }
+    }
+    if !unknown.is_empty() && !allow_unknown_programs {
+        return Err(anyhow!(
+            "Imported transaction calls programs this CLI doesn't know ({}); pass \
+             --allow-unknown-programs to send it anyway",
+            unknown
+                .iter()
+                .map(Pubkey::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    Ok(())
+}
+
+// One line for an instruction of a known program; None for other programs.
+// Escrow instructions on a different escrow are an error, not a description.
+fn describe(
+    ctx: &Context,
+    account_keys: &[Pubkey],
+    instruction: &CompiledInstruction,
+    escrow: &Pubkey,
+) -> Result<Option<String>> {
+    let program = *account_keys
+        .get(instruction.program_id_index as usize)
+        .ok_or_else(|| anyhow!("Imported transaction names a program outside its accounts"))?;
+    let account = |position: usize| {
+        instruction
+            .accounts
+            .get(position)
+            .and_then(|index| account_keys.get(*index as usize).copied())
+    };
+    let data = instruction.data.as_slice();
+    if program == ctx.program_id {
+        let name = match data.first() {
+            Some(0) => "create_offer",
+            Some(1) => "join_offer",
+            Some(2) => "fund_escrow",
+            Some(3) => "confirm_escrow",
+            Some(4) => "arbiter_confirm",
+            Some(5) => "arbiter_cancel",
+            Some(6) => "close_escrow",
+            Some(8) => "mutual_cancel",
+            _ => {
+                return Err(anyhow!(
+                    "Imported transaction has an unknown escrow instruction"
+                ))
+            }
+        };
+        // The escrow follows the signers: buyer and seller for a mutual
+        // cancel, a single party otherwise
+        let target = account(if name == "mutual_cancel" { 2 } else { 1 });
+        if target != Some(*escrow) {
+            return Err(anyhow!(
+                "Imported {} acts on {}, not escrow {}",
+                name,
+                target.map_or("nothing".to_string(), |key| key.to_string()),
+                escrow
+            ));
+        }
+        return Ok(Some(format!("escrow {} on {}", name, escrow)));
+    }
+    if program == system_program::id() {
+        return match bincode::deserialize(data) {
+            Ok(SystemInstruction::AdvanceNonceAccount) => Ok(Some(format!(
+                "advance nonce account {}",
+                account(0).unwrap_or_default()
+            ))),
+            _ => Ok(None),
+        };
+    }
+    if program == compute_budget::id() {
+        // Borsh: a one-byte tag, then a u32 unit limit or u64 micro-lamport price
+        return Ok(match (data.first(), data.get(1..)) {
+            (Some(2), Some(limit)) if limit.len() == 4 => Some(format!(
+                "compute unit limit {}",
+                u32::from_le_bytes(limit.try_into()?)
+            )),
+            (Some(3), Some(price)) if price.len() == 8 => Some(format!(
+                "compute unit price {} micro-lamports",
+                u64::from_le_bytes(price.try_into()?)
+            )),
+            _ => None,
+        });
+    }
+    if program == MEMO_PROGRAM_ID {
+        return Ok(Some(format!("memo \"{}\"", String::from_utf8_lossy(data))));
+    }
+    Ok(None)
+}
@@ -0,0 +1,137 @@
+use anyhow::{anyhow, Result};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::transcript;
+
+// How long the Ctrl-C handler asks the RPC node about an in-flight
+// transaction before giving up on knowing its outcome
+const STATUS_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Transaction that has been handed to the RPC but not confirmed yet, with
+// the endpoint and commitment to look it up at
+struct InFlight {
+    signature: Signature,
+    escrow: Pubkey,
+    rpc_url: String,
+    commitment: CommitmentConfig,
+}
+
+static IN_FLIGHT: Mutex<Option<InFlight>> = Mutex::new(None);
+
+// Last transaction this run saw confirmed, as long as nothing else is
+// being built or sent after it
+static LANDED: Mutex<Option<Signature>> = Mutex::new(None);
+
+// On Ctrl-C, say what became of the transaction in flight, or that one had
+// already landed, and exit with the code the outcome deserves: 0 once the
+// transaction is confirmed, 1 when it failed, 130 when nothing was sent or
+// its fate is unknown
+pub fn install() -> Result<()> {
+    ctrlc::set_handler(|| {
+        let in_flight = IN_FLIGHT.lock().ok().and_then(|mut guard| guard.take());
+        let landed = LANDED.lock().map(|guard| *guard).unwrap_or(None);
+        eprintln!();
+        if let Some(in_flight) = in_flight {
+            let client = RpcClient::new_with_timeout_and_commitment(
+                in_flight.rpc_url.clone(),
+                STATUS_TIMEOUT,
+                in_flight.commitment,
+            );
+            match client.get_signature_status(&in_flight.signature) {
+                Ok(Some(Ok(()))) => {
+                    eprintln!(
+                        "Interrupted, but transaction {} was confirmed",
+                        in_flight.signature
+                    );
+                    transcript::record(format!("Interrupted after {} landed", in_flight.signature));
+                    transcript::exit(0);
+                }
+                Ok(Some(Err(e))) => {
+                    eprintln!(
+                        "Interrupted; transaction {} failed: {}",
+                        in_flight.signature, e
+                    );
+                    transcript::record(format!("Interrupted, {} failed", in_flight.signature));
+                    transcript::exit(1);
+                }
+                _ => {
+                    eprintln!("Interrupted while waiting for confirmation.");
+                    follow_up(&in_flight.signature, &in_flight.escrow);
+                }
+            }
+        } else if let Some(signature) = landed {
+            eprintln!("Interrupted after transaction {} was confirmed", signature);
+            transcript::record(format!("Interrupted after {} landed", signature));
+            transcript::exit(0);
+        }
+        transcript::record("Interrupted");
+        transcript::exit(130);
+    })
+    .map_err(|e| anyhow!("Failed to install Ctrl-C handler: {}", e))
+}
+
+// How to find out what became of a transaction we stopped waiting for
+pub fn follow_up(signature: &Signature, escrow: &Pubkey) {
+    eprintln!("The transaction may still land:");
+    eprintln!("  Signature: {}", signature);
+    eprintln!("  Escrow:    {}", escrow);
+    eprintln!("Check its status later with:");
+    eprintln!("  solana confirm {}", signature);
+    eprintln!("  escrow-cli info -e {}", escrow);
+}
+
+// A sent transaction whose confirmation wasn't seen in time
+#[derive(Debug)]
+pub struct Unconfirmed {
+    pub signature: Signature,
+    pub waited: Duration,
+}
+
+impl fmt::Display for Unconfirmed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Transaction {} was not confirmed within {}s",
+            self.signature,
+            self.waited.as_secs()
+        )
+    }
+}
+
+impl std::error::Error for Unconfirmed {}
+
+pub fn track(signature: Signature, escrow: Pubkey, client: &RpcClient) {
+    building();
+    if let Ok(mut guard) = IN_FLIGHT.lock() {
+        *guard = Some(InFlight {
+            signature,
+            escrow,
+            rpc_url: client.url(),
+            commitment: client.commitment(),
+        });
+    }
+}
+
+pub fn landed(signature: Signature) {
+    if let Ok(mut guard) = LANDED.lock() {
+        *guard = Some(signature);
+    }
+}
+
+// A command calls this before building another transaction, so a Ctrl-C
+// from here on no longer counts the earlier one as the outcome
+pub fn building() {
+    if let Ok(mut guard) = LANDED.lock() {
+        *guard = None;
+    }
+}
+
+pub fn clear() {
+    if let Ok(mut guard) = IN_FLIGHT.lock() {
+        *guard = None;
+    }
+}
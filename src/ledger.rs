@@ -0,0 +1,645 @@
+use anyhow::{anyhow, Result};
+use clap::{Args, ValueEnum};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::account::{EscrowAccount, EscrowState};
+use crate::archive::ArchiveRecord;
+use crate::amount::{parse_amount, Lamports};
+use crate::config::{config_dir, expand_home, Config};
+use crate::duration::parse_duration;
+use crate::pipe;
+use crate::seal;
+use crate::sla;
+use crate::Context;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    /// One escrow per line
+    Jsonl,
+    /// The whole ledger as a single JSON document
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ListSort {
+    /// Escrow address, the order `--before`/`--after` page through
+    Address,
+    Amount,
+    State,
+    /// Fiat price per SOL (offers only)
+    Rate,
+}
+
+// Paging, state and amount filters and sort order shared by `list` and
+// `offers`
+#[derive(Args, Debug)]
+pub struct PageFilter {
+    /// Only show escrows in this state (repeatable)
+    #[arg(short = 's', long = "state", value_enum)]
+    pub states: Vec<EscrowState>,
+    /// Minimum amount, in lamports or with a SOL suffix
+    #[arg(long, value_parser = parse_amount)]
+    pub min_amount: Option<Lamports>,
+    /// Maximum amount, in lamports or with a SOL suffix
+    #[arg(long, value_parser = parse_amount)]
+    pub max_amount: Option<Lamports>,
+    /// Only escrows whose address sorts after this one (next page)
+    #[arg(long)]
+    pub after: Option<String>,
+    /// Only escrows whose address sorts before this one (previous page)
+    #[arg(long)]
+    pub before: Option<String>,
+    /// Sort order [default: address for `list`, rate for `offers`]
+    #[arg(long, value_enum)]
+    pub sort: Option<ListSort>,
+    /// Sort in descending order
+    #[arg(long)]
+    pub desc: bool,
+    /// Show at most this many escrows
+    #[arg(short = 'n', long)]
+    pub limit: Option<usize>,
+}
+
+impl PageFilter {
+    // Whether a live escrow passes the state and amount filters
+    pub fn matches(&self, account: &EscrowAccount) -> bool {
+        (self.states.is_empty() || self.states.contains(&account.state))
+            && self.min_amount.map_or(true, |min| account.amount >= min)
+            && self.max_amount.map_or(true, |max| account.amount <= max)
+    }
+
+    // Whether the address falls between --after and --before
+    pub fn in_range(&self, escrow: &str) -> bool {
+        let after = self.after.as_deref().map_or(true, |after| escrow > after);
+        after && self.before.as_deref().map_or(true, |before| escrow < before)
+    }
+
+    // The flag that pages on from `last`, once a full page sorted by address
+    // has been shown
+    pub fn next_page(&self, shown: usize, last: &str) -> Option<String> {
+        let full = self.limit.is_some_and(|limit| shown == limit);
+        let cursor = if self.desc { "before" } else { "after" };
+        full.then(|| format!("Next page: --{} {}", cursor, last))
+    }
+}
+
+// Which ledger escrows `list` shows, and in what order
+#[derive(Args, Debug)]
+pub struct ListFilter {
+    #[command(flatten)]
+    pub page: PageFilter,
+    /// Only show escrows carrying this tag (repeatable)
+    #[arg(short = 't', long = "tag", value_parser = parse_tag)]
+    pub tags: Vec<String>,
+    /// Only open escrows that have sat in their state at least this long
+    #[arg(long, value_parser = parse_duration)]
+    pub stale: Option<Duration>,
+}
+
+impl ListFilter {
+    // Closed and unreadable escrows only pass when no state, amount or
+    // staleness filter is given
+    fn matches(&self, account: Option<&EscrowAccount>) -> bool {
+        let Some(account) = account else {
+            return self.page.states.is_empty()
+                && self.page.min_amount.is_none()
+                && self.page.max_amount.is_none()
+                && self.stale.is_none();
+        };
+        if self.stale.is_some() && !is_open(account) {
+            return false;
+        }
+        self.page.matches(account)
+    }
+}
+
+// Not yet settled: still waiting on a party
+fn is_open(account: &EscrowAccount) -> bool {
+    matches!(
+        account.state,
+        EscrowState::Created | EscrowState::Initialized | EscrowState::Funded
+    )
+}
+
+// One line of a JSONL export
+#[derive(Deserialize, Serialize)]
+struct ExportLine {
+    escrow: String,
+    #[serde(flatten)]
+    entry: LedgerEntry,
+}
+
+// Local record of the escrows this machine has interacted with
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Ledger {
+    pub escrows: BTreeMap<String, LedgerEntry>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LedgerEntry {
+    // Local labels such as `electronics` or `repeat-customer`
+    pub tags: BTreeSet<String>,
+    pub costs: Vec<CostRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archive: Option<ArchiveRecord>,
+    // Escrow this one is part of, e.g. the deposit of a larger deal
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    // What this part is, e.g. `deposit` or `milestone-2`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl LedgerEntry {
+    // True when the entry carries every one of `tags`
+    pub fn has_tags(&self, tags: &[String]) -> bool {
+        tags.iter().all(|tag| self.tags.contains(tag))
+    }
+}
+
+// What one confirmed transaction spent, in lamports. Rent and principal are
+// signed: positive when deposited into the escrow, negative when paid out.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CostRecord {
+    pub signature: String,
+    pub action: String,
+    pub fee: u64,
+    pub priority_fee: u64,
+    pub rent: i64,
+    pub principal: i64,
+    pub block_time: Option<i64>,
+}
+
+impl Ledger {
+    // A missing ledger file is an empty ledger
+    pub fn load(path: &Path) -> Result<Ledger> {
+        if !path.exists() {
+            return Ok(Ledger::default());
+        }
+        let contents = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read ledger {}: {}", path.display(), e))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| anyhow!("Invalid ledger {}: {}", path.display(), e))
+    }
+
+    // Write to a temporary file first so an interrupted save can't truncate it
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn entry_mut(&mut self, escrow: &str) -> &mut LedgerEntry {
+        self.escrows.entry(escrow.to_string()).or_default()
+    }
+
+    // Union tags and add cost records whose signature isn't known yet;
+    // returns the number of new cost records
+    pub fn merge(&mut self, escrow: &str, other: LedgerEntry) -> usize {
+        let entry = self.entry_mut(escrow);
+        entry.tags.extend(other.tags);
+        if entry.archive.is_none() {
+            entry.archive = other.archive;
+        }
+        if entry.parent.is_none() {
+            entry.parent = other.parent;
+            entry.label = entry.label.take().or(other.label);
+        }
+        let mut added = 0;
+        for cost in other.costs {
+            if !entry.costs.iter().any(|known| known.signature == cost.signature) {
+                entry.costs.push(cost);
+                added += 1;
+            }
+        }
+        added
+    }
+
+    // Escrows linked directly under `escrow`
+    pub fn children<'a>(&'a self, escrow: &'a str) -> impl Iterator<Item = &'a String> + 'a {
+        self.escrows
+            .iter()
+            .filter(move |(_, entry)| entry.parent.as_deref() == Some(escrow))
+            .map(|(child, _)| child)
+    }
+
+    // `escrow` followed by the escrows above it, up to the top of the deal;
+    // stops at a repeat in case an import linked a cycle
+    pub fn lineage<'a>(&'a self, escrow: &'a str) -> Vec<&'a str> {
+        let mut lineage = vec![escrow];
+        let mut current = escrow;
+        while let Some(parent) = self
+            .escrows
+            .get(current)
+            .and_then(|entry| entry.parent.as_deref())
+        {
+            if lineage.contains(&parent) {
+                break;
+            }
+            lineage.push(parent);
+            current = parent;
+        }
+        lineage
+    }
+}
+
+// `ledger` from the config, or ~/.config/escrow-cli/ledger.json. Under a
+// profile without its own `ledger`, the file name carries the profile name
+// (ledger-mainnet.json), so escrows from different clusters don't mix.
+pub fn ledger_path(config: &Config) -> Result<PathBuf> {
+    let path = match &config.ledger {
+        Some(path) => expand_home(path),
+        None => config_dir()
+            .map(|dir| dir.join("ledger.json"))
+            .ok_or_else(|| {
+                anyhow!("Cannot locate a config directory; set `ledger` in the config")
+            })?,
+    };
+    let Some(profile) = &config.ledger_profile else {
+        return Ok(path);
+    };
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, profile, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, profile),
+    };
+    Ok(path.with_file_name(name))
+}
+
+// Load, change and save the ledger in one step
+pub fn update(config: &Config, change: impl FnOnce(&mut Ledger)) -> Result<()> {
+    let path = ledger_path(config)?;
+    config.sandbox.check_write(&path)?;
+    let mut ledger = Ledger::load(&path)?;
+    change(&mut ledger);
+    ledger.save(&path)
+}
+
+// Tags are single words so they survive shell quoting and listings
+pub fn parse_tag(input: &str) -> Result<String, String> {
+    let tag = input.trim();
+    if tag.is_empty() || tag.contains(|c: char| c.is_whitespace() || c == ',') {
+        return Err(format!("Invalid tag '{}': use a single word, e.g. repeat-customer", input));
+    }
+    Ok(tag.to_string())
+}
+
+// Add (or with `remove`, drop) local tags on an escrow
+pub fn tag(ctx: &Context, escrow_account: &str, tags: &[String], remove: bool) -> Result<()> {
+    Pubkey::from_str(escrow_account)?;
+    update(&ctx.config, |ledger| {
+        let entry = ledger.entry_mut(escrow_account);
+        for tag in tags {
+            if remove {
+                entry.tags.remove(tag);
+            } else {
+                entry.tags.insert(tag.clone());
+            }
+        }
+    })?;
+    if !tags.is_empty() {
+        println!("{} tags {}", if remove { "Removed" } else { "Added" }, tags.join(", "));
+    }
+    Ok(())
+}
+
+// Make `escrow` part of the deal under `parent`, or with `parent` None,
+// detach it
+pub fn link(
+    ctx: &Context,
+    escrow_account: &str,
+    parent: Option<&str>,
+    label: Option<&str>,
+) -> Result<()> {
+    Pubkey::from_str(escrow_account)?;
+    let path = ledger_path(&ctx.config)?;
+    ctx.config.sandbox.check_write(&path)?;
+    let mut ledger = Ledger::load(&path)?;
+    if let Some(parent) = parent {
+        Pubkey::from_str(parent).map_err(|_| anyhow!("Invalid parent escrow '{}'", parent))?;
+        if ledger.lineage(parent).contains(&escrow_account) {
+            return Err(anyhow!("{} is already above {} in the deal", escrow_account, parent));
+        }
+    }
+    let entry = ledger.entry_mut(escrow_account);
+    entry.parent = parent.map(str::to_string);
+    entry.label = label.map(str::to_string);
+    ledger.save(&path)?;
+    match parent {
+        Some(parent) => println!("Linked {} under {}", escrow_account, parent),
+        None => println!("Unlinked {}", escrow_account),
+    }
+    Ok(())
+}
+
+// Print the deal `escrow` belongs to as a tree with live states, and the
+// amount across all parts
+pub fn show_deal(ctx: &Context, escrow_account: &str) -> Result<()> {
+    // Without a ledger location there is nothing linked
+    let Ok(path) = ledger_path(&ctx.config) else {
+        return Ok(());
+    };
+    // Shown under the on-chain details, so a ledger or RPC problem only warns
+    let ledger = match Ledger::load(&path) {
+        Ok(ledger) => ledger,
+        Err(e) => {
+            eprintln!("Warning: could not load the ledger: {}", e);
+            return Ok(());
+        }
+    };
+    let lineage = ledger.lineage(escrow_account);
+    let root = lineage[lineage.len() - 1];
+    if lineage.len() == 1 && ledger.children(root).next().is_none() {
+        return Ok(());
+    }
+    let mut parts = Vec::new();
+    collect_parts(&ledger, root, 0, &mut parts);
+    let accounts = match deal_accounts(ctx, &parts) {
+        Ok(accounts) => accounts,
+        Err(e) => {
+            eprintln!("Warning: could not fetch the linked escrows: {}", e);
+            return Ok(());
+        }
+    };
+    // Settled parts still exist until closed, but their amount is no longer
+    // in play
+    let (mut total, mut open) = (Lamports::ZERO, 0);
+    println!("Linked deal:");
+    for ((escrow, depth), account) in parts.iter().zip(accounts) {
+        let label = ledger.escrows.get(*escrow).and_then(|entry| entry.label.as_deref());
+        let status = match account.map(|account| EscrowAccount::unpack(&account.data)) {
+            Some(Ok(escrow)) => {
+                if is_open(&escrow) {
+                    total = total.checked_add(escrow.amount)?;
+                    open += 1;
+                }
+                format!("{} {} SOL", escrow.state, escrow.amount)
+            }
+            Some(Err(e)) => format!("unreadable ({})", e),
+            None => "closed".to_string(),
+        };
+        let marker = if *escrow == escrow_account { "*" } else { " " };
+        println!(
+            "{} {}{}  {}{}",
+            marker,
+            "  ".repeat(*depth),
+            escrow,
+            status,
+            label.map(|label| format!("  [{}]", label)).unwrap_or_default()
+        );
+    }
+    println!("  Total: {} SOL in {} of {} escrows still open", total, open, parts.len());
+    Ok(())
+}
+
+fn deal_accounts(ctx: &Context, parts: &[(&str, usize)]) -> Result<Vec<Option<Account>>> {
+    let pubkeys = parts
+        .iter()
+        .map(|(escrow, _)| Pubkey::from_str(escrow))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut accounts = Vec::new();
+    for chunk in pubkeys.chunks(100) {
+        accounts.extend(ctx.get_multiple_accounts(chunk)?);
+    }
+    Ok(accounts)
+}
+
+// Depth-first walk of a deal, parents before their children
+fn collect_parts<'a>(
+    ledger: &'a Ledger,
+    escrow: &'a str,
+    depth: usize,
+    parts: &mut Vec<(&'a str, usize)>,
+) {
+    if parts.iter().any(|(part, _)| *part == escrow) {
+        return;
+    }
+    parts.push((escrow, depth));
+    for child in ledger.children(escrow) {
+        collect_parts(ledger, child, depth + 1, parts);
+    }
+}
+
+// Ledger entry with its live account, if it still exists
+type ListRow<'a> = (&'a String, &'a LedgerEntry, Option<Result<EscrowAccount, String>>);
+
+// List escrows known to the local ledger with their tags and live state.
+// Accounts are fetched a page of 100 at a time; in address order the scan
+// stops as soon as `limit` escrows have matched.
+pub fn list(ctx: &Context, filter: &ListFilter) -> Result<()> {
+    let ledger = Ledger::load(&ledger_path(&ctx.config)?)?;
+    let mut entries: Vec<(&String, &LedgerEntry)> = ledger
+        .escrows
+        .iter()
+        .filter(|(escrow, entry)| entry.has_tags(&filter.tags) && filter.page.in_range(escrow))
+        .collect();
+    let sort = filter.page.sort.unwrap_or(ListSort::Address);
+    if sort == ListSort::Rate {
+        return Err(anyhow!("--sort rate only applies to offers"));
+    }
+    let streaming = sort == ListSort::Address;
+    if streaming && filter.page.desc {
+        entries.reverse();
+    }
+    let mut rows: Vec<ListRow> = Vec::new();
+    // Time in state for the open escrows listed. Each one walks signatures,
+    // so only --stale asks before truncating; otherwise just the rows shown.
+    let mut ages: HashMap<&str, Duration> = HashMap::new();
+    for page in entries.chunks(100) {
+        if streaming && filter.page.limit.is_some_and(|limit| rows.len() >= limit) {
+            break;
+        }
+        let pubkeys = page
+            .iter()
+            .map(|(escrow, _)| Pubkey::from_str(escrow))
+            .collect::<Result<Vec<_>, _>>()?;
+        for ((escrow, entry), account) in page.iter().zip(ctx.get_multiple_accounts(&pubkeys)?) {
+            let account = account
+                .map(|account| EscrowAccount::unpack(&account.data).map_err(|e| e.to_string()));
+            let live = account.as_ref().and_then(|account| account.as_ref().ok());
+            if !filter.matches(live) {
+                continue;
+            }
+            if let Some(stale) = filter.stale {
+                let age = match live.is_some_and(is_open) {
+                    true => sla::state_age(ctx, &Pubkey::from_str(escrow)?)?,
+                    false => None,
+                };
+                let Some(age) = age.filter(|age| *age >= stale) else {
+                    continue;
+                };
+                ages.insert(escrow.as_str(), age);
+            }
+            rows.push((escrow, entry, account));
+        }
+    }
+    if !streaming {
+        rows.sort_by_key(|(_, _, account)| match account {
+            Some(Ok(escrow)) => match sort {
+                ListSort::Amount => (0, escrow.amount, escrow.state),
+                _ => (0, Lamports::ZERO, escrow.state),
+            },
+            // Closed escrows sort last
+            _ => (1, Lamports::ZERO, EscrowState::Uninitialized),
+        });
+        if filter.page.desc {
+            rows.reverse();
+        }
+    }
+    rows.truncate(filter.page.limit.unwrap_or(usize::MAX));
+    if rows.is_empty() {
+        println!("No matching escrows in the ledger");
+        return Ok(());
+    }
+    if filter.stale.is_none() {
+        for (escrow, _, account) in &rows {
+            if !matches!(account, Some(Ok(account)) if is_open(account)) {
+                continue;
+            }
+            if let Some(age) = sla::state_age(ctx, &Pubkey::from_str(escrow)?)? {
+                ages.insert(escrow.as_str(), age);
+            }
+        }
+    }
+    // Linked parts print indented under their parent when both are listed
+    let listed: BTreeSet<&str> = rows.iter().map(|(escrow, _, _)| escrow.as_str()).collect();
+    let mut ordered: Vec<(&ListRow, usize)> = Vec::new();
+    for row in &rows {
+        if !row.1.parent.as_deref().is_some_and(|parent| listed.contains(parent)) {
+            push_with_children(&rows, row, 0, &mut ordered);
+        }
+    }
+    // Anything left is on a cycle an import brought in
+    for row in &rows {
+        push_with_children(&rows, row, 0, &mut ordered);
+    }
+    for ((escrow, entry, account), depth) in ordered {
+        let status = match account {
+            Some(Ok(account)) => {
+                let mut status =
+                    format!("{:<10} {:>14} SOL", account.state.to_string(), account.amount);
+                if let Some(age) = ages.get(escrow.as_str()) {
+                    status = format!("{}  {}", status, ctx.config.sla.render(account.state, *age));
+                }
+                status
+            }
+            Some(Err(e)) => format!("unreadable ({})", e),
+            None => match &entry.archive {
+                Some(archive) => format!(
+                    "closed, archived as {}",
+                    archive.account["state"].as_str().unwrap_or("?")
+                ),
+                None => "closed".to_string(),
+            },
+        };
+        let mut tags = entry.tags.iter().cloned().collect::<Vec<_>>().join(", ");
+        if let Some(label) = &entry.label {
+            tags = format!("[{}] {}", label, tags);
+        }
+        println!("{}{}  {}  {}", "  ".repeat(depth), escrow, status, tags);
+    }
+    if let Some((last, _, _)) = rows.last().filter(|_| streaming) {
+        if let Some(next) = filter.page.next_page(rows.len(), last) {
+            println!("{}", next);
+        }
+    }
+    Ok(())
+}
+
+fn push_with_children<'r, 'a>(
+    rows: &'r [ListRow<'a>],
+    row: &'r ListRow<'a>,
+    depth: usize,
+    ordered: &mut Vec<(&'r ListRow<'a>, usize)>,
+) {
+    if ordered.iter().any(|(listed, _)| listed.0 == row.0) {
+        return;
+    }
+    ordered.push((row, depth));
+    for child in rows.iter().filter(|child| child.1.parent.as_ref() == Some(row.0)) {
+        push_with_children(rows, child, depth + 1, ordered);
+    }
+}
+
+// Write the ledger to `out` (stdout when omitted), optionally encrypted with
+// an age passphrase
+pub fn export(ctx: &Context, out: Option<&str>, format: ExportFormat, encrypt: bool) -> Result<()> {
+    let ledger = Ledger::load(&ledger_path(&ctx.config)?)?;
+    let count = ledger.escrows.len();
+    let mut contents = match format {
+        ExportFormat::Json => serde_json::to_string_pretty(&ledger)?,
+        ExportFormat::Jsonl => {
+            let mut lines = String::new();
+            for (escrow, entry) in ledger.escrows {
+                lines.push_str(&serde_json::to_string(&ExportLine { escrow, entry })?);
+                lines.push('\n');
+            }
+            lines
+        }
+    }
+    .into_bytes();
+    if encrypt {
+        contents = seal::encrypt(&contents, "Ledger")?;
+    }
+    match out {
+        Some(path) => {
+            ctx.config.sandbox.check_write(Path::new(path))?;
+            fs::write(path, &contents)?;
+            eprintln!("Exported {} escrows to {}", count, path);
+        }
+        None => io::stdout().write_all(&contents)?,
+    }
+    Ok(())
+}
+
+// Merge an export (JSONL or JSON, plain or age-encrypted) into the ledger
+pub fn import(ctx: &Context, file: &str) -> Result<()> {
+    let mut contents = pipe::read_input(file)?;
+    if seal::is_encrypted(&contents) {
+        contents = seal::decrypt(&contents, file, "Ledger")?;
+    }
+    let text = String::from_utf8(contents)?;
+    // A JSON export is one document with an `escrows` map; anything else is
+    // treated as JSONL
+    let whole = serde_json::from_str::<serde_json::Value>(&text).ok();
+    let entries: Vec<(String, LedgerEntry)> = match whole {
+        Some(value) if value.get("escrows").is_some() => {
+            serde_json::from_value::<Ledger>(value)?.escrows.into_iter().collect()
+        }
+        _ => text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+            .map(|(index, line)| {
+                serde_json::from_str::<ExportLine>(line)
+                    .map(|line| (line.escrow, line.entry))
+                    .map_err(|e| anyhow!("{} line {}: {}", file, index + 1, e))
+            })
+            .collect::<Result<_>>()?,
+    };
+    for (escrow, _) in &entries {
+        Pubkey::from_str(escrow).map_err(|_| anyhow!("Invalid escrow address '{}'", escrow))?;
+    }
+    let escrows = entries.len();
+    let mut added = 0;
+    update(&ctx.config, |ledger| {
+        for (escrow, entry) in entries {
+            added += ledger.merge(&escrow, entry);
+        }
+    })?;
+    println!("Imported {} escrows, {} new cost records", escrows, added);
+    Ok(())
+}
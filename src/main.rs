@@ -1,78 +1,664 @@
 use anyhow::{anyhow, Result};
-use clap::{Parser, Subcommand};
-use solana_client::rpc_client::RpcClient;
+use clap::{Args, Parser, Subcommand};
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{
+        RpcAccountInfoConfig, RpcSendTransactionConfig, RpcSimulateTransactionAccountsConfig,
+        RpcSimulateTransactionConfig,
+    },
+};
 use solana_sdk::{
-    commitment_config::CommitmentConfig,
+    account::Account,
     instruction::{AccountMeta, Instruction},
-    message::Message,
     pubkey::Pubkey,
-    signature::{read_keypair_file, Signature, Signer},
+    signature::{Keypair, Signature, Signer},
     system_instruction,
     transaction::Transaction,
 };
+use solana_transaction_status::UiTransactionEncoding;
+use std::cell::Cell;
 use std::str::FromStr;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+// Everything the commands print also goes to the --transcript, if any
+macro_rules! println {
+    () => {
+        std::println!()
+    };
+    ($($arg:tt)*) => {{
+        let line = format!($($arg)*);
+        std::println!("{}", line);
+        $crate::transcript::output(&line);
+    }};
+}
+
+macro_rules! eprintln {
+    () => {
+        std::eprintln!()
+    };
+    ($($arg:tt)*) => {{
+        let line = format!($($arg)*);
+        std::eprintln!("{}", line);
+        $crate::transcript::output(&line);
+    }};
+}
+
+mod account;
+mod actions;
+mod amount;
+mod archive;
+mod audit;
+mod balance;
+mod blink;
+mod bot;
+mod build;
+mod cache;
+mod chaos;
+mod compat;
+mod config;
+mod costs;
+mod decision;
+mod derive;
+mod dispute;
+mod duration;
+mod envelope;
+mod filter;
+mod fingerprint;
+mod history;
+mod hooks;
+mod inspect;
+mod interrupt;
+mod ledger;
+mod memo;
+mod network;
+mod note;
+mod onboarding;
+mod packet;
+mod pipe;
+mod plugin;
+mod preflight;
+mod preview;
+mod quote;
+mod rpc;
+mod safety;
+mod sandbox;
+mod sla;
+mod schedule;
+mod seal;
+mod search;
+mod selftest;
+mod storage;
+mod swap;
+mod sweep;
+mod terms;
+mod totp;
+mod transcript;
+mod trust;
+mod update;
+mod vacation;
+mod vectors;
+mod verdict;
+mod version;
+mod watch;
+
+use account::{unpack_state, EscrowAccount, EscrowState, ESCROW_ACCOUNT_SIZE, STATE_SLICE};
+use amount::{parse_amount, Lamports};
+use build::PartiallySignedTx;
+use config::Config;
+use duration::parse_duration;
 
 const PROGRAM_ID: &str = "5dkhUQ8PtXMnyQLzmg1HquD7dypQv2xQqdw49Q8kEqf3";
-const ESCROW_ACCOUNT_SIZE: usize = 106; // 32+32+32+8+1+1 = 106 bytes
+const DEFAULT_RPC_URL: &str = "https://solana-devnet.g.alchemy.com/v2/h1IAKlzdhlhF0Yo8w9ajfdTTzVsAddJ5";
 
 #[derive(Parser, Debug)]
 #[command(version, about)]
 struct Cli {
+    /// Path to the config file (defaults to ~/.config/escrow-cli/config.toml)
+    #[arg(long, global = true)]
+    config: Option<String>,
+    /// Named profile from the config file
+    #[arg(short = 'p', long, global = true)]
+    profile: Option<String>,
+    /// Refuse to sign or send any transaction
+    #[arg(long, global = true)]
+    read_only: bool,
+    /// Don't accept RPC responses from nodes behind this slot
+    #[arg(long, global = true)]
+    min_context_slot: Option<u64>,
+    /// Simulate and preview fund movements without sending anything
+    #[arg(long, global = true)]
+    dry_run: bool,
+    /// Refuse every request to a host other than the RPC node (indexer,
+    /// URL hooks, Jupiter, storage gateways, release checks)
+    #[arg(long, global = true)]
+    no_network_except_rpc: bool,
+    /// Give up on each RPC request, and on waiting for a confirmation, after
+    /// this long (e.g. 30s); an unconfirmed send still prints its signature
+    #[arg(long, global = true, value_parser = parse_duration)]
+    timeout: Option<Duration>,
+    /// Write a transcript of the session (command, RPC endpoint,
+    /// transactions, output, outcome) for support; paths, URLs and text are
+    /// redacted
+    #[arg(long, global = true, value_name = "FILE")]
+    transcript: Option<String>,
+    /// Leave the transcript unredacted
+    #[arg(long, global = true, requires = "transcript")]
+    transcript_unredacted: bool,
+    /// Print amounts as plain digits with a `.` decimal point, ignoring the
+    /// locale's digit grouping, for scripts
+    #[arg(long, global = true)]
+    raw: bool,
     #[command(subcommand)]
     command: Command,
 }
 
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Print the config file with secrets redacted
+    Show,
+    /// Set a value, e.g. `config set rpc_url https://...` or
+    /// `config set profiles.work.read_only true`
+    Set { key: String, value: String },
+}
+
+#[derive(Subcommand, Debug)]
+enum PacketCommand {
+    /// Check a deal packet's fields and arbiter endorsement
+    Validate {
+        #[arg(short = 'f', long)]
+        file: String,
+    },
+    /// Endorse a deal packet as its arbiter
+    Sign {
+        #[arg(short = 'f', long)]
+        file: String,
+        #[arg(short = 'k', long)]
+        arbiter_keypair: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum WebhookCommand {
+    /// Check a received hook payload against its X-Escrow-* signature headers
+    Verify {
+        /// X-Escrow-Signer: pubkey of the sending CLI's signing keypair
+        #[arg(long)]
+        signer: String,
+        /// X-Escrow-Timestamp
+        #[arg(long)]
+        timestamp: i64,
+        /// X-Escrow-Signature
+        #[arg(long)]
+        signature: String,
+        /// File holding the exact request body (`-` for stdin)
+        #[arg(short = 'f', long, default_value = "-")]
+        file: String,
+        /// Reject payloads whose timestamp is further than this from now
+        #[arg(long, value_parser = parse_duration, default_value = "5m")]
+        max_age: Duration,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ArbiterCommand {
+    /// Publish a signed note to both parties, shown in `info` and `next`
+    Note {
+        #[arg(short = 'a', long)]
+        arbiter_keypair: String,
+        #[command(flatten)]
+        escrow_account: EscrowArg,
+        /// The note, e.g. "need delivery proof by Friday"
+        #[arg(short = 'm', long)]
+        message: String,
+    },
+    /// Sign a decision record for an arbiter_confirm or arbiter_cancel
+    /// transaction, checkable offline with `verify-decision`
+    DecisionRecord {
+        #[arg(short = 'a', long)]
+        arbiter_keypair: String,
+        #[command(flatten)]
+        escrow_account: EscrowArg,
+        /// Signature of the transaction that carried out the verdict
+        #[arg(short = 't', long)]
+        transaction: String,
+        /// Written rationale; the record commits to its SHA-256
+        #[arg(short = 'r', long)]
+        rationale: Option<String>,
+        /// Output file, or `-` for stdout
+        #[arg(short = 'o', long, default_value = "-")]
+        out: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TotpCommand {
+    /// Create the TOTP secret for an authenticator app and store it
+    /// encrypted in the keystore
+    Setup,
+}
+
+#[derive(Subcommand, Debug)]
+enum CompatCommand {
+    /// Rebuild every instruction and account from an older release's
+    /// recorded test vector inputs and diff the encodings against its output
+    Check {
+        /// Release recorded in compat/<RELEASE>.json and built in (v0.1.0),
+        /// or a vectors file written by that release's `test-vectors`
+        #[arg(long, value_name = "RELEASE")]
+        against: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum RpcCommand {
+    /// Measure latency and slot lag of rpc_url and every rpc_endpoints entry
+    Probe,
+}
+
+#[derive(Subcommand, Debug)]
+enum DevCommand {
+    /// Proxy JSON-RPC to the configured node while injecting latency, 429s,
+    /// dropped confirmations and stale slots; point another profile's
+    /// rpc_url at it
+    ChaosProxy {
+        #[arg(long, default_value = "127.0.0.1:8999")]
+        listen: String,
+        /// Add up to this many milliseconds before each request
+        #[arg(long, default_value_t = 0)]
+        latency_ms: u64,
+        /// Share of requests answered with HTTP 429 (0 to 1)
+        #[arg(long, value_parser = chaos::parse_rate, default_value = "0")]
+        rate_limit: f64,
+        /// Share of getSignatureStatuses calls answered as not yet seen
+        #[arg(long, value_parser = chaos::parse_rate, default_value = "0")]
+        drop_confirmations: f64,
+        /// Share of calls answered as a node 150 slots behind
+        #[arg(long, value_parser = chaos::parse_rate, default_value = "0")]
+        stale_slots: f64,
+        /// Seed for the fault sequence, to replay a run
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum BotCommand {
+    /// Watch for open offers matching a bot config and join them as seller
+    Match {
+        /// Bot config (TOML): seller_keypair, arbiters, min_amount,
+        /// max_amount, interval, max_joins
+        #[arg(short = 'f', long)]
+        file: String,
+        /// Scan once instead of watching
+        #[arg(long)]
+        once: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum LedgerCommand {
+    /// Write the local ledger to a file or stdout
+    Export {
+        #[arg(short = 'o', long)]
+        out: Option<String>,
+        #[arg(long, value_enum, default_value = "jsonl")]
+        format: ledger::ExportFormat,
+        /// Encrypt with a passphrase (age); read from
+        /// $ESCROW_CLI_LEDGER_PASSPHRASE or prompted for
+        #[arg(long)]
+        encrypt: bool,
+    },
+    /// Merge an export into the local ledger, skipping known transactions
+    Import {
+        /// Export file (`-` for stdin)
+        file: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum DisputeCommand {
+    /// Show an escrow with its committed terms and fetch attached documents
+    Show {
+        #[arg(short = 'e', long)]
+        escrow_account: String,
+        /// Directory to save fetched documents into
+        #[arg(short = 'o', long)]
+        out: Option<String>,
+    },
+    /// Package everything about an escrow into a zip archive for review
+    Bundle {
+        #[arg(short = 'e', long)]
+        escrow_account: String,
+        #[arg(short = 'o', long)]
+        out: String,
+        /// Local files (e.g. notes submitted by the parties) to include
+        #[arg(short = 'n', long = "note")]
+        notes: Vec<String>,
+    },
+}
+
+// Escrow address, given positionally (`info <ESCROW>`) or with -e
+#[derive(Args, Debug)]
+struct EscrowArg {
+    /// Escrow account address
+    #[arg(value_name = "ESCROW", required_unless_present = "escrow_account")]
+    escrow: Option<String>,
+    #[arg(short = 'e', long, conflicts_with = "escrow")]
+    escrow_account: Option<String>,
+}
+
+impl EscrowArg {
+    fn value(&self) -> &str {
+        self.escrow
+            .as_deref()
+            .or(self.escrow_account.as_deref())
+            .unwrap_or_default()
+    }
+}
+
+impl Command {
+    // Commands that sign and send transactions
+    fn is_mutating(&self) -> bool {
+        matches!(
+            self,
+            Command::CreateOffer { .. }
+                | Command::JoinOffer { .. }
+                | Command::Fund { .. }
+                | Command::Confirm { .. }
+                | Command::ReleaseScheduled { .. }
+                | Command::ArbiterConfirm { .. }
+                | Command::ArbiterCancel { .. }
+                | Command::MutualCancel { .. }
+                | Command::CosignCancel { .. }
+                | Command::Close { .. }
+                | Command::Sweep { .. }
+                | Command::AttachTerms { .. }
+                | Command::Archive {
+                    anchor_keypair: Some(_),
+                    ..
+                }
+                | Command::ArbiterAway { .. }
+                | Command::Arbiter {
+                    command: ArbiterCommand::Note { .. }
+                }
+                | Command::Bot { .. }
+                | Command::SelfTest { .. }
+        )
+    }
+}
+
+// How long to wait for a sent transaction to reach the client commitment,
+// unless --timeout ends the wait sooner
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(90);
+
+// Shared state handed to every command
+struct Context {
+    client: RpcClient,
+    program_id: Pubkey,
+    config: Config,
+    // Reads and sends must come from a node that has seen this slot; raised
+    // after every confirmed transaction so follow-up reads see its effects
+    min_context_slot: Cell<Option<u64>>,
+    // Stop after simulating and previewing each transaction
+    dry_run: bool,
+    // Set by --timeout: stop waiting for confirmations at this point
+    deadline: Option<Instant>,
+    escrows: cache::EscrowCache,
+}
+
+impl Context {
+    // `timeout` bounds each RPC request (--timeout)
+    fn new(config: Config, timeout: Option<Duration>) -> Result<Context> {
+        let program_id = Pubkey::from_str(config.program_id.as_deref().unwrap_or(PROGRAM_ID))
+            .map_err(|_| anyhow!("Invalid program ID in config"))?;
+        let client = rpc::connect(&config, timeout)?;
+        Ok(Context {
+            client,
+            program_id,
+            config,
+            min_context_slot: Cell::new(None),
+            dry_run: false,
+            deadline: None,
+            escrows: cache::EscrowCache::default(),
+        })
+    }
+
+    fn observe_slot(&self, slot: u64) {
+        let current = self.min_context_slot.get().unwrap_or(0);
+        self.min_context_slot.set(Some(current.max(slot)));
+    }
+
+    fn account_config(&self) -> RpcAccountInfoConfig {
+        RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            commitment: Some(self.client.commitment()),
+            min_context_slot: self.min_context_slot.get(),
+            ..RpcAccountInfoConfig::default()
+        }
+    }
+
+    fn get_account_data(&self, pubkey: &Pubkey) -> Result<Vec<u8>> {
+        self.client
+            .get_account_with_config(pubkey, self.account_config())?
+            .value
+            .map(|account| account.data)
+            .ok_or_else(|| anyhow!("Account {} not found", pubkey))
+    }
+
+    // Decoded escrow account, from the cache when it was read since the last
+    // confirmed send
+    fn get_escrow(&self, pubkey: &Pubkey) -> Result<EscrowAccount> {
+        let min_slot = self.min_context_slot.get().unwrap_or(0);
+        if let Some(escrow) = self.escrows.get(pubkey, min_slot) {
+            return Ok(escrow);
+        }
+        match self.refresh_escrow(pubkey)? {
+            (_, Some((escrow, _))) => Ok(escrow),
+            (_, None) => Err(anyhow!("Account {} not found", pubkey)),
+        }
+    }
+
+    // Re-read an escrow at a slot no older than the previous read, returning
+    // the response slot and the account with whether it changed; `None` once
+    // the account is closed
+    fn refresh_escrow(&self, pubkey: &Pubkey) -> Result<(u64, Option<(EscrowAccount, bool)>)> {
+        let config = RpcAccountInfoConfig {
+            min_context_slot: self.min_context_slot.get().max(self.escrows.slot(pubkey)),
+            ..self.account_config()
+        };
+        let response = self.client.get_account_with_config(pubkey, config)?;
+        let slot = response.context.slot;
+        match response.value {
+            Some(account) => Ok((slot, Some(self.escrows.update(pubkey, slot, account.data)?))),
+            None => {
+                self.escrows.remove(pubkey);
+                Ok((slot, None))
+            }
+        }
+    }
+
+    fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<Account>>> {
+        Ok(self
+            .client
+            .get_multiple_accounts_with_config(pubkeys, self.account_config())?
+            .value)
+    }
+
+    // Send and wait for the client commitment, returning the landing slot
+    fn send_and_confirm(&self, transaction: &Transaction) -> Result<(Signature, u64)> {
+        let config = RpcSendTransactionConfig {
+            preflight_commitment: Some(self.client.commitment().commitment),
+            min_context_slot: self.min_context_slot.get(),
+            ..RpcSendTransactionConfig::default()
+        };
+        let signature = self.client.send_transaction_with_config(transaction, config)?;
+        let started = Instant::now();
+        let wait = match self.deadline {
+            Some(deadline) => deadline
+                .saturating_duration_since(started)
+                .min(CONFIRM_TIMEOUT),
+            None => CONFIRM_TIMEOUT,
+        };
+        while started.elapsed() < wait {
+            let statuses = self.client.get_signature_statuses(&[signature])?.value;
+            if let Some(Some(status)) = statuses.into_iter().next() {
+                if let Some(err) = status.err {
+                    return Err(anyhow!("Transaction {} failed: {}", signature, err));
+                }
+                if status.satisfies_commitment(self.client.commitment()) {
+                    return Ok((signature, status.slot));
+                }
+            }
+            sleep(Duration::from_millis(500));
+        }
+        Err(interrupt::Unconfirmed {
+            signature,
+            waited: started.elapsed(),
+        }
+        .into())
+    }
+
+    // Size of escrow accounts created by this program deployment
+    fn account_size(&self) -> usize {
+        self.config.account_size.unwrap_or(ESCROW_ACCOUNT_SIZE)
+    }
+
+    // Read a keypair, resolving bare file names against the profile keystore;
+    // `-` reads it from stdin
+    fn read_keypair(&self, path: &str, role: &str) -> Result<Keypair> {
+        if path == pipe::STDIO {
+            return pipe::read_stdin_keypair(role);
+        }
+        let resolved = self.config.resolve_keypair_path(path);
+        self.config.sandbox.read_keypair(
+            &resolved,
+            self.config.keystore.as_deref(),
+            &format!("{} keypair", role),
+        )
+    }
+}
+
 #[derive(Subcommand, Debug)]
 enum Command {
     /// Create a new escrow offer
     CreateOffer {
         #[arg(short = 'b', long)]
         buyer_keypair: String,
-        #[arg(short = 'e', long)]
-        escrow_keypair: String,
-        #[arg(short = 'r', long)]
-        arbiter: String,
-        #[arg(short = 'm', long)]
-        amount: u64,
+        #[arg(short = 'e', long, required_unless_present = "escrow_index")]
+        escrow_keypair: Option<String>,
+        /// Derive the escrow keypair from the buyer keypair and this index
+        /// instead of reading a file; `recover` regenerates it
+        #[arg(long, conflicts_with = "escrow_keypair")]
+        escrow_index: Option<u32>,
+        #[arg(short = 'r', long, required_unless_present = "packet", conflicts_with = "packet")]
+        arbiter: Option<String>,
+        /// Amount in lamports, or in SOL with a suffix (e.g. 1.5SOL)
+        #[arg(short = 'm', long, value_parser = parse_amount, required_unless_present = "packet")]
+        amount: Option<Lamports>,
+        /// Local tag stored in the ledger (repeatable)
+        #[arg(short = 't', long = "tag", value_parser = ledger::parse_tag)]
+        tags: Vec<String>,
+        /// Take the arbiter, amount and tags from an endorsed deal packet
+        #[arg(long)]
+        packet: Option<String>,
+        #[command(flatten)]
+        quote: quote::FiatQuote,
     },
     /// Join an existing offer as seller
     JoinOffer {
         #[arg(short = 's', long)]
         seller_keypair: String,
-        #[arg(short = 'e', long)]
-        escrow_account: String,
+        #[command(flatten)]
+        escrow_account: EscrowArg,
     },
     /// Fund the escrow contract
     Fund {
         #[arg(short = 'b', long)]
         buyer_keypair: String,
-        #[arg(short = 'e', long)]
-        escrow_account: String,
+        #[command(flatten)]
+        escrow_account: EscrowArg,
+        /// Only fund if the escrow's fingerprint matches the words the
+        /// seller read out
+        #[arg(long)]
+        fingerprint: Option<String>,
     },
     /// Confirm the transaction as buyer
     Confirm {
         #[arg(short = 's', long)]
         seller_keypair: String,
-        #[arg(short = 'e', long)]
-        escrow_account: String,
+        #[command(flatten)]
+        escrow_account: EscrowArg,
+        /// Swap the released SOL into this token via Jupiter (mint address,
+        /// USDC or USDT; mainnet only)
+        #[arg(long)]
+        settle_in: Option<String>,
+        /// Maximum slippage for the swap, in basis points
+        #[arg(long, default_value_t = 50, requires = "settle_in")]
+        slippage_bps: u16,
+        /// Sign now but release later: unix timestamp or offset like +7d
+        #[arg(
+            long,
+            value_parser = schedule::parse_release_at,
+            conflicts_with = "settle_in",
+            requires = "nonce_account"
+        )]
+        release_at: Option<i64>,
+        /// Durable nonce account (authority: the signing keypair) that keeps
+        /// the scheduled transaction valid
+        #[arg(long)]
+        nonce_account: Option<String>,
+        /// Where to save the scheduled transaction (`-` for stdout)
+        #[arg(short = 'o', long, requires = "release_at")]
+        out: Option<String>,
+    },
+    /// Send a scheduled release once its time has come, optionally only
+    /// after an external condition reports delivery
+    ReleaseScheduled {
+        /// Scheduled release file (`-` for stdin)
+        #[arg(short = 'f', long)]
+        file: String,
+        /// URL returning JSON, e.g. {"delivered": true}
+        #[arg(long, conflicts_with = "when_command")]
+        when_url: Option<String>,
+        /// Command (run with `sh -c`) printing the same JSON
+        #[arg(long)]
+        when_command: Option<String>,
+        /// JSON field that must be true
+        #[arg(long, default_value = "delivered")]
+        field: String,
+        /// Seconds between condition checks
+        #[arg(short = 'i', long, default_value_t = 60)]
+        interval: u64,
+        /// Send the release even if it calls programs besides the escrow
+        /// program, compute budget, nonce advances and memos
+        #[arg(long)]
+        allow_unknown_programs: bool,
     },
     /// Confirm as arbiter
     ArbiterConfirm {
         #[arg(short = 'a', long)]
         arbiter_keypair: String,
-        #[arg(short = 'e', long)]
-        escrow_account: String,
+        #[command(flatten)]
+        escrow_account: EscrowArg,
         #[arg(short = 's', long)]
         seller: String,
+        /// Post this [verdicts] template from the config as a memo
+        #[arg(long)]
+        verdict_template: Option<String>,
     },
     /// Cancel as arbiter
     ArbiterCancel {
         #[arg(short = 'a', long)]
         arbiter_keypair: String,
-        #[arg(short = 'e', long)]
-        escrow_account: String,
+        #[command(flatten)]
+        escrow_account: EscrowArg,
         #[arg(short = 'b', long)]
         buyer: String,
+        /// Post this [verdicts] template from the config as a memo
+        #[arg(long)]
+        verdict_template: Option<String>,
     },
     /// Mutual cancel by buyer and seller
     MutualCancel {
@@ -80,39 +666,362 @@ enum Command {
         buyer_keypair: String,
         #[arg(short = 's', long)]
         seller_keypair: String,
-        #[arg(short = 'e', long)]
-        escrow_account: String,
+        #[command(flatten)]
+        escrow_account: EscrowArg,
+    },
+    /// Co-sign and send a mutual-cancel transaction prepared by the other
+    /// party with `watch --prepare-cancel`
+    CosignCancel {
+        /// Your buyer or seller keypair
+        #[arg(short = 'k', long)]
+        keypair: String,
+        #[command(flatten)]
+        escrow_account: EscrowArg,
+        /// The prepared transaction in any export encoding, or `-` for stdin
+        #[arg(short = 'f', long)]
+        file: String,
     },
     /// Close escrow account
     Close {
         #[arg(short = 'c', long)]
         closer_keypair: String,
-        #[arg(short = 'e', long)]
-        escrow_account: String,
+        #[command(flatten)]
+        escrow_account: EscrowArg,
+        /// Also publish the archived state's hash in a memo before closing
+        #[arg(long)]
+        anchor: bool,
+    },
+    /// Close your Completed and Cancelled escrows once they have been
+    /// finalized for a while, reclaiming their rent
+    Sweep {
+        #[arg(short = 'c', long)]
+        closer_keypair: String,
+        /// How long an escrow must have been settled before it is closed
+        #[arg(long, value_parser = parse_duration, default_value = "24h")]
+        after: Duration,
+        /// Keep sweeping every this many seconds, with a daily summary to the
+        /// notify hook
+        #[arg(short = 'i', long)]
+        interval: Option<u64>,
+    },
+    /// Regenerate an escrow keypair made with `create-offer --escrow-index`
+    Recover {
+        #[arg(short = 'b', long)]
+        buyer_keypair: String,
+        #[arg(long)]
+        index: u32,
+        /// Write the escrow keypair here (`-` for stdout)
+        #[arg(short = 'o', long)]
+        out: Option<String>,
+    },
+    /// Record a settled escrow's final state in the local ledger
+    Archive {
+        #[command(flatten)]
+        escrow_account: EscrowArg,
+        /// Publish the state hash in a memo signed by this party keypair
+        #[arg(short = 'k', long)]
+        anchor_keypair: Option<String>,
     },
     /// Get escrow information
     Info {
+        #[command(flatten)]
+        escrow_account: EscrowArg,
+    },
+    /// Watch an escrow and alert on unexpected account writes
+    Watch {
+        #[command(flatten)]
+        escrow_account: EscrowArg,
+        /// Seconds between polls
+        #[arg(short = 'i', long, default_value_t = 5)]
+        interval: u64,
+        /// Exit with an error on the first alert
+        #[arg(long)]
+        exit_on_alert: bool,
+        /// Suggest mutual-cancel once the escrow has waited this long for
+        /// funding after the seller joined (e.g. 48h)
+        #[arg(long, value_parser = parse_duration)]
+        nudge_after: Option<Duration>,
+        #[command(flatten)]
+        prepare_cancel: watch::PrepareCancel,
+    },
+    /// Publish the hash of a terms document in a memo tied to the escrow
+    AttachTerms {
+        /// Keypair of the buyer, seller or arbiter committing the terms
+        #[arg(short = 'k', long)]
+        keypair: String,
+        #[command(flatten)]
+        escrow_account: EscrowArg,
+        #[arg(short = 'f', long)]
+        file: String,
+        /// Upload the document through the configured IPFS API
+        #[arg(long, conflicts_with = "arweave_id")]
+        ipfs: bool,
+        /// Arweave transaction ID of an already uploaded copy
+        #[arg(long)]
+        arweave_id: Option<String>,
+    },
+    /// Check that a document matches the terms committed for the escrow
+    VerifyTerms {
+        #[command(flatten)]
+        escrow_account: EscrowArg,
+        #[arg(short = 'f', long)]
+        file: String,
+    },
+    /// Show fees, rent and principal recorded for each escrow
+    Report {
+        /// Only show this escrow
+        #[arg(short = 'e', long)]
+        escrow_account: Option<String>,
+        /// Only show escrows carrying this tag (repeatable)
+        #[arg(short = 't', long = "tag", value_parser = ledger::parse_tag)]
+        tags: Vec<String>,
+    },
+    /// Show what each party can do with an escrow next
+    Next {
+        #[command(flatten)]
+        escrow_account: EscrowArg,
+        /// Only show this role's actions
+        #[arg(long = "as", value_enum)]
+        role: Option<actions::Role>,
+        /// Only show the actions of the roles this pubkey holds
+        #[arg(short = 'p', long, conflicts_with = "role")]
+        pubkey: Option<String>,
+    },
+    /// Show the transactions that touched an escrow, newest first
+    History {
+        #[command(flatten)]
+        escrow_account: EscrowArg,
+        /// Show at most this many transactions
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+        /// Ignore the local history cache and fetch everything again
+        #[arg(long)]
+        refresh: bool,
+    },
+    /// Print the version and the commit this binary was built from
+    Version {
+        /// Also print the target program ID and the binary's hash
+        #[arg(long)]
+        verify: bool,
+    },
+    /// Write canonical test vectors (account bytes, instruction data) as JSON
+    /// for checking other clients against this one
+    TestVectors {
+        /// Output file, or `-` for stdout
+        #[arg(short = 'o', long, default_value = "-")]
+        out: String,
+    },
+    /// Check that encodings still match an older release's
+    Compat {
+        #[command(subcommand)]
+        command: CompatCommand,
+    },
+    /// While on vacation, point Funded escrows naming you to your backup
+    /// arbiter (see [vacation] in the config)
+    ArbiterAway {
+        #[arg(short = 'a', long)]
+        arbiter_keypair: String,
+        /// Keep checking every this many seconds until the vacation ends
+        #[arg(short = 'i', long)]
+        interval: Option<u64>,
+    },
+    /// Arbiter tools beyond deciding an escrow
+    Arbiter {
+        #[command(subcommand)]
+        command: ArbiterCommand,
+    },
+    /// Two-factor codes for releases and cancels above `totp.above`
+    Totp {
+        #[command(subcommand)]
+        command: TotpCommand,
+    },
+    /// RPC endpoint diagnostics
+    Rpc {
+        #[command(subcommand)]
+        command: RpcCommand,
+    },
+    /// Tools for developing against the CLI
+    Dev {
+        #[command(subcommand)]
+        command: DevCommand,
+    },
+    /// Show the program's upgrade authority and deploy slot and trust it
+    TrustProgram {
+        /// Trust without asking
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+    /// Install the latest signed release over this binary
+    SelfUpdate {
+        /// Only report whether a newer release exists
+        #[arg(long)]
+        check: bool,
+    },
+    /// List escrows recorded in the local ledger with their current state
+    List {
+        #[command(flatten)]
+        filter: ledger::ListFilter,
+    },
+    /// Recompute vault PDAs and check stored bumps and vault balances
+    VerifyVaultBump {
+        /// Audit escrows where this keypair or pubkey is a party, instead of
+        /// the escrows in the local ledger
+        #[arg(short = 'k', long)]
+        keypair: Option<String>,
+    },
+    /// Show a wallet's SOL balance and what it has in open escrows
+    Balance {
+        /// Keypair file or pubkey of the wallet
+        #[arg(short = 'k', long)]
+        keypair: String,
+    },
+    /// List open offers that publish a fiat price, cheapest rate first
+    Offers {
+        /// Only offers priced in this currency, e.g. EUR
+        #[arg(long)]
+        currency: Option<String>,
+        /// Only offers accepting this payment method, e.g. SEPA
+        #[arg(long)]
+        method: Option<String>,
+        #[command(flatten)]
+        filter: ledger::PageFilter,
+    },
+    /// Search escrows by address, party, amount, tag or memo text
+    Search {
+        query: String,
+        /// Also query the chain for escrows involving a pubkey query and
+        /// search the parties' memos
+        #[arg(long)]
+        chain: bool,
+    },
+    /// Back up, move or restore the local ledger
+    Ledger {
+        #[command(subcommand)]
+        command: LedgerCommand,
+    },
+    /// Check a decision record's arbiter signature without RPC access
+    VerifyDecision {
+        /// Decision record file (`-` for stdin)
+        #[arg(short = 'f', long)]
+        file: String,
+        /// Also check this rationale document against the record's hash
+        #[arg(short = 'r', long)]
+        rationale: Option<String>,
+    },
+    /// Work with deal packets: arbiter-endorsed offer templates
+    Packet {
+        #[command(subcommand)]
+        command: PacketCommand,
+    },
+    /// Authenticate notifications sent by signed hooks
+    Webhook {
+        #[command(subcommand)]
+        command: WebhookCommand,
+    },
+    /// Add or remove local tags on an escrow
+    Tag {
         #[arg(short = 'e', long)]
         escrow_account: String,
+        #[arg(required = true, value_parser = ledger::parse_tag)]
+        tags: Vec<String>,
+        /// Remove the tags instead of adding them
+        #[arg(long)]
+        remove: bool,
     },
+    /// Track an escrow as part of a larger deal, e.g. the deposit ahead of
+    /// the balance or one milestone of several
+    Link {
+        #[command(flatten)]
+        escrow_account: EscrowArg,
+        /// The escrow heading the deal
+        #[arg(short = 'p', long, required_unless_present = "unlink")]
+        parent: Option<String>,
+        /// What this part is, e.g. deposit or milestone-2
+        #[arg(short = 'l', long, value_parser = ledger::parse_tag)]
+        label: Option<String>,
+        /// Detach the escrow from its deal
+        #[arg(long, conflicts_with_all = ["parent", "label"])]
+        unlink: bool,
+    },
+    /// Automated market-making
+    Bot {
+        #[command(subcommand)]
+        command: BotCommand,
+    },
+    /// Run the setup assistant (network, keypair, devnet airdrop)
+    Init,
+    /// Run a full escrow on devnet with throwaway keys and time each step
+    SelfTest {
+        /// Escrow amount, in lamports or SOL (e.g. 0.01SOL)
+        #[arg(short = 'm', long, value_parser = parse_amount, default_value = "0.01SOL")]
+        amount: Lamports,
+    },
+    /// Show or change the config file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Dispute tools for arbiters
+    Dispute {
+        #[command(subcommand)]
+        command: DisputeCommand,
+    },
+    /// Emit a Solana Actions (Blink) payload for an escrow step
+    Blink {
+        #[command(flatten)]
+        escrow_account: EscrowArg,
+        #[arg(long, value_enum)]
+        step: blink::BlinkStep,
+        /// URL where the action is hosted, used to build the blink link
+        #[arg(long)]
+        action_url: Option<String>,
+        /// Icon URL shown by Blink clients
+        #[arg(long)]
+        icon: Option<String>,
+        /// Also print a link opening the blink in this mobile wallet
+        #[arg(long, value_enum)]
+        wallet: Option<blink::Wallet>,
+        /// Poll for the signed transaction, giving up after this many seconds
+        #[arg(long, value_name = "SECONDS", num_args = 0..=1, default_missing_value = "300")]
+        wait: Option<u64>,
+    },
+    /// Run an external `escrow-cli-<COMMAND>` plugin from PATH
+    #[command(external_subcommand)]
+    External(Vec<String>),
 }
 
-#[derive(Debug, PartialEq, Eq)]
-enum EscrowState {
-    Uninitialized,
-    Created,
-    Initialized,
-    Funded,
-    Completed,
-    Cancelled,
-}
-
-// Simulate and send transaction, print logs if any
+// Simulate and send transaction, print logs if any, then summarize what the
+// transaction cost and record it against the escrow in the local ledger.
+// Under --dry-run nothing is sent and `None` is returned after the preview,
+// so flows with several transactions go on to preview the rest.
 fn simulate_and_send(
-    client: &RpcClient,
+    ctx: &Context,
     transaction: &Transaction,
-) -> Result<Signature> {
-    let simulation_result = client.simulate_transaction(transaction)?;
+    escrow: &Pubkey,
+    action: &str,
+) -> Result<Option<Signature>> {
+    preflight::check(transaction)?;
+    // A dry run asks the simulation for the written accounts' final states
+    let (writable, before) = if ctx.dry_run {
+        let writable = preview::writable_accounts(transaction);
+        let before = ctx.get_multiple_accounts(&writable)?;
+        (writable, before)
+    } else {
+        (Vec::new(), Vec::new())
+    };
+    let simulation_result = ctx.client.simulate_transaction_with_config(
+        transaction,
+        RpcSimulateTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: Some(ctx.client.commitment()),
+            min_context_slot: ctx.min_context_slot.get(),
+            accounts: ctx.dry_run.then(|| RpcSimulateTransactionAccountsConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                addresses: writable.iter().map(Pubkey::to_string).collect(),
+            }),
+            ..RpcSimulateTransactionConfig::default()
+        },
+    )?;
     if let Some(logs) = simulation_result.value.logs {
         println!("Transaction logs:");
         for log in logs {
@@ -120,458 +1029,1201 @@ fn simulate_and_send(
         }
     }
     if let Some(err) = simulation_result.value.err {
+        transcript::record(format!("Simulated {} on {}: failed", action, escrow));
         return Err(anyhow!("Simulation error: {:?}", err));
     }
-    let signature = client.send_and_confirm_transaction(transaction)?;
-    Ok(signature)
+    transcript::record(format!("Simulated {} on {}: ok", action, escrow));
+    if ctx.dry_run {
+        let after = simulation_result.value.accounts.unwrap_or_default();
+        preview::show(ctx, escrow, &writable, &before, &after)?;
+        println!("Dry run: {} was not sent", action);
+        transcript::record(format!("Dry run: {} not sent", action));
+        return Ok(None);
+    }
+    if ctx.config.read_only {
+        return Err(anyhow!("Read-only mode: refusing to send transaction"));
+    }
+    hooks::pre_send(&ctx.config, transaction)?;
+    interrupt::track(transaction.signatures[0], *escrow, &ctx.client);
+    let sent = ctx.send_and_confirm(transaction);
+    interrupt::clear();
+    let (signature, slot) = sent.map_err(|e| {
+        if let Some(unconfirmed) = e.downcast_ref::<interrupt::Unconfirmed>() {
+            transcript::record(format!("Sent {}: {} (unconfirmed)", action, unconfirmed.signature));
+            interrupt::follow_up(&unconfirmed.signature, escrow);
+        }
+        e
+    })?;
+    transcript::record(format!("Sent {}: {} (slot {})", action, signature, slot));
+    interrupt::landed(signature);
+    ctx.observe_slot(slot);
+    hooks::post_confirm(&ctx.config, transaction, &signature);
+    costs::record(ctx, transaction, &signature, escrow, action);
+    Ok(Some(signature))
+}
+
+// Re-parse the command line with a configured alias replaced by its expansion.
+// `external` is the tail of argv from the alias on, so its position is known;
+// searching argv for the name could hit a global option's value instead.
+fn expand_alias(external: &[String], expansion: &str) -> Cli {
+    let mut argv: Vec<String> = std::env::args().collect();
+    let index = argv.len().saturating_sub(external.len());
+    argv.splice(
+        index..(index + 1).min(argv.len()),
+        expansion.split_whitespace().map(str::to_string),
+    );
+    Cli::parse_from(argv)
 }
 
 fn main() -> Result<()> {
-    let args = Cli::parse();
-    let rpc_url = "https://solana-devnet.g.alchemy.com/v2/h1IAKlzdhlhF0Yo8w9ajfdTTzVsAddJ5".to_string();
-    let client = RpcClient::new_with_commitment(rpc_url, CommitmentConfig::confirmed());
+    let mut args = Cli::parse();
+    // Unknown subcommands may be aliases from the config before they are
+    // treated as plugins
+    if let Command::External(external) = &args.command {
+        let config = Config::load(args.config.as_deref())?;
+        let external = external.clone();
+        let alias = external.first().cloned().unwrap_or_default();
+        if let Some(expansion) = config.aliases.get(&alias) {
+            args = expand_alias(&external, expansion);
+        }
+    }
+    if let Some(path) = &args.transcript {
+        let config = Config::load(args.config.as_deref())?;
+        transcript::start(&config.sandbox, path, !args.transcript_unredacted)?;
+    }
+    let result = run(args);
+    transcript::finish(&result);
+    result
+}
+
+fn run(args: Cli) -> Result<()> {
+    if args.no_network_except_rpc {
+        network::restrict_to_rpc();
+    }
+    if args.raw {
+        amount::set_raw();
+    }
+    interrupt::install()?;
+    // Config commands must work even when the current file fails to load
+    match &args.command {
+        Command::Init => return onboarding::run(args.config.as_deref()),
+        Command::Config { command } => {
+            return match command {
+                ConfigCommand::Show => config::show(args.config.as_deref()),
+                ConfigCommand::Set { key, value } => {
+                    config::set(args.config.as_deref(), key, value)
+                }
+            }
+        }
+        _ => {}
+    }
+    if onboarding::is_first_run(args.config.as_deref()) {
+        onboarding::run(None)?;
+    }
+    let mut config = Config::load(args.config.as_deref())?;
+    config.apply_profile(args.profile.as_deref())?;
+    config.read_only |= args.read_only;
+    if config.read_only && args.command.is_mutating() {
+        return Err(anyhow!("Read-only mode: signing and sending are disabled"));
+    }
+    // Probing must not depend on the endpoint auto mode would pick
+    if let Command::Rpc {
+        command: RpcCommand::Probe,
+    } = &args.command
+    {
+        return rpc::probe(&config);
+    }
+    // Nothing in a compat check touches the network
+    if let Command::Compat {
+        command: CompatCommand::Check { against },
+    } = &args.command
+    {
+        return compat::check(against);
+    }
+    let mut ctx = Context::new(config, args.timeout)?;
+    transcript::record_url("RPC endpoint", &ctx.client.url());
+    transcript::record(format!("Program: {}", ctx.program_id));
+    ctx.min_context_slot.set(args.min_context_slot);
+    ctx.dry_run = args.dry_run;
+    ctx.deadline = args.timeout.map(|timeout| Instant::now() + timeout);
+    if args.command.is_mutating() {
+        trust::check(&ctx, args.config.as_deref())?;
+    }
     match args.command {
         Command::CreateOffer {
             buyer_keypair,
             escrow_keypair,
+            escrow_index,
             arbiter,
             amount,
-        } => create_offer(
-            &client,
-            &buyer_keypair,
-            &escrow_keypair,
-            &arbiter,
-            amount,
-        ),
+            mut tags,
+            packet,
+            quote,
+        } => {
+            let (arbiter, amount) = match packet {
+                Some(path) => {
+                    let packet = packet::DealPacket::load(&path)?;
+                    packet.validate()?;
+                    println!("Using deal packet '{}' endorsed by {}", packet.name, packet.arbiter);
+                    tags.extend(packet.tags.iter().cloned());
+                    let amount = packet.offer_amount(amount)?;
+                    (packet.arbiter, amount)
+                }
+                None => (arbiter.unwrap_or_default(), amount.unwrap_or_default()),
+            };
+            // Loaded once: a keypair piped on stdin can't be read twice
+            let buyer_keypair = ctx.read_keypair(&buyer_keypair, "buyer")?;
+            let escrow_keypair = match (escrow_keypair, escrow_index) {
+                (_, Some(index)) => {
+                    let escrow = derive::escrow_keypair(&buyer_keypair, index)?;
+                    println!(
+                        "Escrow #{} derived from the buyer keypair: {}",
+                        index,
+                        escrow.pubkey()
+                    );
+                    escrow
+                }
+                (path, None) => ctx.read_keypair(&path.unwrap_or_default(), "escrow")?,
+            };
+            create_offer(
+                &ctx,
+                &buyer_keypair,
+                &escrow_keypair,
+                &arbiter,
+                amount,
+                &quote,
+            )?;
+            ledger::tag(&ctx, &escrow_keypair.pubkey().to_string(), &tags, false)
+        }
         Command::JoinOffer {
             seller_keypair,
             escrow_account,
-        } => join_offer(&client, &seller_keypair, &escrow_account),
+        } => join_offer(&ctx, &seller_keypair, escrow_account.value()),
         Command::Fund {
             buyer_keypair,
             escrow_account,
-        } => fund_escrow(&client, &buyer_keypair, &escrow_account),
+            fingerprint,
+        } => fund_escrow(&ctx, &buyer_keypair, escrow_account.value(), fingerprint.as_deref()),
         Command::Confirm {
             seller_keypair,
             escrow_account,
-        } => confirm_escrow(&client, &seller_keypair, &escrow_account),
+            settle_in,
+            slippage_bps,
+            release_at,
+            nonce_account,
+            out,
+        } => match (settle_in, release_at, nonce_account) {
+            (Some(mint), _, _) => swap::confirm_and_swap(
+                &ctx,
+                &seller_keypair,
+                escrow_account.value(),
+                &mint,
+                slippage_bps,
+            ),
+            (None, Some(release_at), Some(nonce_account)) => schedule::schedule_release(
+                &ctx,
+                &seller_keypair,
+                escrow_account.value(),
+                release_at,
+                &nonce_account,
+                &out.unwrap_or_else(|| {
+                    format!("{}-release.json", escrow_account.value())
+                }),
+            ),
+            _ => confirm_escrow(&ctx, &seller_keypair, escrow_account.value()),
+        },
+        Command::ReleaseScheduled {
+            file,
+            when_url,
+            when_command,
+            field,
+            interval,
+            allow_unknown_programs,
+        } => {
+            let condition = when_url
+                .map(schedule::Condition::Url)
+                .or(when_command.map(schedule::Condition::Command));
+            schedule::release_scheduled(
+                &ctx,
+                &file,
+                condition.as_ref(),
+                &field,
+                interval,
+                allow_unknown_programs,
+            )
+        }
         Command::ArbiterConfirm {
             arbiter_keypair,
             escrow_account,
             seller,
-        } => arbiter_confirm(&client, &arbiter_keypair, &escrow_account, &seller),
+            verdict_template,
+        } => arbiter_confirm(
+            &ctx,
+            &arbiter_keypair,
+            escrow_account.value(),
+            &seller,
+            verdict_template.as_deref(),
+        ),
         Command::ArbiterCancel {
             arbiter_keypair,
             escrow_account,
             buyer,
-        } => arbiter_cancel(&client, &arbiter_keypair, &escrow_account, &buyer),
+            verdict_template,
+        } => arbiter_cancel(
+            &ctx,
+            &arbiter_keypair,
+            escrow_account.value(),
+            &buyer,
+            verdict_template.as_deref(),
+        ),
         Command::MutualCancel {
             buyer_keypair,
             seller_keypair,
             escrow_account,
-        } => mutual_cancel(&client, &buyer_keypair, &seller_keypair, &escrow_account),
+        } => mutual_cancel(
+            &ctx,
+            &buyer_keypair,
+            &seller_keypair,
+            escrow_account.value(),
+        ),
+        Command::CosignCancel {
+            keypair,
+            escrow_account,
+            file,
+        } => watch::cosign_cancel(&ctx, &keypair, escrow_account.value(), &file),
         Command::Close {
             closer_keypair,
             escrow_account,
-        } => close_escrow(&client, &closer_keypair, &escrow_account),
-        Command::Info { escrow_account } => get_escrow_info(&client, &escrow_account),
+            anchor,
+        } => close_escrow(&ctx, &closer_keypair, escrow_account.value(), anchor),
+        Command::Sweep {
+            closer_keypair,
+            after,
+            interval,
+        } => sweep::sweep(&ctx, &closer_keypair, after, interval),
+        Command::Recover {
+            buyer_keypair,
+            index,
+            out,
+        } => derive::recover(&ctx, &buyer_keypair, index, out.as_deref()),
+        Command::Archive {
+            escrow_account,
+            anchor_keypair,
+        } => {
+            let anchor_keypair = anchor_keypair
+                .as_deref()
+                .map(|path| ctx.read_keypair(path, "anchoring"))
+                .transpose()?;
+            archive::archive(&ctx, escrow_account.value(), anchor_keypair.as_ref())
+        }
+        Command::Info { escrow_account } => get_escrow_info(&ctx, escrow_account.value()),
+        Command::Report {
+            escrow_account,
+            tags,
+        } => costs::report(&ctx, escrow_account.as_deref(), &tags),
+        Command::Next {
+            escrow_account,
+            role,
+            pubkey,
+        } => actions::next(&ctx, escrow_account.value(), role, pubkey.as_deref()),
+        Command::History {
+            escrow_account,
+            limit,
+            refresh,
+        } => history::history(&ctx, escrow_account.value(), limit, refresh),
+        Command::Version { verify } => version::version(&ctx, verify),
+        Command::TestVectors { out } => vectors::generate(&ctx, &out),
+        Command::Compat { .. } => unreachable!("handled before connecting"),
+        Command::ArbiterAway {
+            arbiter_keypair,
+            interval,
+        } => vacation::arbiter_away(&ctx, &arbiter_keypair, interval),
+        Command::Arbiter { command } => match command {
+            ArbiterCommand::Note {
+                arbiter_keypair,
+                escrow_account,
+                message,
+            } => note::post(&ctx, &arbiter_keypair, escrow_account.value(), &message),
+            ArbiterCommand::DecisionRecord {
+                arbiter_keypair,
+                escrow_account,
+                transaction,
+                rationale,
+                out,
+            } => decision::record(
+                &ctx,
+                &arbiter_keypair,
+                escrow_account.value(),
+                &transaction,
+                rationale.as_deref(),
+                &out,
+            ),
+        },
+        Command::Totp { command } => match command {
+            TotpCommand::Setup => totp::setup(&ctx),
+        },
+        Command::Rpc { .. } => unreachable!("handled before connecting"),
+        Command::Dev { command } => match command {
+            DevCommand::ChaosProxy {
+                listen,
+                latency_ms,
+                rate_limit,
+                drop_confirmations,
+                stale_slots,
+                seed,
+            } => {
+                let faults = chaos::Faults {
+                    latency: Duration::from_millis(latency_ms),
+                    rate_limit,
+                    drop_confirmations,
+                    stale_slots,
+                };
+                chaos::chaos_proxy(&ctx, &listen, faults, seed)
+            }
+        },
+        Command::TrustProgram { yes } => trust::trust_program(&ctx, args.config.as_deref(), yes),
+        Command::SelfUpdate { check } => update::self_update(&ctx.config, check),
+        Command::List { filter } => ledger::list(&ctx, &filter),
+        Command::VerifyVaultBump { keypair } => audit::verify_vault_bump(&ctx, keypair.as_deref()),
+        Command::Balance { keypair } => balance::balance(&ctx, &keypair),
+        Command::Offers {
+            currency,
+            method,
+            filter,
+        } => quote::offers(&ctx, currency.as_deref(), method.as_deref(), &filter),
+        Command::Search { query, chain } => search::search(&ctx, &query, chain),
+        Command::Ledger { command } => match command {
+            LedgerCommand::Export {
+                out,
+                format,
+                encrypt,
+            } => ledger::export(&ctx, out.as_deref(), format, encrypt),
+            LedgerCommand::Import { file } => ledger::import(&ctx, &file),
+        },
+        Command::VerifyDecision { file, rationale } => {
+            decision::verify(&file, rationale.as_deref())
+        }
+        Command::Packet { command } => match command {
+            PacketCommand::Validate { file } => packet::validate(&file),
+            PacketCommand::Sign {
+                file,
+                arbiter_keypair,
+            } => packet::sign(&ctx, &file, &arbiter_keypair),
+        },
+        Command::Webhook { command } => match command {
+            WebhookCommand::Verify {
+                signer,
+                timestamp,
+                signature,
+                file,
+                max_age,
+            } => {
+                let body = String::from_utf8(pipe::read_input(&file)?)?;
+                hooks::verify_payload(&body, &signer, timestamp, &signature, max_age.as_secs())?;
+                println!("Payload signature verified (signer {})", signer);
+                Ok(())
+            }
+        },
+        Command::Tag {
+            escrow_account,
+            tags,
+            remove,
+        } => ledger::tag(&ctx, &escrow_account, &tags, remove),
+        Command::Link {
+            escrow_account,
+            parent,
+            label,
+            unlink: _,
+        } => ledger::link(&ctx, escrow_account.value(), parent.as_deref(), label.as_deref()),
+        Command::Bot { command } => match command {
+            BotCommand::Match { file, once } => bot::run(&ctx, &file, once),
+        },
+        Command::SelfTest { amount } => selftest::run(&ctx, amount),
+        Command::Watch {
+            escrow_account,
+            interval,
+            exit_on_alert,
+            nudge_after,
+            prepare_cancel,
+        } => watch::watch(
+            &ctx,
+            escrow_account.value(),
+            interval,
+            exit_on_alert,
+            nudge_after,
+            &prepare_cancel,
+        ),
+        Command::AttachTerms {
+            keypair,
+            escrow_account,
+            file,
+            ipfs,
+            arweave_id,
+        } => terms::attach_terms(
+            &ctx,
+            &keypair,
+            escrow_account.value(),
+            &file,
+            ipfs,
+            arweave_id.as_deref(),
+        ),
+        Command::VerifyTerms {
+            escrow_account,
+            file,
+        } => terms::verify_terms(&ctx, escrow_account.value(), &file),
+        Command::Init | Command::Config { .. } => {
+            unreachable!("handled before loading the config")
+        }
+        Command::Dispute { command } => match command {
+            DisputeCommand::Show {
+                escrow_account,
+                out,
+            } => dispute::show(&ctx, &escrow_account, out.as_deref()),
+            DisputeCommand::Bundle {
+                escrow_account,
+                out,
+                notes,
+            } => dispute::bundle(&ctx, &escrow_account, &out, &notes),
+        },
+        Command::Blink {
+            escrow_account,
+            step,
+            action_url,
+            icon,
+            wallet,
+            wait,
+        } => blink::blink(
+            &ctx,
+            escrow_account.value(),
+            step,
+            action_url.as_deref(),
+            icon.as_deref(),
+            wallet,
+            wait,
+        ),
+        Command::External(args) => plugin::run_plugin(
+            &args,
+            &ctx.client.url(),
+            &ctx.program_id.to_string(),
+            ctx.config.read_only,
+        ),
     }
 }
 
 // Check escrow state by reading account data
-fn check_state(client: &RpcClient, escrow_account: &str) -> Result<EscrowState> {
+fn check_state(ctx: &Context, escrow_account: &str) -> Result<EscrowState> {
     let escrow_pubkey = Pubkey::from_str(escrow_account)?;
-    let account_data = client.get_account_data(&escrow_pubkey)?;
-    if account_data.len() < 106 {
-        return Err(anyhow!("Invalid account data length"));
-    }
-    let state_byte = account_data[104];
-    match state_byte {
-        0 => Ok(EscrowState::Uninitialized),
-        1 => Ok(EscrowState::Created),
-        2 => Ok(EscrowState::Initialized),
-        3 => Ok(EscrowState::Funded),
-        4 => Ok(EscrowState::Completed),
-        5 => Ok(EscrowState::Cancelled),
-        _ => Err(anyhow!("Invalid state byte: {}", state_byte)),
+    let min_slot = ctx.min_context_slot.get().unwrap_or(0);
+    if let Some(escrow) = ctx.escrows.get(&escrow_pubkey, min_slot) {
+        return Ok(escrow.state);
     }
+    // Two bytes instead of the whole account
+    let (offset, length) = STATE_SLICE;
+    let config = RpcAccountInfoConfig {
+        data_slice: Some(UiDataSliceConfig { offset, length }),
+        ..ctx.account_config()
+    };
+    let slice = ctx
+        .client
+        .get_account_with_config(&escrow_pubkey, config)?
+        .value
+        .map(|account| account.data)
+        .ok_or_else(|| anyhow!("Account {} not found", escrow_pubkey))?;
+    Ok(unpack_state(&slice)?)
 }
 
 // Create a new escrow offer
 fn create_offer(
-    client: &RpcClient,
-    buyer_keypair_path: &str,
-    escrow_keypair_path: &str,
+    ctx: &Context,
+    buyer_keypair: &Keypair,
+    escrow_keypair: &Keypair,
     arbiter: &str,
-    amount: u64,
+    amount: Lamports,
+    quote: &quote::FiatQuote,
 ) -> Result<()> {
-    let buyer_keypair = read_keypair_file(buyer_keypair_path)
-        .map_err(|_| anyhow!("Failed to read buyer keypair"))?;
-    let escrow_keypair = read_keypair_file(escrow_keypair_path)
-        .map_err(|_| anyhow!("Failed to read escrow keypair"))?;
-    let program_id = Pubkey::from_str(PROGRAM_ID)?;
-    let arbiter_pubkey = Pubkey::from_str(arbiter)?;
-    let create_account_ix = system_instruction::create_account(
+    let mut transaction = build_create_offer(
+        ctx,
         &buyer_keypair.pubkey(),
         &escrow_keypair.pubkey(),
-        client
-            .get_minimum_balance_for_rent_exemption(ESCROW_ACCOUNT_SIZE)
+        arbiter,
+        amount,
+        quote,
+    )?;
+    transaction.sign(&[buyer_keypair, escrow_keypair])?;
+    let Some(signature) = transaction.execute(ctx)? else {
+        return Ok(());
+    };
+    println!("Offer created successfully! Signature: {}", signature);
+    Ok(())
+}
+
+// Build phase of create-offer; the buyer and the new escrow account sign
+fn build_create_offer(
+    ctx: &Context,
+    buyer: &Pubkey,
+    escrow: &Pubkey,
+    arbiter: &str,
+    amount: Lamports,
+    quote: &quote::FiatQuote,
+) -> Result<PartiallySignedTx> {
+    let program_id = ctx.program_id;
+    let arbiter_pubkey = Pubkey::from_str(arbiter)?;
+    let create_account_ix = system_instruction::create_account(
+        buyer,
+        escrow,
+        ctx.client
+            .get_minimum_balance_for_rent_exemption(ctx.account_size())
             .map_err(|e| anyhow!("Rent exemption error: {}", e))?,
-        ESCROW_ACCOUNT_SIZE as u64,
+        ctx.account_size() as u64,
         &program_id,
     );
-    let vault_pda = get_vault_pda(&escrow_keypair.pubkey(), &program_id);
-    let data = {
-        let mut data = vec![0]; // instruction index: create_offer
-        data.extend_from_slice(&amount.to_le_bytes());
-        data.extend_from_slice(arbiter_pubkey.as_ref());
-        data
-    };
-    let initialize_ix = Instruction {
-        program_id,
+    let initialize_ix = create_offer_instruction(
+        &program_id,
+        buyer,
+        escrow,
+        &arbiter_pubkey,
+        amount,
+    );
+    let mut instructions = vec![create_account_ix, initialize_ix];
+    if let Some(memo) = quote.memo(escrow) {
+        instructions.push(memo::memo_instruction(&memo, &[buyer]));
+    }
+    PartiallySignedTx::new(ctx, &instructions, buyer, escrow, "create_offer")
+}
+
+// Build the create_offer instruction signed by the buyer
+fn create_offer_instruction(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    escrow: &Pubkey,
+    arbiter: &Pubkey,
+    amount: Lamports,
+) -> Instruction {
+    let vault_pda = get_vault_pda(escrow, program_id);
+    let mut data = vec![0]; // instruction index: create_offer
+    data.extend_from_slice(&amount.lamports().to_le_bytes());
+    data.extend_from_slice(arbiter.as_ref());
+    Instruction {
+        program_id: *program_id,
         accounts: vec![
-            AccountMeta::new(buyer_keypair.pubkey(), true),
-            AccountMeta::new(escrow_keypair.pubkey(), false),
+            AccountMeta::new(*buyer, true),
+            AccountMeta::new(*escrow, false),
             AccountMeta::new(vault_pda, false),
             AccountMeta::new_readonly(solana_sdk::system_program::id(), false),
         ],
         data,
-    };
-    let blockhash = client
-        .get_latest_blockhash()
-        .map_err(|e| anyhow!("Blockhash error: {}", e))?;
-    let message = Message::new(
-        &[create_account_ix, initialize_ix],
-        Some(&buyer_keypair.pubkey()),
-    );
-    let transaction = Transaction::new(
-        &[&buyer_keypair, &escrow_keypair],
-        message,
-        blockhash,
-    );
-    let signature = simulate_and_send(client, &transaction)?;
-    println!("Offer created successfully! Signature: {}", signature);
-    Ok(())
+    }
 }
 
 // Seller joins an offer
 fn join_offer(
-    client: &RpcClient,
+    ctx: &Context,
     seller_keypair_path: &str,
     escrow_account: &str,
 ) -> Result<()> {
-    let seller_keypair = read_keypair_file(seller_keypair_path)
-        .map_err(|_| anyhow!("Failed to read seller keypair"))?;
+    let seller_keypair = ctx.read_keypair(seller_keypair_path, "seller")?;
+    let mut transaction = build_join_offer(ctx, &seller_keypair.pubkey(), escrow_account)?;
+    transaction.sign(&[&seller_keypair])?;
+    let Some(signature) = transaction.execute(ctx)? else {
+        return Ok(());
+    };
+    println!("Joined offer successfully! Signature: {}", signature);
+    Ok(())
+}
+
+// Build phase of join-offer; the seller signs
+fn build_join_offer(
+    ctx: &Context,
+    seller: &Pubkey,
+    escrow_account: &str,
+) -> Result<PartiallySignedTx> {
     let escrow_pubkey = Pubkey::from_str(escrow_account)?;
-    let program_id = Pubkey::from_str(PROGRAM_ID)?;
-    match check_state(client, escrow_account)? {
+    let program_id = ctx.program_id;
+    match check_state(ctx, escrow_account)? {
         EscrowState::Created => {},
         other_state => return Err(anyhow!(
             "Escrow must be in Created state, current state: {:?}", 
             other_state
         )),
     }
-    let data = {
-        let mut data = vec![1]; // instruction index: join_offer
-        data.extend_from_slice(seller_keypair.pubkey().as_ref());
-        data
-    };
-    let join_ix = Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(seller_keypair.pubkey(), true),
-            AccountMeta::new(escrow_pubkey, false),
-        ],
-        data,
-    };
-    let blockhash = client
-        .get_latest_blockhash()
-        .map_err(|e| anyhow!("Blockhash error: {}", e))?;
-    let message = Message::new(&[join_ix], Some(&seller_keypair.pubkey()));
-    let transaction = Transaction::new(&[&seller_keypair], message, blockhash);
-    let signature = simulate_and_send(client, &transaction)?;
-    println!("Joined offer successfully! Signature: {}", signature);
+    check_buyer_funding(ctx, &escrow_pubkey)?;
+    let join_ix = join_instruction(&program_id, seller, &escrow_pubkey);
+    PartiallySignedTx::new(ctx, &[join_ix], seller, &escrow_pubkey, "join_offer")
+}
+
+// Show the buyer's balance and account age before the seller commits to the
+// offer, warning when the buyer clearly can't fund it
+fn check_buyer_funding(ctx: &Context, escrow_pubkey: &Pubkey) -> Result<()> {
+    let escrow = ctx.get_escrow(escrow_pubkey)?;
+    let balance = ctx.client.get_balance(&escrow.buyer)?;
+    println!("Buyer {}:", escrow.buyer);
+    println!("  Balance: {} SOL", amount::format_sol(balance));
+    // Signatures come newest first, at most 1000 per page
+    let history = ctx.client.get_signatures_for_address(&escrow.buyer)?;
+    let oldest = history.last().and_then(|status| status.block_time);
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs() as i64;
+    match oldest {
+        Some(first_seen) => println!(
+            "  Account age: {}{}",
+            if history.len() >= 1000 { "at least " } else { "" },
+            duration::format_duration(Duration::from_secs((now - first_seen).max(0) as u64))
+        ),
+        None => println!("  Account age: no transaction history"),
+    }
+    if Lamports::new(balance) < escrow.amount {
+        eprintln!(
+            "Warning: the buyer holds {} SOL but the offer is for {} SOL; they cannot fund it \
+             without topping up",
+            amount::format_sol(balance),
+            escrow.amount
+        );
+    }
     Ok(())
 }
 
 // Buyer funds the escrow
 fn fund_escrow(
-    client: &RpcClient,
+    ctx: &Context,
     buyer_keypair_path: &str,
     escrow_account: &str,
+    expected_fingerprint: Option<&str>,
 ) -> Result<()> {
-    let buyer_keypair = read_keypair_file(buyer_keypair_path)
-        .map_err(|_| anyhow!("Failed to read buyer keypair"))?;
+    let buyer_keypair = ctx.read_keypair(buyer_keypair_path, "buyer")?;
+    let mut transaction = build_fund_escrow(
+        ctx,
+        &buyer_keypair.pubkey(),
+        escrow_account,
+        expected_fingerprint,
+    )?;
+    transaction.sign(&[&buyer_keypair])?;
+    let Some(signature) = transaction.execute(ctx)? else {
+        return Ok(());
+    };
+    println!("Escrow funded successfully! Signature: {}", signature);
+    Ok(())
+}
+
+// Build phase of fund; the buyer signs
+fn build_fund_escrow(
+    ctx: &Context,
+    buyer: &Pubkey,
+    escrow_account: &str,
+    expected_fingerprint: Option<&str>,
+) -> Result<PartiallySignedTx> {
     let escrow_pubkey = Pubkey::from_str(escrow_account)?;
-    let program_id = Pubkey::from_str(PROGRAM_ID)?;
-    match check_state(client, escrow_account)? {
+    let program_id = ctx.program_id;
+    match check_state(ctx, escrow_account)? {
         EscrowState::Initialized => {},
         other_state => return Err(anyhow!(
             "Escrow must be in Initialized state, current state: {:?}", 
             other_state
         )),
     }
-    let vault_pda = get_vault_pda(&escrow_pubkey, &program_id);
-    let fund_ix = Instruction {
-        program_id,
+    // Read out to the seller, who sees the same words in `info`
+    let words = fingerprint::fingerprint(&escrow_pubkey, &ctx.get_escrow(&escrow_pubkey)?);
+    println!("Fingerprint: {}", words);
+    if let Some(expected) = expected_fingerprint {
+        if !fingerprint::matches(expected, &words) {
+            return Err(anyhow!(
+                "Fingerprint mismatch: expected {}, this escrow is {}. Not funding.",
+                expected,
+                words
+            ));
+        }
+    }
+    let fund_ix = fund_instruction(&program_id, buyer, &escrow_pubkey);
+    PartiallySignedTx::new(ctx, &[fund_ix], buyer, &escrow_pubkey, "fund_escrow")
+}
+
+// Build the join_offer instruction signed by the seller
+fn join_instruction(program_id: &Pubkey, seller: &Pubkey, escrow: &Pubkey) -> Instruction {
+    let mut data = vec![1]; // instruction index: join_offer
+    data.extend_from_slice(seller.as_ref());
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*seller, true),
+            AccountMeta::new(*escrow, false),
+        ],
+        data,
+    }
+}
+
+// Build the fund_escrow instruction signed by the buyer
+fn fund_instruction(program_id: &Pubkey, buyer: &Pubkey, escrow: &Pubkey) -> Instruction {
+    let vault_pda = get_vault_pda(escrow, program_id);
+    Instruction {
+        program_id: *program_id,
         accounts: vec![
-            AccountMeta::new(buyer_keypair.pubkey(), true),
-            AccountMeta::new(escrow_pubkey, false),
+            AccountMeta::new(*buyer, true),
+            AccountMeta::new(*escrow, false),
             AccountMeta::new(vault_pda, false),
             AccountMeta::new_readonly(solana_sdk::system_program::id(), false),
         ],
         data: vec![2], // instruction index: fund_escrow
-    };
-    let blockhash = client
-        .get_latest_blockhash()
-        .map_err(|e| anyhow!("Blockhash error: {}", e))?;
-    let message = Message::new(&[fund_ix], Some(&buyer_keypair.pubkey()));
-    let transaction = Transaction::new(&[&buyer_keypair], message, blockhash);
-    let signature = simulate_and_send(client, &transaction)?;
-    println!("Escrow funded successfully! Signature: {}", signature);
-    Ok(())
+    }
 }
 
 // Seller confirms escrow
 fn confirm_escrow(
-    client: &RpcClient,
+    ctx: &Context,
     seller_keypair_path: &str,
     escrow_account: &str,
 ) -> Result<()> {
-    let seller_keypair = read_keypair_file(seller_keypair_path)
-        .map_err(|_| anyhow!("Failed to read seller keypair"))?;
+    let seller_keypair = ctx.read_keypair(seller_keypair_path, "seller")?;
+    let mut transaction = build_confirm_escrow(ctx, &seller_keypair.pubkey(), escrow_account)?;
+    totp::check(ctx, "confirm", ctx.get_escrow(&transaction.escrow)?.amount)?;
+    transaction.sign(&[&seller_keypair])?;
+    let Some(signature) = transaction.execute(ctx)? else {
+        return Ok(());
+    };
+    println!("Transaction confirmed! Signature: {}", signature);
+    Ok(())
+}
+
+// Build phase of confirm; the seller signs
+fn build_confirm_escrow(
+    ctx: &Context,
+    seller: &Pubkey,
+    escrow_account: &str,
+) -> Result<PartiallySignedTx> {
     let escrow_pubkey = Pubkey::from_str(escrow_account)?;
-    let program_id = Pubkey::from_str(PROGRAM_ID)?;
-    match check_state(client, escrow_account)? {
+    let program_id = ctx.program_id;
+    match check_state(ctx, escrow_account)? {
         EscrowState::Funded => {},
         other_state => return Err(anyhow!(
             "Escrow must be in Funded state, current state: {:?}", 
             other_state
         )),
     }
-    let vault_pda = get_vault_pda(&escrow_pubkey, &program_id);
-    let confirm_ix = Instruction {
-        program_id,
+    safety::check_finalized(ctx, &escrow_pubkey, "confirm")?;
+    safety::check_cross(ctx, &escrow_pubkey, "confirm")?;
+    let confirm_ix = confirm_instruction(&program_id, seller, &escrow_pubkey);
+    PartiallySignedTx::new(ctx, &[confirm_ix], seller, &escrow_pubkey, "confirm_escrow")
+}
+
+// Build the confirm_escrow instruction signed by the seller
+fn confirm_instruction(program_id: &Pubkey, seller: &Pubkey, escrow: &Pubkey) -> Instruction {
+    let vault_pda = get_vault_pda(escrow, program_id);
+    Instruction {
+        program_id: *program_id,
         accounts: vec![
-            AccountMeta::new(seller_keypair.pubkey(), true),
-            AccountMeta::new(escrow_pubkey, false),
+            AccountMeta::new(*seller, true),
+            AccountMeta::new(*escrow, false),
             AccountMeta::new(vault_pda, false),
             AccountMeta::new_readonly(solana_sdk::system_program::id(), false),
         ],
         data: vec![3], // instruction index: confirm_escrow
-    };
-    let blockhash = client
-        .get_latest_blockhash()
-        .map_err(|e| anyhow!("Blockhash error: {}", e))?;
-    let message = Message::new(&[confirm_ix], Some(&seller_keypair.pubkey()));
-    let transaction = Transaction::new(&[&seller_keypair], message, blockhash);
-    let signature = simulate_and_send(client, &transaction)?;
-    println!("Transaction confirmed! Signature: {}", signature);
-    Ok(())
+    }
 }
 
 // Arbiter confirms escrow
 fn arbiter_confirm(
-    client: &RpcClient,
+    ctx: &Context,
     arbiter_keypair_path: &str,
     escrow_account: &str,
     seller: &str,
+    verdict_template: Option<&str>,
 ) -> Result<()> {
-    let arbiter_keypair = read_keypair_file(arbiter_keypair_path)
-        .map_err(|_| anyhow!("Failed to read arbiter keypair"))?;
-    let escrow_pubkey = Pubkey::from_str(escrow_account)?;
-    let seller_pubkey = Pubkey::from_str(seller)?;
-    let program_id = Pubkey::from_str(PROGRAM_ID)?;
-    match check_state(client, escrow_account)? {
-        EscrowState::Funded => {},
-        other_state => return Err(anyhow!(
-            "Escrow must be in Funded state, current state: {:?}", 
-            other_state
-        )),
-    }
-    let vault_pda = get_vault_pda(&escrow_pubkey, &program_id);
-    let confirm_ix = Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(arbiter_keypair.pubkey(), true),
-            AccountMeta::new(escrow_pubkey, false),
-            AccountMeta::new(vault_pda, false),
-            AccountMeta::new(seller_pubkey, false),
-        ],
-        data: vec![4], // instruction index: arbiter_confirm
+    let arbiter_keypair = ctx.read_keypair(arbiter_keypair_path, "arbiter")?;
+    let mut transaction = build_arbiter_decision(
+        ctx,
+        ArbiterDecision::Release,
+        &arbiter_keypair.pubkey(),
+        escrow_account,
+        seller,
+        verdict_template,
+    )?;
+    totp::check(ctx, "arbiter-confirm", ctx.get_escrow(&transaction.escrow)?.amount)?;
+    transaction.sign(&[&arbiter_keypair])?;
+    let Some(signature) = transaction.execute(ctx)? else {
+        return Ok(());
     };
-    let blockhash = client
-        .get_latest_blockhash()
-        .map_err(|e| anyhow!("Blockhash error: {}", e))?;
-    let message = Message::new(&[confirm_ix], Some(&arbiter_keypair.pubkey()));
-    let transaction = Transaction::new(&[&arbiter_keypair], message, blockhash);
-    let signature = simulate_and_send(client, &transaction)?;
     println!("Arbiter confirmed! Signature: {}", signature);
     Ok(())
 }
 
 // Arbiter cancels escrow
 fn arbiter_cancel(
-    client: &RpcClient,
+    ctx: &Context,
     arbiter_keypair_path: &str,
     escrow_account: &str,
     buyer: &str,
+    verdict_template: Option<&str>,
 ) -> Result<()> {
-    let arbiter_keypair = read_keypair_file(arbiter_keypair_path)
-        .map_err(|_| anyhow!("Failed to read arbiter keypair"))?;
+    let arbiter_keypair = ctx.read_keypair(arbiter_keypair_path, "arbiter")?;
+    let mut transaction = build_arbiter_decision(
+        ctx,
+        ArbiterDecision::Refund,
+        &arbiter_keypair.pubkey(),
+        escrow_account,
+        buyer,
+        verdict_template,
+    )?;
+    totp::check(ctx, "arbiter-cancel", ctx.get_escrow(&transaction.escrow)?.amount)?;
+    transaction.sign(&[&arbiter_keypair])?;
+    let Some(signature) = transaction.execute(ctx)? else {
+        return Ok(());
+    };
+    println!("Arbiter canceled! Signature: {}", signature);
+    Ok(())
+}
+
+// Build phase of arbiter-confirm and arbiter-cancel; the arbiter signs and
+// `recipient` is the seller or the buyer respectively
+fn build_arbiter_decision(
+    ctx: &Context,
+    decision: ArbiterDecision,
+    arbiter: &Pubkey,
+    escrow_account: &str,
+    recipient: &str,
+    verdict_template: Option<&str>,
+) -> Result<PartiallySignedTx> {
     let escrow_pubkey = Pubkey::from_str(escrow_account)?;
-    let buyer_pubkey = Pubkey::from_str(buyer)?;
-    let program_id = Pubkey::from_str(PROGRAM_ID)?;
-    match check_state(client, escrow_account)? {
+    let recipient_pubkey = Pubkey::from_str(recipient)?;
+    let program_id = ctx.program_id;
+    match check_state(ctx, escrow_account)? {
         EscrowState::Funded => {},
         other_state => return Err(anyhow!(
             "Escrow must be in Funded state, current state: {:?}", 
             other_state
         )),
     }
-    let vault_pda = get_vault_pda(&escrow_pubkey, &program_id);
-    let cancel_ix = Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(arbiter_keypair.pubkey(), true),
-            AccountMeta::new(escrow_pubkey, false),
-            AccountMeta::new(vault_pda, false),
-            AccountMeta::new(buyer_pubkey, false),
-        ],
-        data: vec![5], // instruction index: arbiter_cancel
+    let (command, verdict, action) = match decision {
+        ArbiterDecision::Release => ("arbiter-confirm", "release", "arbiter_confirm"),
+        ArbiterDecision::Refund => ("arbiter-cancel", "refund", "arbiter_cancel"),
     };
-    let blockhash = client
-        .get_latest_blockhash()
-        .map_err(|e| anyhow!("Blockhash error: {}", e))?;
-    let message = Message::new(&[cancel_ix], Some(&arbiter_keypair.pubkey()));
-    let transaction = Transaction::new(&[&arbiter_keypair], message, blockhash);
-    let signature = simulate_and_send(client, &transaction)?;
-    println!("Arbiter canceled! Signature: {}", signature);
-    Ok(())
+    safety::check_finalized(ctx, &escrow_pubkey, command)?;
+    safety::check_cross(ctx, &escrow_pubkey, command)?;
+    let decision_ix = arbiter_instruction(
+        &program_id,
+        decision,
+        arbiter,
+        &escrow_pubkey,
+        &recipient_pubkey,
+    );
+    let mut instructions = vec![decision_ix];
+    if let Some(template) = verdict_template {
+        instructions.push(verdict::verdict_instruction(
+            ctx,
+            template,
+            &escrow_pubkey,
+            verdict,
+            arbiter,
+        )?);
+    }
+    PartiallySignedTx::new(ctx, &instructions, arbiter, &escrow_pubkey, action)
 }
 
 // Buyer and seller mutually cancel escrow
 fn mutual_cancel(
-    client: &RpcClient,
+    ctx: &Context,
     buyer_keypair_path: &str,
     seller_keypair_path: &str,
     escrow_account: &str,
 ) -> Result<()> {
-    let buyer_keypair = read_keypair_file(buyer_keypair_path)
-        .map_err(|_| anyhow!("Failed to read buyer keypair"))?;
-    let seller_keypair = read_keypair_file(seller_keypair_path)
-        .map_err(|_| anyhow!("Failed to read seller keypair"))?;
+    let buyer_keypair = ctx.read_keypair(buyer_keypair_path, "buyer")?;
+    let seller_keypair = ctx.read_keypair(seller_keypair_path, "seller")?;
+    let mut transaction = build_mutual_cancel(
+        ctx,
+        &buyer_keypair.pubkey(),
+        &seller_keypair.pubkey(),
+        escrow_account,
+    )?;
+    totp::check(ctx, "mutual-cancel", ctx.get_escrow(&transaction.escrow)?.amount)?;
+    transaction.sign(&[&buyer_keypair, &seller_keypair])?;
+    let Some(signature) = transaction.execute(ctx)? else {
+        return Ok(());
+    };
+    println!("Mutual cancel successful! Signature: {}", signature);
+    Ok(())
+}
+
+// Build phase of mutual-cancel; the buyer pays and both parties sign
+fn build_mutual_cancel(
+    ctx: &Context,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    escrow_account: &str,
+) -> Result<PartiallySignedTx> {
     let escrow_pubkey = Pubkey::from_str(escrow_account)?;
-    let program_id = Pubkey::from_str(PROGRAM_ID)?;
-    match check_state(client, escrow_account)? {
+    let program_id = ctx.program_id;
+    match check_state(ctx, escrow_account)? {
         EscrowState::Initialized | EscrowState::Funded => {},
         other_state => return Err(anyhow!(
             "Escrow must be in Initialized or Funded state, current state: {:?}", 
             other_state
         )),
     }
-    let vault_pda = get_vault_pda(&escrow_pubkey, &program_id);
-    let cancel_ix = Instruction {
-        program_id,
+    let cancel_ix = mutual_cancel_instruction(&program_id, buyer, seller, &escrow_pubkey);
+    PartiallySignedTx::new(ctx, &[cancel_ix], buyer, &escrow_pubkey, "mutual_cancel")
+}
+
+// Which way an arbiter settles a Funded escrow
+#[derive(Debug, Clone, Copy)]
+enum ArbiterDecision {
+    // arbiter_confirm: pay the seller
+    Release,
+    // arbiter_cancel: refund the buyer
+    Refund,
+}
+
+// Build arbiter_confirm or arbiter_cancel; `recipient` is the seller or the
+// buyer respectively
+fn arbiter_instruction(
+    program_id: &Pubkey,
+    decision: ArbiterDecision,
+    arbiter: &Pubkey,
+    escrow: &Pubkey,
+    recipient: &Pubkey,
+) -> Instruction {
+    let vault_pda = get_vault_pda(escrow, program_id);
+    let data = match decision {
+        ArbiterDecision::Release => vec![4], // instruction index: arbiter_confirm
+        ArbiterDecision::Refund => vec![5],  // instruction index: arbiter_cancel
+    };
+    Instruction {
+        program_id: *program_id,
         accounts: vec![
-            AccountMeta::new(buyer_keypair.pubkey(), true),
-            AccountMeta::new(seller_keypair.pubkey(), true),
-            AccountMeta::new(escrow_pubkey, false),
+            AccountMeta::new(*arbiter, true),
+            AccountMeta::new(*escrow, false),
+            AccountMeta::new(vault_pda, false),
+            AccountMeta::new(*recipient, false),
+        ],
+        data,
+    }
+}
+
+// Build the mutual_cancel instruction signed by both buyer and seller
+fn mutual_cancel_instruction(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    escrow: &Pubkey,
+) -> Instruction {
+    let vault_pda = get_vault_pda(escrow, program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*buyer, true),
+            AccountMeta::new(*seller, true),
+            AccountMeta::new(*escrow, false),
             AccountMeta::new(vault_pda, false),
             AccountMeta::new_readonly(solana_sdk::system_program::id(), false),
         ],
         data: vec![8], // instruction index: mutual_cancel
-    };
-    let blockhash = client.get_latest_blockhash()?;
-    let message = Message::new(
-        &[cancel_ix],
-        Some(&buyer_keypair.pubkey()),
-    );
-    let transaction = Transaction::new(
-        &[&buyer_keypair, &seller_keypair],
-        message,
-        blockhash,
-    );
-    let signature = simulate_and_send(client, &transaction)?;
-    println!("Mutual cancel successful! Signature: {}", signature);
-    Ok(())
+    }
 }
 
 // Close escrow account
 fn close_escrow(
-    client: &RpcClient,
+    ctx: &Context,
     closer_keypair_path: &str,
     escrow_account: &str,
+    anchor: bool,
 ) -> Result<()> {
-    let closer_keypair = read_keypair_file(closer_keypair_path)
-        .map_err(|_| anyhow!("Failed to read closer keypair"))?;
+    let closer_keypair = ctx.read_keypair(closer_keypair_path, "closer")?;
+    let mut transaction = build_close_escrow(ctx, &closer_keypair.pubkey(), escrow_account)?;
+    // Keep the final state once the account is gone. Anyone may close a
+    // settled escrow, but only a party's memo anchors its record.
+    let escrow = ctx.get_escrow(&transaction.escrow)?;
+    let anchor = anchor && {
+        let party = escrow.parties().contains(&closer_keypair.pubkey());
+        if !party {
+            eprintln!(
+                "Warning: {} is not a party to this escrow, archiving without an anchor",
+                closer_keypair.pubkey()
+            );
+        }
+        party
+    };
+    archive::archive(ctx, escrow_account, anchor.then_some(&closer_keypair))?;
+    interrupt::building();
+    transaction.sign(&[&closer_keypair])?;
+    let Some(signature) = transaction.execute(ctx)? else {
+        return Ok(());
+    };
+    println!("Escrow closed! Signature: {}", signature);
+    Ok(())
+}
+
+// Build phase of close; the closer signs
+fn build_close_escrow(
+    ctx: &Context,
+    closer: &Pubkey,
+    escrow_account: &str,
+) -> Result<PartiallySignedTx> {
     let escrow_pubkey = Pubkey::from_str(escrow_account)?;
-    let program_id = Pubkey::from_str(PROGRAM_ID)?;
-    match check_state(client, escrow_account)? {
+    let program_id = ctx.program_id;
+    match check_state(ctx, escrow_account)? {
         EscrowState::Completed | EscrowState::Cancelled => {},
         other_state => return Err(anyhow!(
             "Escrow must be Completed or Cancelled, current state: {:?}", 
             other_state
         )),
     }
-    let close_ix = Instruction {
-        program_id,
+    safety::check_finalized(ctx, &escrow_pubkey, "close")?;
+    safety::check_cross(ctx, &escrow_pubkey, "close")?;
+    let close_ix = close_instruction(&program_id, closer, &escrow_pubkey);
+    PartiallySignedTx::new(ctx, &[close_ix], closer, &escrow_pubkey, "close_escrow")
+}
+
+// Build the close_escrow instruction signed by the closer
+fn close_instruction(program_id: &Pubkey, closer: &Pubkey, escrow: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: *program_id,
         accounts: vec![
-            AccountMeta::new(closer_keypair.pubkey(), true),
-            AccountMeta::new(escrow_pubkey, false),
+            AccountMeta::new(*closer, true),
+            AccountMeta::new(*escrow, false),
         ],
         data: vec![6], // instruction index: close_escrow
-    };
-    let blockhash = client.get_latest_blockhash()?;
-    let message = Message::new(&[close_ix], Some(&closer_keypair.pubkey()));
-    let transaction = Transaction::new(&[&closer_keypair], message, blockhash);
-    let signature = simulate_and_send(client, &transaction)?;
-    println!("Escrow closed! Signature: {}", signature);
-    Ok(())
+    }
 }
 
 // Print escrow account info
 fn get_escrow_info(
-    client: &RpcClient,
+    ctx: &Context,
     escrow_account: &str,
 ) -> Result<()> {
     let escrow_pubkey = Pubkey::from_str(escrow_account)?;
-    let account_data = client.get_account_data(&escrow_pubkey)?;
-    if account_data.len() < 106 {
-        return Err(anyhow!("Invalid account data length"));
-    }
-    let buyer = Pubkey::new(&account_data[0..32]);
-    let seller = Pubkey::new(&account_data[32..64]);
-    let arbiter = Pubkey::new(&account_data[64..96]);
-    let amount = u64::from_le_bytes(account_data[96..104].try_into()?);
-    let state_byte = account_data[104];
-    let vault_bump = account_data[105];
-    let state = match state_byte {
-        0 => "Uninitialized",
-        1 => "Created",
-        2 => "Initialized",
-        3 => "Funded",
-        4 => "Completed",
-        5 => "Cancelled",
-        _ => "Unknown",
-    };
+    let escrow = ctx.get_escrow(&escrow_pubkey)?;
     println!("Escrow Information:");
     println!("====================");
-    println!("State: {}", state);
-    println!("Amount: {} lamports", amount);
-    println!("Buyer: {}", buyer);
-    println!("Seller: {}", seller);
-    println!("Arbiter: {}", arbiter);
-    println!("Vault Bump: {}", vault_bump);
+    println!("State: {}", escrow.state);
+    println!(
+        "Amount: {} lamports",
+        amount::format_lamports(escrow.amount.lamports())
+    );
+    println!("Buyer: {}", escrow.buyer);
+    println!("Seller: {}", escrow.seller);
+    println!("Arbiter: {}", escrow.arbiter);
+    println!("Vault Bump: {}", escrow.vault_bump);
+    println!("Fingerprint: {}", fingerprint::fingerprint(&escrow_pubkey, &escrow));
+    if let Some(age) = sla::state_age(ctx, &escrow_pubkey)? {
+        println!("In {} for: {}", escrow.state, ctx.config.sla.render(escrow.state, age));
+    }
     println!("====================");
-    Ok(())
+    note::show(ctx, &escrow_pubkey, &escrow)?;
+    ledger::show_deal(ctx, escrow_account)
 }
 
 // Derive vault PDA for escrow
 fn get_vault_pda(escrow_account: &Pubkey, program_id: &Pubkey) -> Pubkey {
-    let (pda, _) = Pubkey::find_program_address(
+    let (pda, _) = find_vault_pda(escrow_account, program_id);
+    pda
+}
+
+// Vault PDA together with its canonical bump
+fn find_vault_pda(escrow_account: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
         &[b"vault", escrow_account.as_ref()],
         program_id,
-    );
-    pda
-}
\ No newline at end of file
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn pubkey() -> impl Strategy<Value = Pubkey> {
+        any::<[u8; 32]>().prop_map(Pubkey::new_from_array)
+    }
+
+    // Every builder: addressed to the program, the signing party first and
+    // writable, and only `signers` sign
+    fn check_shape(instruction: &Instruction, program_id: &Pubkey, signers: &[Pubkey]) {
+        assert_eq!(instruction.program_id, *program_id);
+        assert!(instruction.accounts[0].is_writable);
+        let signing: Vec<Pubkey> = instruction
+            .accounts
+            .iter()
+            .filter(|meta| meta.is_signer)
+            .map(|meta| meta.pubkey)
+            .collect();
+        assert_eq!(signing, signers);
+    }
+
+    proptest! {
+        #[test]
+        fn create_offer_encodes_amount_and_arbiter(
+            program_id in pubkey(),
+            buyer in pubkey(),
+            escrow in pubkey(),
+            arbiter in pubkey(),
+            amount in any::<u64>(),
+        ) {
+            let lamports = Lamports::new(amount);
+            let instruction =
+                create_offer_instruction(&program_id, &buyer, &escrow, &arbiter, lamports);
+            check_shape(&instruction, &program_id, &[buyer]);
+            prop_assert_eq!(instruction.data.len(), 1 + 8 + 32);
+            prop_assert_eq!(instruction.data[0], 0);
+            prop_assert_eq!(&instruction.data[1..9], &amount.to_le_bytes());
+            prop_assert_eq!(&instruction.data[9..], arbiter.as_ref());
+            prop_assert_eq!(instruction.accounts[2].pubkey, get_vault_pda(&escrow, &program_id));
+        }
+
+        #[test]
+        fn join_encodes_seller(program_id in pubkey(), seller in pubkey(), escrow in pubkey()) {
+            let instruction = join_instruction(&program_id, &seller, &escrow);
+            check_shape(&instruction, &program_id, &[seller]);
+            prop_assert_eq!(instruction.data[0], 1);
+            prop_assert_eq!(&instruction.data[1..], seller.as_ref());
+        }
+
+        #[test]
+        fn party_instructions_use_their_index(
+            program_id in pubkey(),
+            buyer in pubkey(),
+            seller in pubkey(),
+            arbiter in pubkey(),
+            escrow in pubkey(),
+        ) {
+            let vault = get_vault_pda(&escrow, &program_id);
+            let cases = [
+                (fund_instruction(&program_id, &buyer, &escrow), vec![buyer], 2),
+                (confirm_instruction(&program_id, &seller, &escrow), vec![seller], 3),
+                (
+                    arbiter_instruction(
+                        &program_id,
+                        ArbiterDecision::Release,
+                        &arbiter,
+                        &escrow,
+                        &seller,
+                    ),
+                    vec![arbiter],
+                    4,
+                ),
+                (
+                    arbiter_instruction(
+                        &program_id,
+                        ArbiterDecision::Refund,
+                        &arbiter,
+                        &escrow,
+                        &buyer,
+                    ),
+                    vec![arbiter],
+                    5,
+                ),
+                (close_instruction(&program_id, &buyer, &escrow), vec![buyer], 6),
+                (
+                    mutual_cancel_instruction(&program_id, &buyer, &seller, &escrow),
+                    vec![buyer, seller],
+                    8,
+                ),
+            ];
+            for (instruction, signers, index) in cases {
+                check_shape(&instruction, &program_id, &signers);
+                prop_assert_eq!(&instruction.data, &vec![index]);
+                prop_assert!(instruction.accounts.iter().any(|meta| meta.pubkey == escrow));
+                // Everything but close moves lamports through the vault
+                if index != 6 {
+                    prop_assert!(instruction.accounts.iter().any(|meta| meta.pubkey == vault));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use anyhow::{anyhow, Result};
+use solana_client::{
+    rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient},
+    rpc_config::RpcTransactionConfig,
+    rpc_response::RpcConfirmedTransactionStatusWithSignature,
+};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey,
+    pubkey::Pubkey,
+    signature::Signature,
+};
+use solana_transaction_status::UiTransactionEncoding;
+use std::str::FromStr;
+
+pub const MEMO_PROGRAM_ID: Pubkey = pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
+// A memo found in an address's signature history
+#[derive(Debug, Clone)]
+pub struct MemoRecord {
+    pub signature: String,
+    pub memo: String,
+    pub block_time: Option<i64>,
+}
+
+// Build an SPL memo instruction; every listed account must sign
+pub fn memo_instruction(memo: &str, signers: &[&Pubkey]) -> Instruction {
+    Instruction {
+        program_id: MEMO_PROGRAM_ID,
+        accounts: signers
+            .iter()
+            .map(|signer| AccountMeta::new_readonly(**signer, true))
+            .collect(),
+        data: memo.as_bytes().to_vec(),
+    }
+}
+
+// Memos of successful transactions touching `address`, newest first
+pub fn find_memos(client: &RpcClient, address: &Pubkey) -> Result<Vec<MemoRecord>> {
+    Ok(collect_memos(client.get_signatures_for_address(address)?))
+}
+
+// Like `find_memos`, but only looks at the `limit` newest transactions
+pub fn find_recent_memos(
+    client: &RpcClient,
+    address: &Pubkey,
+    limit: usize,
+) -> Result<Vec<MemoRecord>> {
+    let statuses = client.get_signatures_for_address_with_config(
+        address,
+        GetConfirmedSignaturesForAddress2Config {
+            limit: Some(limit),
+            ..GetConfirmedSignaturesForAddress2Config::default()
+        },
+    )?;
+    Ok(collect_memos(statuses))
+}
+
+fn collect_memos(statuses: Vec<RpcConfirmedTransactionStatusWithSignature>) -> Vec<MemoRecord> {
+    let mut records = Vec::new();
+    for status in statuses {
+        if status.err.is_some() {
+            continue;
+        }
+        if let Some(field) = &status.memo {
+            for memo in split_memo_field(field) {
+                records.push(MemoRecord {
+                    signature: status.signature.clone(),
+                    memo,
+                    block_time: status.block_time,
+                });
+            }
+        }
+    }
+    records
+}
+
+// Accounts that signed the given transaction
+pub fn transaction_signers(client: &RpcClient, signature: &str) -> Result<Vec<Pubkey>> {
+    let signature = Signature::from_str(signature)?;
+    let transaction = client.get_transaction_with_config(
+        &signature,
+        RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: None,
+            max_supported_transaction_version: Some(0),
+        },
+    )?;
+    let decoded = transaction
+        .transaction
+        .transaction
+        .decode()
+        .ok_or_else(|| anyhow!("Failed to decode transaction {}", signature))?;
+    let signer_count = decoded.message.header().num_required_signatures as usize;
+    Ok(decoded
+        .message
+        .static_account_keys()
+        .iter()
+        .take(signer_count)
+        .copied()
+        .collect())
+}
+
+// The RPC reports memos as "[len] text", joined with "; " when a transaction
+// carries several of them
+fn split_memo_field(field: &str) -> Vec<String> {
+    let mut memos = Vec::new();
+    let mut rest = field;
+    while let Some(stripped) = rest.strip_prefix('[') {
+        let Some(close) = stripped.find("] ") else {
+            break;
+        };
+        let Ok(len) = stripped[..close].parse::<usize>() else {
+            break;
+        };
+        let start = close + 2;
+        match stripped.get(start..start + len) {
+            Some(text) => memos.push(text.to_string()),
+            None => {
+                memos.push(stripped[start..].to_string());
+                return memos;
+            }
+        }
+        rest = stripped[start + len..].trim_start_matches("; ");
+    }
+    if memos.is_empty() && !field.is_empty() {
+        memos.push(field.to_string());
+    }
+    memos
+}
@@ -0,0 +1,130 @@
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Every request to a host other than the RPC node (indexers, URL hooks,
+// Jupiter, storage gateways, releases) goes through here. Builds without
+// the `external-network` feature compile none of these requests (reqwest
+// itself stays, as solana-client's RPC transport);
+// --no-network-except-rpc refuses them at run time.
+static RPC_ONLY: AtomicBool = AtomicBool::new(false);
+
+pub fn restrict_to_rpc() {
+    RPC_ONLY.store(true, Ordering::SeqCst);
+}
+
+#[derive(Clone, Copy)]
+enum Method {
+    Get,
+    Post,
+}
+
+// Payloads are only read by the HTTP client
+#[cfg_attr(not(feature = "external-network"), allow(dead_code))]
+pub enum Body {
+    Empty,
+    Json(Value),
+    // Already serialized JSON, sent byte for byte (hook signatures cover it)
+    JsonText(String),
+    // A multipart form with a single `file` field
+    File { name: String, contents: Vec<u8> },
+}
+
+// GET `url`, returning the body of a 2xx response
+pub fn get(what: &str, url: &str, query: &[(&str, String)]) -> Result<Vec<u8>> {
+    send(what, Method::Get, url, query, &[], Body::Empty)
+}
+
+pub fn get_json<T: DeserializeOwned>(what: &str, url: &str, query: &[(&str, String)]) -> Result<T> {
+    let body = get(what, url, query)?;
+    serde_json::from_slice(&body).map_err(|e| anyhow!("Invalid {} response: {}", what, e))
+}
+
+// POST to `url`, returning the body of a 2xx response
+pub fn post(what: &str, url: &str, headers: &[(&str, String)], body: Body) -> Result<Vec<u8>> {
+    send(what, Method::Post, url, &[], headers, body)
+}
+
+pub fn post_json<T: DeserializeOwned>(what: &str, url: &str, body: Value) -> Result<T> {
+    let body = post(what, url, &[], Body::Json(body))?;
+    serde_json::from_slice(&body).map_err(|e| anyhow!("Invalid {} response: {}", what, e))
+}
+
+fn send(
+    what: &str,
+    method: Method,
+    url: &str,
+    query: &[(&str, String)],
+    headers: &[(&str, String)],
+    body: Body,
+) -> Result<Vec<u8>> {
+    if RPC_ONLY.load(Ordering::SeqCst) {
+        return Err(anyhow!(
+            "{} is disabled: --no-network-except-rpc only allows the RPC node",
+            what
+        ));
+    }
+    transport(what, method, url, query, headers, body)
+}
+
+#[cfg(feature = "external-network")]
+fn transport(
+    what: &str,
+    method: Method,
+    url: &str,
+    query: &[(&str, String)],
+    headers: &[(&str, String)],
+    body: Body,
+) -> Result<Vec<u8>> {
+    use reqwest::blocking::{multipart, Client};
+
+    let client = Client::builder()
+        .user_agent(concat!("escrow-cli/", env!("CARGO_PKG_VERSION")))
+        .build()?;
+    let mut request = match method {
+        Method::Get => client.get(url),
+        Method::Post => client.post(url),
+    };
+    if !query.is_empty() {
+        request = request.query(query);
+    }
+    for (name, value) in headers {
+        request = request.header(*name, value);
+    }
+    request = match body {
+        Body::Empty => request,
+        Body::Json(value) => request.json(&value),
+        Body::JsonText(text) => request
+            .header("Content-Type", "application/json")
+            .body(text),
+        Body::File { name, contents } => request.multipart(
+            multipart::Form::new().part("file", multipart::Part::bytes(contents).file_name(name)),
+        ),
+    };
+    let response = request
+        .send()
+        .map_err(|e| anyhow!("{} request failed: {}", what, e))?;
+    if !response.status().is_success() {
+        return Err(anyhow!("{} returned HTTP {}", what, response.status()));
+    }
+    let bytes = response
+        .bytes()
+        .map_err(|e| anyhow!("{} response failed: {}", what, e))?;
+    Ok(bytes.to_vec())
+}
+
+#[cfg(not(feature = "external-network"))]
+fn transport(
+    what: &str,
+    _method: Method,
+    _url: &str,
+    _query: &[(&str, String)],
+    _headers: &[(&str, String)],
+    _body: Body,
+) -> Result<Vec<u8>> {
+    Err(anyhow!(
+        "{} is unavailable: this build leaves out the external-network feature",
+        what
+    ))
+}
@@ -0,0 +1,112 @@
+use anyhow::{anyhow, Result};
+use serde_json::json;
+use solana_sdk::{message::Message, pubkey::Pubkey, signature::Signer, transaction::Transaction};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::account::EscrowAccount;
+use crate::duration::format_duration;
+use crate::memo::{find_recent_memos, memo_instruction, transaction_signers, MemoRecord};
+use crate::{hooks, simulate_and_send, sla, transcript, Context};
+
+const NOTE_TAG: &str = "escrow-note:v1";
+// Keeps the memo transaction well inside the packet size limit
+const MAX_NOTE_LEN: usize = 500;
+// How far back in the arbiter's history `show` looks for notes
+const NOTE_SCAN_LIMIT: usize = 200;
+
+// Publish a note from the escrow's arbiter to both parties as a memo signed
+// by the arbiter
+pub fn post(
+    ctx: &Context,
+    arbiter_keypair_path: &str,
+    escrow_account: &str,
+    text: &str,
+) -> Result<()> {
+    let arbiter_keypair = ctx.read_keypair(arbiter_keypair_path, "arbiter")?;
+    let arbiter = arbiter_keypair.pubkey();
+    let escrow_pubkey = Pubkey::from_str(escrow_account)?;
+    let escrow = ctx.get_escrow(&escrow_pubkey)?;
+    if escrow.arbiter != arbiter {
+        return Err(anyhow!(
+            "Escrow {} names arbiter {}, not {}",
+            escrow_pubkey,
+            escrow.arbiter,
+            arbiter
+        ));
+    }
+    let text = text.trim();
+    if text.is_empty() || text.len() > MAX_NOTE_LEN {
+        return Err(anyhow!("A note must be 1 to {} bytes long", MAX_NOTE_LEN));
+    }
+    let memo = format!("{} {} {}", NOTE_TAG, escrow_pubkey, text);
+    let memo_ix = memo_instruction(&memo, &[&arbiter]);
+    let blockhash = ctx.client.get_latest_blockhash()?;
+    let message = Message::new(&[memo_ix], Some(&arbiter));
+    let transaction = Transaction::new(&[&arbiter_keypair], message, blockhash);
+    let Some(signature) = simulate_and_send(ctx, &transaction, &escrow_pubkey, "arbiter_note")?
+    else {
+        return Ok(());
+    };
+    println!("Note posted successfully! Signature: {}", signature);
+    hooks::notify(
+        &ctx.config,
+        &json!({
+            "event": "arbiter_note",
+            "escrow": escrow_pubkey.to_string(),
+            "buyer": escrow.buyer.to_string(),
+            "seller": escrow.seller.to_string(),
+            "arbiter": arbiter.to_string(),
+            "note": text,
+            "signature": signature.to_string(),
+        }),
+    );
+    Ok(())
+}
+
+// Notes the escrow's arbiter signed for it among its recent transactions, newest
+// first. They live in the arbiter's history, where anyone can add a memo; only
+// the arbiter's count.
+fn notes(ctx: &Context, escrow_pubkey: &Pubkey, escrow: &EscrowAccount) -> Result<Vec<MemoRecord>> {
+    let prefix = format!("{} {} ", NOTE_TAG, escrow_pubkey);
+    let mut notes = Vec::new();
+    for mut record in find_recent_memos(&ctx.client, &escrow.arbiter, NOTE_SCAN_LIMIT)? {
+        let Some(text) = record.memo.strip_prefix(&prefix) else {
+            continue;
+        };
+        if !transaction_signers(&ctx.client, &record.signature)?.contains(&escrow.arbiter) {
+            continue;
+        }
+        record.memo = text.to_string();
+        notes.push(record);
+    }
+    Ok(notes)
+}
+
+// Print the arbiter's notes, if there are any. Notes are advisory, so an RPC
+// failure only warns instead of failing the caller.
+pub fn show(ctx: &Context, escrow_pubkey: &Pubkey, escrow: &EscrowAccount) -> Result<()> {
+    let notes = match notes(ctx, escrow_pubkey, escrow) {
+        Ok(notes) => notes,
+        Err(e) => {
+            eprintln!("Warning: could not load notes from the arbiter: {}", e);
+            return Ok(());
+        }
+    };
+    if notes.is_empty() {
+        return Ok(());
+    }
+    println!("*** Notes from the arbiter ***");
+    for note in notes {
+        let age = match note.block_time {
+            Some(time) => format!(
+                "{} ago",
+                format_duration(Duration::from_secs((sla::now() - time).max(0) as u64))
+            ),
+            None => "unknown".to_string(),
+        };
+        transcript::memo(&note.memo);
+        println!("  [{}] {}", age, note.memo);
+    }
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use anyhow::{anyhow, Result};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    signature::{read_keypair_file, write_keypair_file, Keypair, Signer},
+};
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+
+use crate::amount::{format_sol, LAMPORTS_PER_SOL};
+use crate::config::{config_dir, config_path, default_config_path, Config};
+use crate::{selftest, Context};
+
+const NETWORKS: [(&str, &str); 3] = [
+    ("devnet", "https://api.devnet.solana.com"),
+    ("testnet", "https://api.testnet.solana.com"),
+    ("mainnet-beta", "https://api.mainnet-beta.solana.com"),
+];
+
+// True when nothing is configured yet and a human is at the terminal
+pub fn is_first_run(config_arg: Option<&str>) -> bool {
+    config_arg.is_none()
+        && std::env::var_os("ESCROW_CLI_CONFIG").is_none()
+        && default_config_path().is_some_and(|path| !path.exists())
+        && io::stdin().is_terminal()
+        && io::stdout().is_terminal()
+}
+
+// Guided setup: pick a network, create or import a key, optionally airdrop
+// on devnet, and write the resulting config file
+pub fn run(config_arg: Option<&str>) -> Result<()> {
+    let path = config_path(config_arg)?;
+    // A config that fails to load can't enforce its sandbox; `init` is how
+    // it gets repaired
+    let sandbox = Config::load(config_arg)
+        .map(|config| config.sandbox)
+        .unwrap_or_default();
+    sandbox.check_write(&path)?;
+    println!("Welcome to escrow-cli! Let's set things up.");
+    if !confirm("Run the setup assistant now?", true)? {
+        write_config(&path, &toml::Table::new())?;
+        println!("Skipped. Wrote an empty config to {}", path.display());
+        return Ok(());
+    }
+
+    println!("Networks:");
+    for (index, (name, url)) in NETWORKS.iter().enumerate() {
+        println!("  {}) {} ({})", index + 1, name, url);
+    }
+    let choice = prompt("Network number or custom RPC URL", "1")?;
+    let (network, rpc_url) = match choice.parse::<usize>() {
+        Ok(index) if (1..=NETWORKS.len()).contains(&index) => {
+            let (name, url) = NETWORKS[index - 1];
+            (name.to_string(), url.to_string())
+        }
+        _ if choice.starts_with("http://") || choice.starts_with("https://") => {
+            ("custom".to_string(), choice)
+        }
+        _ => return Err(anyhow!("Unknown network choice '{}'", choice)),
+    };
+
+    let keystore = config_dir()
+        .ok_or_else(|| anyhow!("Cannot locate a config directory"))?
+        .join("keys");
+    sandbox.check_write(&keystore)?;
+    fs::create_dir_all(&keystore)?;
+    let key_path = keystore.join("default.json");
+    sandbox.check_write(&key_path)?;
+    let existing = prompt("Path of an existing keypair to import (empty to create one)", "")?;
+    let keypair = if existing.is_empty() {
+        Keypair::new()
+    } else {
+        read_keypair_file(&existing).map_err(|_| anyhow!("Failed to read keypair {}", existing))?
+    };
+    if key_path.exists() && !confirm(&format!("Overwrite {}?", key_path.display()), false)? {
+        return Err(anyhow!("Setup aborted, {} left untouched", key_path.display()));
+    }
+    write_keypair_file(&keypair, &key_path)
+        .map_err(|e| anyhow!("Failed to write {}: {}", key_path.display(), e))?;
+    println!("Keypair {} saved to {}", keypair.pubkey(), key_path.display());
+
+    if network == "devnet" && confirm("Request a 1 SOL devnet airdrop?", true)? {
+        let client = RpcClient::new_with_commitment(rpc_url.clone(), CommitmentConfig::confirmed());
+        match airdrop(&client, &keypair, LAMPORTS_PER_SOL) {
+            Ok(balance) => println!("Airdrop received, balance: {} SOL", format_sol(balance)),
+            Err(e) => println!("Airdrop failed ({}), you can retry later with a faucet", e),
+        }
+    }
+
+    let mut table = toml::Table::new();
+    table.insert("rpc_url".to_string(), toml::Value::String(rpc_url));
+    table.insert(
+        "keystore".to_string(),
+        toml::Value::String(keystore.display().to_string()),
+    );
+    write_config(&path, &table)?;
+    println!("Config written to {}", path.display());
+    println!("Keypairs in the keystore can be passed by name, e.g. `-b default.json`.");
+
+    if network == "devnet" && confirm("Run a self-test escrow against throwaway keys?", true)? {
+        let path = path
+            .to_str()
+            .ok_or_else(|| anyhow!("Non UTF-8 config path {}", path.display()))?;
+        let ctx = Context::new(Config::load(Some(path))?, None)?;
+        selftest::run(&ctx, LAMPORTS_PER_SOL / 100)?;
+    }
+    Ok(())
+}
+
+// Request an airdrop and wait for it to land; returns the new balance
+pub fn airdrop(client: &RpcClient, keypair: &Keypair, lamports: u64) -> Result<u64> {
+    let signature = client.request_airdrop(&keypair.pubkey(), lamports)?;
+    client.poll_for_signature(&signature)?;
+    Ok(client.get_balance(&keypair.pubkey())?)
+}
+
+fn write_config(path: &Path, table: &toml::Table) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, toml::to_string_pretty(table)?)?;
+    Ok(())
+}
+
+pub fn prompt(question: &str, default: &str) -> Result<String> {
+    if default.is_empty() {
+        print!("{}: ", question);
+    } else {
+        print!("{} [{}]: ", question, default);
+    }
+    io::stdout().flush()?;
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    let answer = line.trim();
+    Ok(if answer.is_empty() {
+        default.to_string()
+    } else {
+        answer.to_string()
+    })
+}
+
+pub fn confirm(question: &str, default: bool) -> Result<bool> {
+    let answer = prompt(question, if default { "Y/n" } else { "y/N" })?;
+    Ok(match answer.to_ascii_lowercase().as_str() {
+        "y" | "yes" => true,
+        "n" | "no" => false,
+        _ => default,
+    })
+}
@@ -0,0 +1,177 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Signature, Signer},
+};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::amount::{parse_amount, Lamports};
+use crate::ledger::parse_tag;
+use crate::Context;
+
+const PACKET_VERSION: u32 = 1;
+
+// A standard deal type an arbiter has pre-approved: everything create-offer
+// needs besides the buyer and escrow keypairs, signed by the arbiter
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DealPacket {
+    pub version: u32,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub arbiter: String,
+    // Fixed amount, e.g. "1.5SOL" or "1500000000"; without one the buyer
+    // picks an amount up to `max_amount`
+    pub amount: Option<String>,
+    pub max_amount: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // SHA-256 of the terms document every offer of this type follows
+    pub terms_sha256: Option<String>,
+    pub endorsement: Option<String>,
+}
+
+impl DealPacket {
+    pub fn load(path: &str) -> Result<DealPacket> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| anyhow!("Failed to read {}: {}", path, e))?;
+        let packet: DealPacket =
+            toml::from_str(&contents).map_err(|e| anyhow!("Invalid deal packet {}: {}", path, e))?;
+        if packet.version != PACKET_VERSION {
+            return Err(anyhow!(
+                "Unsupported deal packet version {} (expected {})",
+                packet.version,
+                PACKET_VERSION
+            ));
+        }
+        Ok(packet)
+    }
+
+    pub fn arbiter(&self) -> Result<Pubkey> {
+        Pubkey::from_str(&self.arbiter)
+            .map_err(|_| anyhow!("Invalid arbiter pubkey '{}'", self.arbiter))
+    }
+
+    fn amounts(&self) -> Result<(Option<Lamports>, Option<Lamports>)> {
+        let parse = |value: &Option<String>| -> Result<Option<Lamports>> {
+            value
+                .as_deref()
+                .map(|amount| parse_amount(amount).map_err(|e| anyhow!("{}", e)))
+                .transpose()
+        };
+        let (amount, max_amount) = (parse(&self.amount)?, parse(&self.max_amount)?);
+        if amount.is_some() == max_amount.is_some() {
+            return Err(anyhow!("A deal packet needs exactly one of amount and max_amount"));
+        }
+        Ok((amount, max_amount))
+    }
+
+    // What the arbiter signs: every field but the endorsement, amounts in
+    // lamports, as JSON in field order
+    fn signed_message(&self) -> Result<Vec<u8>> {
+        let (amount, max_amount) = self.amounts()?;
+        let fields = serde_json::json!([
+            self.version,
+            self.name,
+            self.description,
+            self.arbiter,
+            amount.map(Lamports::lamports),
+            max_amount.map(Lamports::lamports),
+            self.tags,
+            self.terms_sha256,
+        ]);
+        Ok(format!("escrow-packet:v1 {}", fields).into_bytes())
+    }
+
+    // Check the fields and the arbiter's endorsement
+    pub fn validate(&self) -> Result<()> {
+        let arbiter = self.arbiter()?;
+        for tag in &self.tags {
+            parse_tag(tag).map_err(|e| anyhow!(e))?;
+        }
+        let message = self.signed_message()?;
+        let endorsement = self
+            .endorsement
+            .as_deref()
+            .ok_or_else(|| anyhow!("Deal packet '{}' is not endorsed by its arbiter", self.name))?;
+        let signature = Signature::from_str(endorsement)
+            .map_err(|_| anyhow!("Invalid endorsement signature '{}'", endorsement))?;
+        if !signature.verify(arbiter.as_ref(), &message) {
+            return Err(anyhow!(
+                "Endorsement on deal packet '{}' was not made by arbiter {} or the packet was \
+                 changed after signing",
+                self.name,
+                arbiter
+            ));
+        }
+        Ok(())
+    }
+
+    // The offer amount: the packet's fixed amount, or the buyer's within the
+    // packet's bound
+    pub fn offer_amount(&self, requested: Option<Lamports>) -> Result<Lamports> {
+        match (self.amounts()?, requested) {
+            ((Some(fixed), _), None) => Ok(fixed),
+            ((Some(fixed), _), Some(amount)) if amount == fixed => Ok(fixed),
+            ((Some(fixed), _), Some(_)) => Err(anyhow!(
+                "Deal packet '{}' fixes the amount at {} SOL",
+                self.name,
+                fixed
+            )),
+            ((None, Some(max)), Some(amount)) if amount <= max => Ok(amount),
+            ((None, Some(max)), Some(_)) => Err(anyhow!(
+                "Deal packet '{}' allows at most {} SOL",
+                self.name,
+                max
+            )),
+            _ => Err(anyhow!("Deal packet '{}' needs an amount (-m)", self.name)),
+        }
+    }
+}
+
+// Print a packet and whether its endorsement holds
+pub fn validate(path: &str) -> Result<()> {
+    let packet = DealPacket::load(path)?;
+    packet.validate()?;
+    let (amount, max_amount) = packet.amounts()?;
+    println!("Deal packet: {}", packet.name);
+    if !packet.description.is_empty() {
+        println!("  {}", packet.description);
+    }
+    println!("  Arbiter: {}", packet.arbiter);
+    match (amount, max_amount) {
+        (Some(amount), _) => println!("  Amount:  {} SOL", amount),
+        (_, Some(max)) => println!("  Amount:  up to {} SOL", max),
+        _ => {}
+    }
+    if !packet.tags.is_empty() {
+        println!("  Tags:    {}", packet.tags.join(", "));
+    }
+    if let Some(digest) = &packet.terms_sha256 {
+        println!("  Terms:   SHA-256 {}", digest);
+    }
+    println!("Endorsement verified");
+    Ok(())
+}
+
+// Endorse a packet as its arbiter, writing the signature into the file
+pub fn sign(ctx: &Context, path: &str, keypair_path: &str) -> Result<()> {
+    let mut packet = DealPacket::load(path)?;
+    let keypair = ctx.read_keypair(keypair_path, "arbiter")?;
+    if packet.arbiter()? != keypair.pubkey() {
+        return Err(anyhow!(
+            "The packet names arbiter {}, not {}",
+            packet.arbiter,
+            keypair.pubkey()
+        ));
+    }
+    packet.endorsement = Some(keypair.sign_message(&packet.signed_message()?).to_string());
+    ctx.config.sandbox.check_write(Path::new(path))?;
+    fs::write(path, toml::to_string_pretty(&packet)?)?;
+    println!("Endorsed deal packet '{}' in {}", packet.name, path);
+    Ok(())
+}
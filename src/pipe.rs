@@ -0,0 +1,51 @@
+use anyhow::{anyhow, Result};
+use solana_sdk::signature::{read_keypair, Keypair};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::sandbox::Sandbox;
+
+// File arguments given as `-` read from stdin or write to stdout
+pub const STDIO: &str = "-";
+
+// Stdin can only be consumed once per invocation
+static STDIN_TAKEN: AtomicBool = AtomicBool::new(false);
+
+fn take_stdin(what: &str) -> Result<io::Stdin> {
+    if STDIN_TAKEN.swap(true, Ordering::SeqCst) {
+        return Err(anyhow!("Only one argument can be read from stdin ({})", what));
+    }
+    Ok(io::stdin())
+}
+
+// Read a file, or stdin for `-`
+pub fn read_input(path: &str) -> Result<Vec<u8>> {
+    if path == STDIO {
+        let mut contents = Vec::new();
+        take_stdin(path)?.read_to_end(&mut contents)?;
+        return Ok(contents);
+    }
+    fs::read(path).map_err(|e| anyhow!("Failed to read {}: {}", path, e))
+}
+
+// Write a file, or stdout for `-`
+pub fn write_output(sandbox: &Sandbox, path: &str, contents: &[u8]) -> Result<()> {
+    if path == STDIO {
+        let mut stdout = io::stdout();
+        stdout.write_all(contents)?;
+        if !contents.ends_with(b"\n") {
+            stdout.write_all(b"\n")?;
+        }
+        return Ok(stdout.flush()?);
+    }
+    sandbox.check_write(Path::new(path))?;
+    fs::write(path, contents).map_err(|e| anyhow!("Failed to write {}: {}", path, e))
+}
+
+// A keypair in the usual JSON byte-array format piped in on stdin
+pub fn read_stdin_keypair(role: &str) -> Result<Keypair> {
+    read_keypair(&mut take_stdin(role)?)
+        .map_err(|e| anyhow!("Failed to read {} keypair from stdin: {}", role, e))
+}
@@ -0,0 +1,39 @@
+use anyhow::{anyhow, Result};
+use std::io::ErrorKind;
+use std::process::Command;
+
+use crate::transcript;
+
+pub const PLUGIN_PREFIX: &str = "escrow-cli-";
+
+// Run an external `escrow-cli-<name>` executable from PATH, git-style.
+// The plugin receives the remaining arguments plus the RPC endpoint and
+// program ID through the environment, and its exit code is propagated. The
+// endpoint is passed in full so the plugin can use it, API key and
+// credentials included: a plugin runs with the user's own privileges and can
+// read the config file anyway, so only install plugins you trust.
+// Plugins are expected to honour ESCROW_CLI_READ_ONLY=1.
+pub fn run_plugin(args: &[String], rpc_url: &str, program_id: &str, read_only: bool) -> Result<()> {
+    let (name, rest) = args
+        .split_first()
+        .ok_or_else(|| anyhow!("Missing plugin command name"))?;
+    let executable = format!("{}{}", PLUGIN_PREFIX, name);
+    let status = Command::new(&executable)
+        .args(rest)
+        .env("ESCROW_CLI_RPC_URL", rpc_url)
+        .env("ESCROW_CLI_PROGRAM_ID", program_id)
+        .env("ESCROW_CLI_READ_ONLY", if read_only { "1" } else { "0" })
+        .status()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => anyhow!(
+                "Unknown command '{}': no '{}' executable found in PATH",
+                name,
+                executable
+            ),
+            _ => anyhow!("Failed to run plugin '{}': {}", executable, e),
+        })?;
+    if !status.success() {
+        transcript::exit(status.code().unwrap_or(1));
+    }
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use anyhow::{anyhow, Result};
+use solana_sdk::{
+    packet::PACKET_DATA_SIZE,
+    transaction::{Transaction, MAX_TX_ACCOUNT_LOCKS},
+};
+
+// Catch transactions the cluster would reject outright, with a message that
+// says which limit was hit instead of an opaque RPC error
+pub fn check(transaction: &Transaction) -> Result<()> {
+    let size = bincode::serialized_size(transaction)? as usize;
+    if size > PACKET_DATA_SIZE {
+        return Err(anyhow!(
+            "Transaction is {} bytes, over the {}-byte packet limit",
+            size,
+            PACKET_DATA_SIZE
+        ));
+    }
+    let message = &transaction.message;
+    let required = message.header.num_required_signatures as usize;
+    if transaction.signatures.len() != required {
+        return Err(anyhow!(
+            "Transaction carries {} signatures but its message requires {}",
+            transaction.signatures.len(),
+            required
+        ));
+    }
+    let accounts = message.account_keys.len();
+    if accounts > MAX_TX_ACCOUNT_LOCKS {
+        return Err(anyhow!(
+            "Transaction locks {} accounts, over the limit of {}",
+            accounts,
+            MAX_TX_ACCOUNT_LOCKS
+        ));
+    }
+    // A malformed header would make the runtime misjudge which accounts it
+    // may write
+    let header = &message.header;
+    if header.num_readonly_signed_accounts as usize > required
+        || required + header.num_readonly_unsigned_accounts as usize > accounts
+    {
+        return Err(anyhow!("Transaction header is inconsistent with its account list"));
+    }
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use anyhow::Result;
+use solana_account_decoder::UiAccount;
+use solana_sdk::{account::Account, pubkey::Pubkey, transaction::Transaction};
+
+use crate::account::EscrowAccount;
+use crate::amount::format_sol;
+use crate::{get_vault_pda, Context};
+
+// Accounts the transaction may write, which are the only ones whose
+// balances can move
+pub fn writable_accounts(transaction: &Transaction) -> Vec<Pubkey> {
+    let message = &transaction.message;
+    message
+        .account_keys
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| message.is_writable(*index))
+        .map(|(_, key)| *key)
+        .collect()
+}
+
+// Show who would gain or lose lamports, comparing current balances with the
+// account states returned by the simulation. The simulation runs the program
+// version actually deployed, so this holds even if it differs from the one
+// this CLI was written against.
+pub fn show(
+    ctx: &Context,
+    escrow: &Pubkey,
+    addresses: &[Pubkey],
+    before: &[Option<Account>],
+    after: &[Option<UiAccount>],
+) -> Result<()> {
+    let escrow_before = addresses
+        .iter()
+        .position(|address| address == escrow)
+        .and_then(|index| before[index].as_ref())
+        .and_then(|account| EscrowAccount::unpack(&account.data).ok());
+    let escrow_after = addresses
+        .iter()
+        .position(|address| address == escrow)
+        .and_then(|index| after.get(index)?.as_ref()?.decode::<Account>())
+        .and_then(|account| EscrowAccount::unpack(&account.data).ok());
+    let vault = get_vault_pda(escrow, &ctx.program_id);
+    let role = |address: &Pubkey| -> &'static str {
+        let parties = escrow_before.as_ref().or(escrow_after.as_ref());
+        match parties {
+            _ if address == escrow => "escrow",
+            _ if *address == vault => "vault",
+            Some(parties) if *address == parties.buyer => "buyer",
+            Some(parties) if *address == parties.seller => "seller",
+            Some(parties) if *address == parties.arbiter => "arbiter",
+            _ => "other",
+        }
+    };
+
+    println!("Fund movements (dry run):");
+    let mut moved = false;
+    for (index, address) in addresses.iter().enumerate() {
+        let old = before[index].as_ref().map_or(0, |account| account.lamports);
+        let new = after
+            .get(index)
+            .and_then(|account| account.as_ref())
+            .map_or(0, |account| account.lamports);
+        if old == new {
+            continue;
+        }
+        moved = true;
+        let (sign, delta) = if new > old { ('+', new - old) } else { ('-', old - new) };
+        println!(
+            "  {:<8} {}  {}{} SOL  ({} -> {})",
+            role(address),
+            address,
+            sign,
+            format_sol(delta),
+            format_sol(old),
+            format_sol(new)
+        );
+    }
+    if !moved {
+        println!("  No balance changes");
+    }
+    match (&escrow_before, &escrow_after) {
+        (Some(before), Some(after)) if before.state != after.state => {
+            println!("Escrow state: {} -> {}", before.state, after.state)
+        }
+        (Some(before), None) => println!("Escrow state: {} -> closed", before.state),
+        (None, Some(after)) => println!("Escrow state: created as {}", after.state),
+        _ => {}
+    }
+    println!("The fee payer's change may include the transaction fee.");
+    Ok(())
+}
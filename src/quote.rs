@@ -0,0 +1,186 @@
+use anyhow::{anyhow, Result};
+use clap::Args;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::account::EscrowAccount;
+use crate::amount::LAMPORTS_PER_SOL;
+use crate::ledger::{ListSort, PageFilter};
+use crate::memo::{find_recent_memos, transaction_signers};
+use crate::search::open_offers;
+use crate::Context;
+
+const QUOTE_TAG: &str = "escrow-quote:v1";
+// How far back in an escrow's history `offers` looks for its quote
+const QUOTE_SCAN_LIMIT: usize = 100;
+
+// Fiat terms of a P2P trade, published with the offer as a memo
+#[derive(Args, Debug, Default, Clone)]
+pub struct FiatQuote {
+    /// Fiat price the buyer pays off-chain for the escrowed SOL, e.g. 1450.50
+    #[arg(long, value_parser = parse_price, requires = "currency")]
+    pub price: Option<String>,
+    /// ISO 4217 currency of the price, e.g. EUR
+    #[arg(long, value_parser = parse_currency, requires = "price")]
+    pub currency: Option<String>,
+    /// Payment method, e.g. SEPA or REVOLUT
+    #[arg(long, value_parser = parse_method, requires = "price")]
+    pub method: Option<String>,
+}
+
+impl FiatQuote {
+    // The memo carrying this quote, if a price was given
+    pub fn memo(&self, escrow: &Pubkey) -> Option<String> {
+        let (price, currency) = (self.price.as_ref()?, self.currency.as_ref()?);
+        let mut memo = format!("{} {} {} {}", QUOTE_TAG, escrow, price, currency);
+        if let Some(method) = &self.method {
+            memo = format!("{} {}", memo, method);
+        }
+        Some(memo)
+    }
+
+    fn from_memo(memo: &str, escrow: &Pubkey) -> Option<FiatQuote> {
+        let rest = memo.strip_prefix(&format!("{} {} ", QUOTE_TAG, escrow))?;
+        let mut fields = rest.split_whitespace();
+        Some(FiatQuote {
+            price: Some(parse_price(fields.next()?).ok()?),
+            currency: Some(parse_currency(fields.next()?).ok()?),
+            method: fields.next().map(parse_method).transpose().ok()?,
+        })
+    }
+}
+
+// Plain decimal with at most two places, so it survives memo round trips
+fn parse_price(input: &str) -> Result<String, String> {
+    let (whole, fraction) = input.split_once('.').unwrap_or((input, ""));
+    let digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    let valid = !whole.is_empty() && !input.ends_with('.') && fraction.len() <= 2;
+    if !valid || !digits(whole) || !digits(fraction) {
+        return Err(format!("Invalid price '{}': expected e.g. 1450 or 1450.50", input));
+    }
+    Ok(input.to_string())
+}
+
+fn parse_currency(input: &str) -> Result<String, String> {
+    if input.len() != 3 || !input.bytes().all(|b| b.is_ascii_alphabetic()) {
+        return Err(format!("Invalid currency '{}': expected a code like EUR", input));
+    }
+    Ok(input.to_ascii_uppercase())
+}
+
+fn parse_method(input: &str) -> Result<String, String> {
+    if input.is_empty() || !input.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-') {
+        return Err(format!("Invalid payment method '{}': use a single word, e.g. SEPA", input));
+    }
+    Ok(input.to_ascii_uppercase())
+}
+
+// The newest quote the buyer published for the escrow among its recent
+// transactions
+fn published_quote(
+    ctx: &Context,
+    escrow_pubkey: &Pubkey,
+    escrow: &EscrowAccount,
+) -> Result<Option<FiatQuote>> {
+    for record in find_recent_memos(&ctx.client, escrow_pubkey, QUOTE_SCAN_LIMIT)? {
+        let Some(quote) = FiatQuote::from_memo(&record.memo, escrow_pubkey) else {
+            continue;
+        };
+        // Anyone can put a memo into the escrow's history; only the buyer's counts
+        if transaction_signers(&ctx.client, &record.signature)?.contains(&escrow.buyer) {
+            return Ok(Some(quote));
+        }
+    }
+    Ok(None)
+}
+
+// List open offers carrying a fiat quote, optionally only those in one
+// currency or payment method, cheapest rate first unless sorted otherwise.
+// Other orders look up quotes only until `limit` offers have matched; an
+// escrow whose quote can't be read is skipped with a warning.
+pub fn offers(
+    ctx: &Context,
+    currency: Option<&str>,
+    method: Option<&str>,
+    filter: &PageFilter,
+) -> Result<()> {
+    let sort = filter.sort.unwrap_or(ListSort::Rate);
+    let mut candidates: Vec<(Pubkey, EscrowAccount)> = open_offers(ctx)?
+        .into_iter()
+        .filter(|(escrow_pubkey, escrow)| {
+            filter.matches(escrow) && filter.in_range(&escrow_pubkey.to_string())
+        })
+        .collect();
+    match sort {
+        ListSort::Amount => candidates.sort_by_key(|(_, escrow)| escrow.amount),
+        ListSort::State => candidates.sort_by_key(|(_, escrow)| escrow.state),
+        _ => candidates.sort_by_cached_key(|(escrow_pubkey, _)| escrow_pubkey.to_string()),
+    }
+    let streaming = sort != ListSort::Rate;
+    if streaming && filter.desc {
+        candidates.reverse();
+    }
+    let mut rows = Vec::new();
+    for (escrow_pubkey, escrow) in candidates {
+        if streaming && filter.limit.is_some_and(|limit| rows.len() >= limit) {
+            break;
+        }
+        let quote = match published_quote(ctx, &escrow_pubkey, &escrow) {
+            Ok(Some(quote)) => quote,
+            Ok(None) => continue,
+            Err(e) => {
+                eprintln!(
+                    "Warning: skipping {}: failed to read its quote: {}",
+                    escrow_pubkey, e
+                );
+                continue;
+            }
+        };
+        let quoted_currency = quote.currency.as_deref().unwrap_or_default();
+        if currency.is_some_and(|currency| !currency.eq_ignore_ascii_case(quoted_currency))
+            || method.is_some_and(|method| {
+                !quote.method.as_deref().is_some_and(|m| m.eq_ignore_ascii_case(method))
+            })
+        {
+            continue;
+        }
+        // Display only; the amounts themselves never go through floats
+        let price: f64 = quote.price.as_deref().unwrap_or_default().parse()?;
+        let rate = price / (escrow.amount.lamports() as f64 / LAMPORTS_PER_SOL as f64);
+        rows.push((rate, escrow_pubkey, escrow, quote));
+    }
+    if !streaming {
+        rows.sort_by(|a, b| a.0.total_cmp(&b.0));
+        if filter.desc {
+            rows.reverse();
+        }
+        rows.truncate(filter.limit.unwrap_or(usize::MAX));
+    }
+    if rows.is_empty() {
+        println!("No open offers with a matching quote");
+        return Ok(());
+    }
+    let next_page = match rows.last() {
+        Some((_, last, _, _)) if sort == ListSort::Address => {
+            filter.next_page(rows.len(), &last.to_string())
+        }
+        _ => None,
+    };
+    for (rate, escrow_pubkey, escrow, quote) in rows {
+        let currency = quote.currency.unwrap_or_default();
+        println!(
+            "{}  {:>14} SOL  for {} {}  (~{:.2} {}/SOL)  {}  arbiter {}",
+            escrow_pubkey,
+            escrow.amount,
+            quote.price.unwrap_or_default(),
+            currency,
+            rate,
+            currency,
+            quote.method.unwrap_or_else(|| "-".to_string()),
+            escrow.arbiter
+        );
+    }
+    if let Some(next) = next_page {
+        println!("{}", next);
+    }
+    Ok(())
+}
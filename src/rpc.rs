@@ -0,0 +1,226 @@
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use solana_client::client_error::{ClientError, ClientErrorKind, Result as ClientResult};
+use solana_client::http_sender::HttpSender;
+use solana_client::rpc_client::{RpcClient, RpcClientConfig};
+use solana_client::rpc_request::RpcRequest;
+use solana_client::rpc_sender::{RpcSender, RpcTransportStats};
+use solana_sdk::commitment_config::CommitmentConfig;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::{redact_url, Config};
+use crate::DEFAULT_RPC_URL;
+
+// `rpc_url = "auto"` picks the healthiest of `rpc_endpoints` at startup
+pub const AUTO: &str = "auto";
+// Endpoints further than this behind the best one are ranked as unhealthy
+const MAX_SLOT_LAG: u64 = 50;
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+// HttpSender's own default
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+// One endpoint's answer to getSlot: latency and slot, or why it failed
+struct Probe {
+    url: String,
+    result: Result<(Duration, u64), String>,
+}
+
+impl Probe {
+    fn run(url: &str) -> Probe {
+        let client = RpcClient::new_with_timeout_and_commitment(
+            url.to_string(),
+            PROBE_TIMEOUT,
+            CommitmentConfig::confirmed(),
+        );
+        let started = Instant::now();
+        let result = client
+            .get_slot()
+            .map(|slot| (started.elapsed(), slot))
+            .map_err(|e| e.to_string());
+        Probe {
+            url: url.to_string(),
+            result,
+        }
+    }
+}
+
+// Probe the endpoints in parallel and order them healthiest first: those
+// within MAX_SLOT_LAG of the best slot by latency, then laggards, then the
+// ones that didn't answer. Returns the probes and the best slot.
+fn rank(endpoints: &[String]) -> (Vec<Probe>, u64) {
+    let mut probes: Vec<Probe> = thread::scope(|scope| {
+        let handles: Vec<_> = endpoints
+            .iter()
+            .map(|url| scope.spawn(move || Probe::run(url)))
+            .collect();
+        handles
+            .into_iter()
+            .zip(endpoints)
+            .map(|(handle, url)| {
+                handle.join().unwrap_or_else(|_| Probe {
+                    url: url.clone(),
+                    result: Err("probe panicked".to_string()),
+                })
+            })
+            .collect()
+    });
+    let best = probes
+        .iter()
+        .filter_map(|probe| probe.result.as_ref().ok().map(|(_, slot)| *slot))
+        .max()
+        .unwrap_or_default();
+    probes.sort_by_key(|probe| match &probe.result {
+        Ok((latency, slot)) => (best - slot > MAX_SLOT_LAG, 0, *latency),
+        Err(_) => (true, 1, Duration::MAX),
+    });
+    (probes, best)
+}
+
+// The endpoints `rpc probe` and auto mode choose from: rpc_endpoints, plus
+// rpc_url when it is a URL
+fn endpoints(config: &Config) -> Vec<String> {
+    let mut endpoints = config.rpc_endpoints.clone();
+    match config.rpc_url.as_deref() {
+        Some(AUTO) => {}
+        url => {
+            let url = url.unwrap_or(DEFAULT_RPC_URL).to_string();
+            if !endpoints.contains(&url) {
+                endpoints.insert(0, url);
+            }
+        }
+    }
+    endpoints
+}
+
+// Print latency and slot lag of every configured endpoint, healthiest first
+pub fn probe(config: &Config) -> Result<()> {
+    let (probes, best) = rank(&endpoints(config));
+    println!(
+        "{:<48} {:>9} {:>12} {:>6}",
+        "Endpoint", "Latency", "Slot", "Lag"
+    );
+    for probe in &probes {
+        let url = redact_url(&probe.url);
+        match &probe.result {
+            Ok((latency, slot)) => println!(
+                "{:<48} {:>7}ms {:>12} {:>6}",
+                url,
+                latency.as_millis(),
+                slot,
+                best - slot
+            ),
+            Err(e) => println!("{:<48} unreachable: {}", url, e),
+        }
+    }
+    match probes.first() {
+        Some(Probe { url, result: Ok(_) }) => {
+            println!("`rpc_url = \"auto\"` would use {}", redact_url(url))
+        }
+        _ => println!("No endpoint answered"),
+    }
+    Ok(())
+}
+
+// The RPC client for the config: a plain one for a URL, or for "auto" one
+// that starts on the healthiest endpoint and fails over down the ranking
+pub fn connect(config: &Config, timeout: Option<Duration>) -> Result<RpcClient> {
+    let timeout = timeout.unwrap_or(REQUEST_TIMEOUT);
+    if config.rpc_url.as_deref() != Some(AUTO) {
+        let url = config.rpc_url.as_deref().unwrap_or(DEFAULT_RPC_URL);
+        return Ok(RpcClient::new_with_timeout_and_commitment(
+            url.to_string(),
+            timeout,
+            CommitmentConfig::confirmed(),
+        ));
+    }
+    if config.rpc_endpoints.is_empty() {
+        return Err(anyhow!(
+            "rpc_url = \"auto\" needs at least one URL in rpc_endpoints"
+        ));
+    }
+    let (probes, _) = rank(&config.rpc_endpoints);
+    match probes.first() {
+        Some(Probe {
+            url,
+            result: Ok((latency, _)),
+        }) => eprintln!(
+            "Using RPC endpoint {} ({}ms)",
+            redact_url(url),
+            latency.as_millis()
+        ),
+        _ => return Err(anyhow!("None of the rpc_endpoints answered")),
+    }
+    let failover = Failover {
+        senders: probes
+            .iter()
+            .map(|probe| HttpSender::new_with_timeout(probe.url.clone(), timeout))
+            .collect(),
+        current: AtomicUsize::new(0),
+    };
+    Ok(RpcClient::new_sender(
+        failover,
+        RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+    ))
+}
+
+// Sends to the current endpoint and moves down the ranking when it can't be
+// reached; JSON-RPC errors are answers and are returned as they are
+struct Failover {
+    senders: Vec<HttpSender>,
+    current: AtomicUsize,
+}
+
+impl RpcSender for Failover {
+    fn send<'a, 'b>(
+        &'a self,
+        request: RpcRequest,
+        params: Value,
+    ) -> Pin<Box<dyn Future<Output = ClientResult<Value>> + Send + 'b>>
+    where
+        'a: 'b,
+        Self: 'b,
+    {
+        Box::pin(async move {
+            let start = self.current.load(Ordering::SeqCst);
+            let mut last_error = None;
+            for offset in 0..self.senders.len() {
+                let index = (start + offset) % self.senders.len();
+                let sender = &self.senders[index];
+                match sender.send(request, params.clone()).await {
+                    Err(e)
+                        if matches!(
+                            e.kind(),
+                            ClientErrorKind::Reqwest(_) | ClientErrorKind::Io(_)
+                        ) =>
+                    {
+                        eprintln!(
+                            "Warning: RPC endpoint {} failed ({}), trying the next one",
+                            redact_url(&sender.url()),
+                            e
+                        );
+                        last_error = Some(e);
+                    }
+                    result => {
+                        self.current.store(index, Ordering::SeqCst);
+                        return result;
+                    }
+                }
+            }
+            Err(last_error.unwrap_or_else(|| {
+                ClientError::from(ClientErrorKind::Custom("No RPC endpoints".to_string()))
+            }))
+        })
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.senders[self.current.load(Ordering::SeqCst)].get_transport_stats()
+    }
+
+    fn url(&self) -> String {
+        self.senders[self.current.load(Ordering::SeqCst)].url()
+    }
+}
@@ -0,0 +1,210 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcAccountInfoConfig;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use std::time::Instant;
+
+use crate::account::EscrowAccount;
+use crate::config::{check_rpc_url, redact_url};
+use crate::rpc::REQUEST_TIMEOUT;
+use crate::Context;
+
+// Commands whose effects can't be undone once they land
+const IRREVERSIBLE: [&str; 4] = ["arbiter-confirm", "arbiter-cancel", "confirm", "close"];
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Safety {
+    // Commands that compare the finalized and confirmed views of the escrow
+    // before signing; an empty list disables the check
+    pub finalized_check: Vec<String>,
+    // Commands that also read the escrow from a second RPC endpoint and
+    // refuse to sign unless both report the same account
+    pub cross_check: Vec<String>,
+    // The second endpoint; defaults to the first of rpc_endpoints that isn't
+    // the one in use
+    pub cross_check_endpoint: Option<String>,
+    // How many slots apart the two reads may be
+    pub cross_check_slot_tolerance: u64,
+}
+
+impl Default for Safety {
+    fn default() -> Self {
+        Safety {
+            finalized_check: vec!["arbiter-confirm".to_string(), "close".to_string()],
+            cross_check: Vec::new(),
+            cross_check_endpoint: None,
+            cross_check_slot_tolerance: 20,
+        }
+    }
+}
+
+impl Safety {
+    pub fn validate(&self) -> Result<()> {
+        for (field, commands) in [
+            ("finalized_check", &self.finalized_check),
+            ("cross_check", &self.cross_check),
+        ] {
+            for command in commands {
+                if !IRREVERSIBLE.contains(&command.as_str()) {
+                    return Err(anyhow!(
+                        "safety.{}: unknown command '{}', expected one of {}",
+                        field,
+                        command,
+                        IRREVERSIBLE.join(", ")
+                    ));
+                }
+            }
+        }
+        check_rpc_url(
+            "safety.cross_check_endpoint",
+            self.cross_check_endpoint.as_deref(),
+        )
+    }
+}
+
+fn describe(data: Option<&[u8]>) -> String {
+    match data.map(EscrowAccount::unpack) {
+        Some(Ok(account)) => format!("{} ({} lamports)", account.state, account.amount.lamports()),
+        Some(Err(e)) => format!("undecodable ({})", e),
+        None => "missing".to_string(),
+    }
+}
+
+// Abort when the escrow as seen at `finalized` differs from the `confirmed`
+// view the command is about to act on, e.g. while a fork is being resolved
+pub fn check_finalized(ctx: &Context, escrow: &Pubkey, command: &str) -> Result<()> {
+    if !ctx.config.safety.finalized_check.iter().any(|c| c == command) {
+        return Ok(());
+    }
+    let confirmed = ctx.get_account_data(escrow)?;
+    let finalized = ctx
+        .client
+        .get_account_with_config(
+            escrow,
+            RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(CommitmentConfig::finalized()),
+                ..RpcAccountInfoConfig::default()
+            },
+        )?
+        .value
+        .map(|account| account.data);
+    if finalized.as_deref() == Some(confirmed.as_slice()) {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "Escrow {} is {} at confirmed but {} at finalized; wait for finalization \
+         before running {} (see [safety] in the config)",
+        escrow,
+        describe(Some(&confirmed)),
+        describe(finalized.as_deref()),
+        command
+    ))
+}
+
+// Abort unless a second, independent endpoint reports the same escrow within
+// the slot tolerance, so a malicious or corrupted RPC node can't feed the
+// command false state
+pub fn check_cross(ctx: &Context, escrow: &Pubkey, command: &str) -> Result<()> {
+    let safety = &ctx.config.safety;
+    if !safety.cross_check.iter().any(|c| c == command) {
+        return Ok(());
+    }
+    // With `rpc_url = "auto"` this is the endpoint actually picked
+    let primary_url = ctx.client.url();
+    let primary_host = host(&primary_url);
+    let second_url = match &safety.cross_check_endpoint {
+        Some(url) if host(url) == primary_host => {
+            return Err(anyhow!(
+                "safety.cross_check_endpoint {} is on the same host as the endpoint in use; \
+                 a cross-check needs a different provider",
+                redact_url(url)
+            ))
+        }
+        Some(url) => url.clone(),
+        None => ctx
+            .config
+            .rpc_endpoints
+            .iter()
+            .find(|url| host(url) != primary_host)
+            .cloned()
+            .ok_or_else(|| {
+                anyhow!(
+                    "safety.cross_check needs a second endpoint on another host than {}: set \
+                     safety.cross_check_endpoint or add one to rpc_endpoints",
+                    redact_url(&primary_url)
+                )
+            })?,
+    };
+    let config = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        commitment: Some(CommitmentConfig::confirmed()),
+        ..RpcAccountInfoConfig::default()
+    };
+    let primary = ctx.client.get_account_with_config(escrow, config.clone())?;
+    // Bounded like the primary client: by what is left of --timeout, or the
+    // usual per-request limit
+    let timeout = ctx
+        .deadline
+        .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+        .unwrap_or(REQUEST_TIMEOUT);
+    let second = RpcClient::new_with_timeout_and_commitment(
+        second_url.clone(),
+        timeout,
+        CommitmentConfig::confirmed(),
+    )
+    .get_account_with_config(escrow, config)
+    .map_err(|e| {
+        anyhow!(
+            "Cross-check endpoint {} failed: {}",
+            redact_url(&second_url),
+            e
+        )
+    })?;
+    let (slot, second_slot) = (primary.context.slot, second.context.slot);
+    if slot.abs_diff(second_slot) > safety.cross_check_slot_tolerance {
+        return Err(anyhow!(
+            "{} is at slot {} but cross-check endpoint {} is at slot {}, more than {} apart; \
+             not running {}",
+            redact_url(&primary_url),
+            slot,
+            redact_url(&second_url),
+            second_slot,
+            safety.cross_check_slot_tolerance,
+            command
+        ));
+    }
+    let data = primary.value.map(|account| account.data);
+    let second_data = second.value.map(|account| account.data);
+    if data == second_data {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "Escrow {} is {} on {} but {} on {}; one endpoint is wrong, not running {} \
+         (see [safety] in the config)",
+        escrow,
+        describe(data.as_deref()),
+        redact_url(&primary_url),
+        describe(second_data.as_deref()),
+        redact_url(&second_url),
+        command
+    ))
+}
+
+// Lowercased host of an endpoint URL, without credentials or port, so two
+// URLs of the same provider compare equal
+fn host(url: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let authority = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = match authority.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => authority.split(':').next().unwrap_or_default(),
+    };
+    host.to_ascii_lowercase()
+}
@@ -0,0 +1,81 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use solana_sdk::signature::{read_keypair_file, Keypair};
+use std::path::{Path, PathBuf};
+
+use crate::config::{config_dir, expand_home};
+
+// File access limits for shared and ops machines
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sandbox {
+    // Enforce the limits below; off by default
+    pub strict: bool,
+    // Directories keypairs may be read from, besides the keystore
+    pub keypair_dirs: Vec<String>,
+}
+
+impl Sandbox {
+    // Keypairs must resolve (after `..` and symlinks) inside an allowed
+    // directory, so a typo or traversal can't pick up an unrelated key file
+    pub fn check_keypair(&self, path: &Path, keystore: Option<&str>) -> Result<()> {
+        if !self.strict {
+            return Ok(());
+        }
+        let allowed: Vec<PathBuf> = self
+            .keypair_dirs
+            .iter()
+            .map(String::as_str)
+            .chain(keystore)
+            .filter_map(|dir| expand_home(dir).canonicalize().ok())
+            .collect();
+        let resolved = path
+            .canonicalize()
+            .map_err(|e| anyhow!("Failed to resolve keypair {}: {}", path.display(), e))?;
+        if allowed.iter().any(|dir| resolved.starts_with(dir)) {
+            return Ok(());
+        }
+        Err(anyhow!(
+            "Sandbox: {} is outside the allowed keypair directories (sandbox.keypair_dirs \
+             and keystore)",
+            resolved.display()
+        ))
+    }
+
+    // Read a keypair after `check_keypair`; `what` names it in the error
+    pub fn read_keypair(&self, path: &Path, keystore: Option<&str>, what: &str) -> Result<Keypair> {
+        self.check_keypair(path, keystore)?;
+        read_keypair_file(path).map_err(|_| anyhow!("Failed to read {}", what))
+    }
+
+    // Only the config directory may be written
+    pub fn check_write(&self, path: &Path) -> Result<()> {
+        if !self.strict {
+            return Ok(());
+        }
+        let dir = config_dir()
+            .and_then(|dir| dir.canonicalize().ok())
+            .ok_or_else(|| anyhow!("Sandbox: the config directory does not exist"))?;
+        // An existing file may be a symlink out of the directory; a new one
+        // is judged by its parent
+        let target = if path.exists() {
+            path.to_path_buf()
+        } else {
+            match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                _ => PathBuf::from("."),
+            }
+        };
+        let resolved = target
+            .canonicalize()
+            .map_err(|e| anyhow!("Failed to resolve {}: {}", path.display(), e))?;
+        if resolved.starts_with(&dir) && path.file_name().map_or(true, |name| name != "..") {
+            return Ok(());
+        }
+        Err(anyhow!(
+            "Sandbox: refusing to write {} outside {}",
+            path.display(),
+            dir.display()
+        ))
+    }
+}
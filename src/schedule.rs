@@ -0,0 +1,233 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use solana_client::nonce_utils;
+use solana_sdk::{
+    message::Message, pubkey::Pubkey, signature::Signer, system_instruction,
+    transaction::Transaction,
+};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+use std::str::FromStr;
+use std::thread::sleep;
+use std::time::Duration;
+
+use crate::account::EscrowState;
+use crate::duration::{format_duration, parse_duration};
+use crate::inspect;
+use crate::network;
+use crate::pipe::{self, STDIO};
+use crate::sla::now;
+use crate::totp;
+use crate::{check_state, confirm_instruction, simulate_and_send, Context};
+
+// A confirm transaction signed ahead of time against a durable nonce, to be
+// sent once `release_at` has passed
+#[derive(Deserialize, Serialize)]
+struct ScheduledRelease {
+    escrow: String,
+    release_at: i64,
+    nonce_account: String,
+    transaction: String,
+}
+
+// Parse a unix timestamp, or `+7d` style offsets from now
+pub fn parse_release_at(input: &str) -> Result<i64, String> {
+    match input.strip_prefix('+') {
+        Some(offset) => Ok(now() + parse_duration(offset)?.as_secs() as i64),
+        None => input
+            .parse()
+            .map_err(|_| format!("Invalid time '{}': expected a unix timestamp or +7d", input)),
+    }
+}
+
+// Sign the confirm now, using the nonce account's stored blockhash so the
+// transaction stays valid until it is sent, and write it to `out`
+pub fn schedule_release(
+    ctx: &Context,
+    keypair_path: &str,
+    escrow_account: &str,
+    release_at: i64,
+    nonce_account: &str,
+    out: &str,
+) -> Result<()> {
+    let keypair = ctx.read_keypair(keypair_path, "seller")?;
+    let escrow_pubkey = Pubkey::from_str(escrow_account)?;
+    let nonce_pubkey = Pubkey::from_str(nonce_account)?;
+    match check_state(ctx, escrow_account)? {
+        EscrowState::Funded => {}
+        other_state => {
+            return Err(anyhow!(
+                "Escrow must be in Funded state, current state: {:?}",
+                other_state
+            ))
+        }
+    }
+    let account = nonce_utils::get_account_with_commitment(
+        &ctx.client,
+        &nonce_pubkey,
+        ctx.client.commitment(),
+    )?;
+    let nonce = nonce_utils::data_from_account(&account)?;
+    if nonce.authority != keypair.pubkey() {
+        return Err(anyhow!(
+            "Nonce authority is {}, not the signing keypair {}",
+            nonce.authority,
+            keypair.pubkey()
+        ));
+    }
+    // Signed now, sent later without asking again
+    totp::check(ctx, "confirm", ctx.get_escrow(&escrow_pubkey)?.amount)?;
+    let advance_ix = system_instruction::advance_nonce_account(&nonce_pubkey, &keypair.pubkey());
+    let confirm_ix = confirm_instruction(&ctx.program_id, &keypair.pubkey(), &escrow_pubkey);
+    let message = Message::new(&[advance_ix, confirm_ix], Some(&keypair.pubkey()));
+    let transaction = Transaction::new(&[&keypair], message, nonce.blockhash());
+    let scheduled = ScheduledRelease {
+        escrow: escrow_pubkey.to_string(),
+        release_at,
+        nonce_account: nonce_pubkey.to_string(),
+        transaction: STANDARD.encode(bincode::serialize(&transaction)?),
+    };
+    let contents = serde_json::to_string_pretty(&scheduled)?;
+    pipe::write_output(&ctx.config.sandbox, out, contents.as_bytes())?;
+    // Keep stdout clean when the release itself is written there
+    if out == STDIO {
+        eprintln!("Release signed for {}", release_at);
+        eprintln!("Using the nonce account for anything else invalidates it.");
+        return Ok(());
+    }
+    println!("Release signed for {} and saved to {}", release_at, out);
+    println!("Run `escrow-cli release-scheduled -f {}` to send it when due.", out);
+    println!("Using the nonce account for anything else invalidates it.");
+    Ok(())
+}
+
+// External signal that must report delivery before a release is sent
+pub enum Condition {
+    // GET a JSON document
+    Url(String),
+    // Run with `sh -c`, e.g. a tracking-API plugin printing JSON
+    Command(String),
+}
+
+// Wait until the scheduled time (and, with a condition, until it reports
+// `field` as true), then send the pre-signed confirm. Every check and the
+// release itself are appended to a JSONL audit log.
+pub fn release_scheduled(
+    ctx: &Context,
+    file: &str,
+    condition: Option<&Condition>,
+    field: &str,
+    interval: u64,
+    allow_unknown_programs: bool,
+) -> Result<()> {
+    let contents = pipe::read_input(file)?;
+    let scheduled: ScheduledRelease = serde_json::from_slice(&contents)
+        .map_err(|e| anyhow!("Invalid scheduled release {}: {}", file, e))?;
+    let transaction: Transaction = bincode::deserialize(&STANDARD.decode(&scheduled.transaction)?)?;
+    let escrow_pubkey = Pubkey::from_str(&scheduled.escrow)?;
+    // The file may have been signed elsewhere; know what it does before
+    // waiting on it
+    inspect::check_imported(ctx, &transaction, &escrow_pubkey, allow_unknown_programs)?;
+    let audit_log = match file {
+        STDIO => format!("{}.audit.jsonl", scheduled.escrow),
+        _ => format!("{}.audit.jsonl", file),
+    };
+    ctx.config.sandbox.check_write(Path::new(&audit_log))?;
+    let wait = scheduled.release_at - now();
+    if wait > 0 {
+        println!(
+            "Releasing escrow {} in {}",
+            escrow_pubkey,
+            format_duration(Duration::from_secs(wait as u64))
+        );
+        sleep(Duration::from_secs(wait as u64));
+    }
+    let trigger = match condition {
+        Some(condition) => {
+            println!("Waiting for `{}` to become true (audit log: {})", field, audit_log);
+            wait_for_condition(condition, field, interval, &audit_log)?
+        }
+        None => Value::Null,
+    };
+    match check_state(ctx, &scheduled.escrow)? {
+        EscrowState::Funded => {}
+        other_state => {
+            audit(&audit_log, json!({ "event": "skipped", "state": other_state.to_string() }))?;
+            return Err(anyhow!(
+                "Escrow is no longer Funded (current state: {:?}), not releasing",
+                other_state
+            ));
+        }
+    }
+    match simulate_and_send(ctx, &transaction, &escrow_pubkey, "confirm_escrow") {
+        Ok(None) => Ok(()),
+        Ok(Some(signature)) => {
+            audit(
+                &audit_log,
+                json!({
+                    "event": "released",
+                    "signature": signature.to_string(),
+                    "trigger": trigger,
+                }),
+            )?;
+            println!("Scheduled release sent! Signature: {}", signature);
+            Ok(())
+        }
+        Err(e) => {
+            audit(&audit_log, json!({ "event": "failed", "error": e.to_string() }))?;
+            Err(e)
+        }
+    }
+}
+
+// Poll the condition until it fires; returns the payload that fired it
+fn wait_for_condition(
+    condition: &Condition,
+    field: &str,
+    interval: u64,
+    audit_log: &str,
+) -> Result<Value> {
+    loop {
+        match check_condition(condition) {
+            Ok(payload) => {
+                let fired = payload.get(field) == Some(&Value::Bool(true));
+                audit(
+                    audit_log,
+                    json!({ "event": "check", "fired": fired, "payload": payload }),
+                )?;
+                if fired {
+                    return Ok(payload);
+                }
+            }
+            Err(e) => {
+                eprintln!("Warning: condition check failed: {}", e);
+                audit(audit_log, json!({ "event": "check", "error": e.to_string() }))?;
+            }
+        }
+        sleep(Duration::from_secs(interval));
+    }
+}
+
+fn check_condition(condition: &Condition) -> Result<Value> {
+    match condition {
+        Condition::Url(url) => network::get_json("Condition URL", url, &[]),
+        Condition::Command(command) => {
+            let output = Command::new("sh").arg("-c").arg(command).output()?;
+            if !output.status.success() {
+                return Err(anyhow!("condition command exited with {}", output.status));
+            }
+            Ok(serde_json::from_slice(&output.stdout)?)
+        }
+    }
+}
+
+fn audit(path: &str, mut entry: Value) -> Result<()> {
+    entry["time"] = json!(now());
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", entry)?;
+    Ok(())
+}
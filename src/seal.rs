@@ -0,0 +1,74 @@
+use anyhow::{anyhow, Result};
+
+// Passphrase encryption for ledger exports and the TOTP secret; `what` names
+// the passphrase in prompts. Builds without the `ledger-encryption` feature
+// leave age out and can only move plain exports.
+#[cfg(feature = "ledger-encryption")]
+const PASSPHRASE_ENV: &str = "ESCROW_CLI_LEDGER_PASSPHRASE";
+const AGE_HEADER: &[u8] = b"age-encryption.org/v1";
+
+pub fn is_encrypted(contents: &[u8]) -> bool {
+    contents.starts_with(AGE_HEADER)
+}
+
+#[cfg(feature = "ledger-encryption")]
+pub fn encrypt(contents: &[u8], what: &str) -> Result<Vec<u8>> {
+    use std::io::Write;
+
+    let encryptor = age::Encryptor::with_user_passphrase(passphrase(what, true)?);
+    let mut encrypted = Vec::new();
+    let mut writer = encryptor.wrap_output(&mut encrypted)?;
+    writer.write_all(contents)?;
+    writer.finish()?;
+    Ok(encrypted)
+}
+
+#[cfg(feature = "ledger-encryption")]
+pub fn decrypt(contents: &[u8], file: &str, what: &str) -> Result<Vec<u8>> {
+    use std::io::Read;
+
+    let decryptor = match age::Decryptor::new(contents)? {
+        age::Decryptor::Passphrase(decryptor) => decryptor,
+        _ => return Err(anyhow!("{} is not passphrase-encrypted", file)),
+    };
+    let mut decrypted = Vec::new();
+    decryptor
+        .decrypt(&passphrase(what, false)?, None)
+        .map_err(|e| anyhow!("Failed to decrypt {}: {}", file, e))?
+        .read_to_end(&mut decrypted)?;
+    Ok(decrypted)
+}
+
+// From $ESCROW_CLI_LEDGER_PASSPHRASE, or asked for without echo
+#[cfg(feature = "ledger-encryption")]
+fn passphrase(what: &str, confirm: bool) -> Result<age::secrecy::Secret<String>> {
+    use age::secrecy::Secret;
+
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(Secret::new(passphrase));
+    }
+    let passphrase = rpassword::prompt_password(format!("{} passphrase: ", what))?;
+    if passphrase.is_empty() {
+        return Err(anyhow!("Empty passphrase"));
+    }
+    if confirm && rpassword::prompt_password("Repeat passphrase: ")? != passphrase {
+        return Err(anyhow!("Passphrases do not match"));
+    }
+    Ok(Secret::new(passphrase))
+}
+
+#[cfg(not(feature = "ledger-encryption"))]
+pub fn encrypt(_contents: &[u8], what: &str) -> Result<Vec<u8>> {
+    Err(anyhow!(
+        "{} encryption is unavailable: this build leaves out the ledger-encryption feature",
+        what
+    ))
+}
+
+#[cfg(not(feature = "ledger-encryption"))]
+pub fn decrypt(_contents: &[u8], file: &str, _what: &str) -> Result<Vec<u8>> {
+    Err(anyhow!(
+        "{} is encrypted and this build leaves out the ledger-encryption feature",
+        file
+    ))
+}
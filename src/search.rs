@@ -0,0 +1,263 @@
+use anyhow::{anyhow, Result};
+use solana_client::rpc_config::RpcProgramAccountsConfig;
+use solana_sdk::pubkey::Pubkey;
+use std::cmp::Reverse;
+use std::collections::{hash_map::Entry, BTreeMap, HashMap};
+use std::str::FromStr;
+
+use crate::account::{EscrowAccount, EscrowState};
+use crate::amount::{parse_amount, Lamports};
+use crate::filter;
+use crate::ledger::{ledger_path, Ledger};
+use crate::memo::{find_memos, MemoRecord};
+use crate::network;
+use crate::{transcript, Context};
+
+// An escrow matching the query, with why it matched
+#[derive(Default)]
+struct Hit {
+    score: u32,
+    reasons: Vec<String>,
+    account: Option<EscrowAccount>,
+}
+
+impl Hit {
+    fn add(&mut self, score: u32, reason: String) {
+        self.score += score;
+        self.reasons.push(reason);
+    }
+}
+
+// Match a query against escrow addresses, parties, amounts, tags and cost
+// records in the local ledger. With `chain`, also find escrows on-chain
+// where a queried pubkey is a party, and search memos posted by the parties.
+pub fn search(ctx: &Context, query: &str, chain: bool) -> Result<()> {
+    let needle = query.trim().to_ascii_lowercase();
+    let query_pubkey = Pubkey::from_str(query.trim()).ok();
+    let query_amount = parse_amount(query).ok();
+    let ledger = Ledger::load(&ledger_path(&ctx.config)?)?;
+    let mut hits: BTreeMap<Pubkey, Hit> = BTreeMap::new();
+    let mut live: Vec<(Pubkey, EscrowAccount)> = Vec::new();
+
+    let escrows = ledger
+        .escrows
+        .keys()
+        .filter_map(|escrow| Pubkey::from_str(escrow).ok())
+        .collect::<Vec<_>>();
+    let mut accounts = Vec::with_capacity(escrows.len());
+    for chunk in escrows.chunks(100) {
+        accounts.extend(ctx.get_multiple_accounts(chunk)?);
+    }
+    for (escrow, account) in escrows.iter().zip(accounts) {
+        let mut hit = Hit {
+            account: account.and_then(|account| EscrowAccount::unpack(&account.data).ok()),
+            ..Hit::default()
+        };
+        let address = escrow.to_string();
+        if Some(*escrow) == query_pubkey {
+            hit.add(100, "escrow address".to_string());
+        } else if needle.len() >= 4 && address.to_ascii_lowercase().contains(&needle) {
+            hit.add(40, format!("escrow address contains '{}'", query));
+        }
+        let entry = &ledger.escrows[&address];
+        for tag in &entry.tags {
+            let tag_lower = tag.to_ascii_lowercase();
+            if tag_lower == needle {
+                hit.add(60, format!("tag '{}'", tag));
+            } else if tag_lower.contains(&needle) {
+                hit.add(30, format!("tag '{}'", tag));
+            }
+        }
+        for cost in &entry.costs {
+            if cost.signature == query.trim() {
+                hit.add(80, format!("{} transaction", cost.action));
+            }
+        }
+        if let Some(account) = hit.account.clone() {
+            score_account(&mut hit, &account, query_pubkey, query_amount);
+            live.push((*escrow, account));
+        }
+        if hit.score > 0 {
+            hits.insert(*escrow, hit);
+        }
+    }
+
+    if chain {
+        if let Some(party) = query_pubkey {
+            for (escrow, account) in escrows_with_party(ctx, &party)? {
+                let hit = hits.entry(escrow).or_default();
+                if hit.account.is_none() {
+                    score_account(hit, &account, query_pubkey, query_amount);
+                    hit.account = Some(account);
+                }
+            }
+        }
+        search_memos(ctx, &live, &needle, &mut hits)?;
+    }
+
+    let mut ranked: Vec<(Pubkey, Hit)> = hits.into_iter().collect();
+    ranked.sort_by_key(|(_, hit)| Reverse(hit.score));
+    if ranked.is_empty() {
+        println!("No matches for '{}'", query);
+    }
+    for (escrow, hit) in ranked {
+        let status = match &hit.account {
+            Some(account) => format!("{} {} SOL", account.state, account.amount),
+            None => "closed".to_string(),
+        };
+        println!("{}  {}  [{}]", escrow, status, hit.score);
+        for reason in &hit.reasons {
+            println!("    matched {}", reason);
+        }
+    }
+    Ok(())
+}
+
+fn score_account(
+    hit: &mut Hit,
+    account: &EscrowAccount,
+    query_pubkey: Option<Pubkey>,
+    query_amount: Option<Lamports>,
+) {
+    let roles = [
+        ("buyer", account.buyer),
+        ("seller", account.seller),
+        ("arbiter", account.arbiter),
+    ];
+    for (role, key) in roles {
+        if Some(key) == query_pubkey {
+            hit.add(70, format!("{} {}", role, key));
+        }
+    }
+    if Some(account.amount) == query_amount {
+        hit.add(50, format!("amount {} SOL", account.amount));
+    }
+}
+
+// Escrows of this program where `party` is the buyer, seller or arbiter.
+// Many public RPC nodes disable getProgramAccounts; then the configured
+// indexer is asked instead, or failing that the local ledger.
+pub fn escrows_with_party(ctx: &Context, party: &Pubkey) -> Result<Vec<(Pubkey, EscrowAccount)>> {
+    let error = match scan_program_accounts(ctx, party) {
+        Ok(found) => return Ok(found),
+        Err(e) => e,
+    };
+    let candidates = match &ctx.config.indexer_url {
+        Some(url) => {
+            eprintln!("getProgramAccounts failed ({}); asking the indexer", error);
+            indexer_escrows(ctx, url, party)?
+        }
+        None => {
+            eprintln!("getProgramAccounts failed ({})", error);
+            eprintln!(
+                "Showing escrows from the local ledger only; escrows created on other \
+                 machines are missing. Set `indexer_url` in the config for a full view."
+            );
+            let ledger = Ledger::load(&ledger_path(&ctx.config)?)?;
+            ledger
+                .escrows
+                .keys()
+                .filter_map(|escrow| Pubkey::from_str(escrow).ok())
+                .collect()
+        }
+    };
+    // Whatever the source, the accounts themselves come from the RPC node
+    let mut found = Vec::new();
+    for chunk in candidates.chunks(100) {
+        for (escrow, account) in chunk.iter().zip(ctx.get_multiple_accounts(chunk)?) {
+            let Some(account) = account.filter(|account| account.owner == ctx.program_id) else {
+                continue;
+            };
+            if let Ok(account) = EscrowAccount::unpack(&account.data) {
+                if account.parties().contains(party) {
+                    found.push((*escrow, account));
+                }
+            }
+        }
+    }
+    Ok(found)
+}
+
+fn scan_program_accounts(ctx: &Context, party: &Pubkey) -> Result<Vec<(Pubkey, EscrowAccount)>> {
+    let mut found = Vec::new();
+    for party_filter in filter::by_party(party) {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![filter::escrows(ctx.account_size()), party_filter]),
+            ..RpcProgramAccountsConfig::default()
+        };
+        for (escrow, account) in ctx
+            .client
+            .get_program_accounts_with_config(&ctx.program_id, config)?
+        {
+            if let Ok(account) = EscrowAccount::unpack(&account.data) {
+                found.push((escrow, account));
+            }
+        }
+    }
+    Ok(found)
+}
+
+// Escrows of this program still waiting for a seller
+pub fn open_offers(ctx: &Context) -> Result<Vec<(Pubkey, EscrowAccount)>> {
+    let config = RpcProgramAccountsConfig {
+        filters: Some(vec![
+            filter::escrows(ctx.account_size()),
+            filter::by_state(EscrowState::Created),
+        ]),
+        ..RpcProgramAccountsConfig::default()
+    };
+    Ok(ctx
+        .client
+        .get_program_accounts_with_config(&ctx.program_id, config)?
+        .into_iter()
+        .filter_map(|(escrow, account)| Some((escrow, EscrowAccount::unpack(&account.data).ok()?)))
+        .collect())
+}
+
+// Escrow addresses the indexer associates with `party`
+fn indexer_escrows(ctx: &Context, url: &str, party: &Pubkey) -> Result<Vec<Pubkey>> {
+    let addresses: Vec<String> = network::get_json(
+        "Indexer",
+        url,
+        &[
+            ("program", ctx.program_id.to_string()),
+            ("party", party.to_string()),
+        ],
+    )?;
+    addresses
+        .iter()
+        .map(|address| {
+            Pubkey::from_str(address)
+                .map_err(|_| anyhow!("Indexer returned an invalid address '{}'", address))
+        })
+        .collect()
+}
+
+// Memos posted by ledger escrows' parties that mention the escrow and the query
+fn search_memos(
+    ctx: &Context,
+    escrows: &[(Pubkey, EscrowAccount)],
+    needle: &str,
+    hits: &mut BTreeMap<Pubkey, Hit>,
+) -> Result<()> {
+    let mut histories: HashMap<Pubkey, Vec<MemoRecord>> = HashMap::new();
+    for (escrow, account) in escrows {
+        let address = escrow.to_string();
+        for party in account.parties() {
+            let records = match histories.entry(party) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(find_memos(&ctx.client, &party)?),
+            };
+            for record in records.iter() {
+                let memo = record.memo.to_ascii_lowercase();
+                if record.memo.contains(&address) && memo.contains(needle) {
+                    let hit = hits.entry(*escrow).or_default();
+                    hit.account.get_or_insert_with(|| account.clone());
+                    transcript::memo(&record.memo);
+                    hit.add(40, format!("memo '{}'", record.memo));
+                }
+            }
+        }
+    }
+    Ok(())
+}
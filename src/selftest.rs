@@ -0,0 +1,140 @@
+use anyhow::{anyhow, Result};
+use solana_sdk::{
+    message::Message,
+    signature::{write_keypair_file, Keypair, Signer},
+    system_instruction,
+    transaction::Transaction,
+};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::amount::{Lamports, LAMPORTS_PER_SOL};
+use crate::onboarding::airdrop;
+use crate::quote::FiatQuote;
+use crate::{close_escrow, confirm_escrow, create_offer, fund_escrow, join_offer, Context};
+
+// Covers rent for the escrow account and the buyer's fees
+const BUYER_OVERHEAD: Lamports = Lamports::new(LAMPORTS_PER_SOL / 10);
+// Fee budget for the seller, who signs join and confirm
+const SELLER_FEE_BUDGET: u64 = 10_000_000;
+
+// Throwaway participants, saved to disk so the real commands can read them
+struct SelfTestKeys {
+    buyer: Keypair,
+    seller: Keypair,
+    arbiter: Keypair,
+    buyer_path: String,
+    seller_path: String,
+    escrow_path: String,
+    escrow_account: String,
+}
+
+// Run create -> join -> fund -> confirm -> close with throwaway keys,
+// going through the same code paths as the real commands
+pub fn run(ctx: &Context, amount: Lamports) -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("escrow-cli-self-test-{}", std::process::id()));
+    ctx.config.sandbox.check_write(&dir)?;
+    fs::create_dir_all(&dir)?;
+    let buyer = Keypair::new();
+    let seller = Keypair::new();
+    let escrow = Keypair::new();
+    let keys = SelfTestKeys {
+        buyer_path: save(ctx, &dir, "buyer", &buyer)?,
+        seller_path: save(ctx, &dir, "seller", &seller)?,
+        escrow_path: save(ctx, &dir, "escrow", &escrow)?,
+        escrow_account: escrow.pubkey().to_string(),
+        buyer,
+        seller,
+        arbiter: Keypair::new(),
+    };
+    println!("Self-test keys in {}", dir.display());
+    println!("Escrow: {} ({} SOL)", keys.escrow_account, amount);
+
+    let mut timings: Vec<(&'static str, Duration)> = Vec::new();
+    let result = run_steps(ctx, amount, &keys, &mut timings);
+
+    println!("Self-test timings:");
+    for (step, elapsed) in &timings {
+        println!("  {:<13} {:>8.2}s", step, elapsed.as_secs_f64());
+    }
+    match result {
+        Ok(()) => {
+            let total: Duration = timings.iter().map(|(_, elapsed)| *elapsed).sum();
+            println!("Self-test passed in {:.2}s", total.as_secs_f64());
+            fs::remove_dir_all(&dir)?;
+            Ok(())
+        }
+        Err(e) => Err(anyhow!(
+            "Self-test failed: {} (keys kept in {})",
+            e,
+            dir.display()
+        )),
+    }
+}
+
+fn run_steps(
+    ctx: &Context,
+    amount: Lamports,
+    keys: &SelfTestKeys,
+    timings: &mut Vec<(&'static str, Duration)>,
+) -> Result<()> {
+    timed(timings, "airdrop", || {
+        airdrop(&ctx.client, &keys.buyer, amount.checked_add(BUYER_OVERHEAD)?.lamports())?;
+        let transfer_ix = system_instruction::transfer(
+            &keys.buyer.pubkey(),
+            &keys.seller.pubkey(),
+            SELLER_FEE_BUDGET,
+        );
+        let blockhash = ctx.client.get_latest_blockhash()?;
+        let message = Message::new(&[transfer_ix], Some(&keys.buyer.pubkey()));
+        let transaction = Transaction::new(&[&keys.buyer], message, blockhash);
+        let (_, slot) = ctx.send_and_confirm(&transaction)?;
+        ctx.observe_slot(slot);
+        Ok(())
+    })?;
+    timed(timings, "create-offer", || {
+        create_offer(
+            ctx,
+            &keys.buyer,
+            &ctx.read_keypair(&keys.escrow_path, "escrow")?,
+            &keys.arbiter.pubkey().to_string(),
+            amount,
+            &FiatQuote::default(),
+        )
+    })?;
+    timed(timings, "join-offer", || {
+        join_offer(ctx, &keys.seller_path, &keys.escrow_account)
+    })?;
+    timed(timings, "fund", || {
+        fund_escrow(ctx, &keys.buyer_path, &keys.escrow_account, None)
+    })?;
+    timed(timings, "confirm", || {
+        confirm_escrow(ctx, &keys.seller_path, &keys.escrow_account)
+    })?;
+    timed(timings, "close", || {
+        close_escrow(ctx, &keys.buyer_path, &keys.escrow_account, false)
+    })
+}
+
+fn timed(
+    timings: &mut Vec<(&'static str, Duration)>,
+    step: &'static str,
+    run: impl FnOnce() -> Result<()>,
+) -> Result<()> {
+    println!("==> {}", step);
+    let started = Instant::now();
+    let result = run();
+    timings.push((step, started.elapsed()));
+    result
+}
+
+fn save(ctx: &Context, dir: &Path, name: &str, keypair: &Keypair) -> Result<String> {
+    let path = dir.join(format!("{}.json", name));
+    ctx.config.sandbox.check_write(&path)?;
+    write_keypair_file(keypair, &path)
+        .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+    path.to_str()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("Non UTF-8 temp path {}", path.display()))
+}
@@ -0,0 +1,163 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use solana_client::{
+    rpc_client::GetConfirmedSignaturesForAddress2Config, rpc_config::RpcTransactionConfig,
+};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::UiTransactionEncoding;
+use std::io::{self, IsTerminal};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::account::EscrowState;
+use crate::duration::{format_duration, parse_duration};
+use crate::Context;
+
+const YELLOW: &str = "\x1b[33m";
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+// How long an escrow may sit in Created, Initialized or Funded before `info`
+// and `list` flag it
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sla {
+    // Durations like "12h" or "3d"
+    pub warn_after: String,
+    pub alert_after: String,
+}
+
+impl Default for Sla {
+    fn default() -> Self {
+        Sla {
+            warn_after: "1d".to_string(),
+            alert_after: "3d".to_string(),
+        }
+    }
+}
+
+impl Sla {
+    pub fn validate(&self) -> Result<()> {
+        let (warn, alert) = self.thresholds()?;
+        if alert < warn {
+            return Err(anyhow!(
+                "sla.alert_after must not be shorter than sla.warn_after"
+            ));
+        }
+        Ok(())
+    }
+
+    fn thresholds(&self) -> Result<(Duration, Duration)> {
+        let parse = |key: &str, value: &str| {
+            parse_duration(value).map_err(|e| anyhow!("sla.{}: {}", key, e))
+        };
+        Ok((
+            parse("warn_after", &self.warn_after)?,
+            parse("alert_after", &self.alert_after)?,
+        ))
+    }
+
+    // "3d 4h", colored yellow or red on a terminal once an open escrow has
+    // waited past the thresholds
+    pub fn render(&self, state: EscrowState, age: Duration) -> String {
+        let text = format_duration(age);
+        let open = matches!(
+            state,
+            EscrowState::Created | EscrowState::Initialized | EscrowState::Funded
+        );
+        let color_ok = io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+        let Ok((warn, alert)) = self.thresholds() else {
+            return text;
+        };
+        match (open && color_ok, age) {
+            (true, age) if age >= alert => format!("{}{}{}", RED, text, RESET),
+            (true, age) if age >= warn => format!("{}{}{}", YELLOW, text, RESET),
+            _ => text,
+        }
+    }
+}
+
+// getSignaturesForAddress page size while looking for the last state change
+const PAGE_SIZE: usize = 100;
+// Transactions looked at before giving up; anyone can pad an escrow's
+// history, and each one costs a getTransaction
+const MAX_SCANNED: usize = 300;
+
+// Escrow program instructions that move an escrow to a new state: everything
+// but close, after which there is no account left to age
+const STATE_CHANGES: [u8; 7] = [0, 1, 2, 3, 4, 5, 8];
+
+// Block time of the newest successful transaction that ran a state-changing
+// escrow program instruction on the escrow, i.e. when it entered its current
+// state. Anything else touching the account (a dust transfer, a memo) leaves
+// the clock alone. None when no such transaction is among the newest
+// MAX_SCANNED.
+pub fn state_since(ctx: &Context, escrow: &Pubkey) -> Result<Option<i64>> {
+    let mut before = None;
+    let mut scanned = 0;
+    while scanned < MAX_SCANNED {
+        let page = ctx.client.get_signatures_for_address_with_config(
+            escrow,
+            GetConfirmedSignaturesForAddress2Config {
+                before,
+                limit: Some(PAGE_SIZE),
+                commitment: Some(ctx.client.commitment()),
+                ..GetConfirmedSignaturesForAddress2Config::default()
+            },
+        )?;
+        for status in page.iter().take(MAX_SCANNED - scanned) {
+            let signature = Signature::from_str(&status.signature)?;
+            before = Some(signature);
+            scanned += 1;
+            if status.err.is_none() && changes_state(ctx, escrow, &signature)? {
+                return Ok(status.block_time);
+            }
+        }
+        if page.len() < PAGE_SIZE {
+            return Ok(None);
+        }
+    }
+    Ok(None)
+}
+
+// Whether a top-level instruction of the transaction is a state-changing
+// escrow program instruction naming the escrow account
+fn changes_state(ctx: &Context, escrow: &Pubkey, signature: &Signature) -> Result<bool> {
+    let transaction = ctx.client.get_transaction_with_config(
+        signature,
+        RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: Some(ctx.client.commitment()),
+            max_supported_transaction_version: Some(0),
+        },
+    )?;
+    let decoded = transaction
+        .transaction
+        .transaction
+        .decode()
+        .ok_or_else(|| anyhow!("Failed to decode transaction {}", signature))?;
+    let keys = decoded.message.static_account_keys();
+    Ok(decoded.message.instructions().iter().any(|ix| {
+        keys.get(ix.program_id_index as usize) == Some(&ctx.program_id)
+            && ix
+                .data
+                .first()
+                .is_some_and(|tag| STATE_CHANGES.contains(tag))
+            && ix
+                .accounts
+                .iter()
+                .any(|&index| keys.get(index as usize) == Some(escrow))
+    }))
+}
+
+// How long the escrow has been in its current state
+pub fn state_age(ctx: &Context, escrow: &Pubkey) -> Result<Option<Duration>> {
+    Ok(state_since(ctx, escrow)?.map(|since| Duration::from_secs((now() - since).max(0) as u64)))
+}
+
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default()
+}
@@ -0,0 +1,66 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::network::{self, Body};
+
+const DEFAULT_IPFS_GATEWAY: &str = "https://ipfs.io";
+const DEFAULT_ARWEAVE_GATEWAY: &str = "https://arweave.net";
+
+// Where terms documents are uploaded to and fetched from
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Storage {
+    // IPFS HTTP API (kubo or a pinning service exposing /api/v0/add)
+    pub ipfs_api_url: Option<String>,
+    pub ipfs_api_token: Option<String>,
+    pub ipfs_gateway: Option<String>,
+    pub arweave_gateway: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct IpfsAddResponse {
+    #[serde(rename = "Hash")]
+    hash: String,
+}
+
+// Upload a document through the IPFS API and return its ipfs:// location
+pub fn upload_ipfs(storage: &Storage, file_name: &str, contents: Vec<u8>) -> Result<String> {
+    let api = storage
+        .ipfs_api_url
+        .as_deref()
+        .ok_or_else(|| anyhow!("Set storage.ipfs_api_url in the config to upload to IPFS"))?;
+    let headers: Vec<(&str, String)> = storage
+        .ipfs_api_token
+        .iter()
+        .map(|token| ("Authorization", format!("Bearer {}", token)))
+        .collect();
+    let response = network::post(
+        "IPFS upload",
+        &format!("{}/api/v0/add?pin=true", api.trim_end_matches('/')),
+        &headers,
+        Body::File {
+            name: file_name.to_string(),
+            contents,
+        },
+    )?;
+    let added: IpfsAddResponse = serde_json::from_slice(&response)
+        .map_err(|e| anyhow!("Invalid IPFS upload response: {}", e))?;
+    Ok(format!("ipfs://{}", added.hash))
+}
+
+// Download a document from an ipfs:// or ar:// location through its gateway
+pub fn fetch(storage: &Storage, location: &str) -> Result<Vec<u8>> {
+    let url = if let Some(cid) = location.strip_prefix("ipfs://") {
+        let gateway = storage.ipfs_gateway.as_deref().unwrap_or(DEFAULT_IPFS_GATEWAY);
+        format!("{}/ipfs/{}", gateway.trim_end_matches('/'), cid)
+    } else if let Some(id) = location.strip_prefix("ar://") {
+        let gateway = storage
+            .arweave_gateway
+            .as_deref()
+            .unwrap_or(DEFAULT_ARWEAVE_GATEWAY);
+        format!("{}/{}", gateway.trim_end_matches('/'), id)
+    } else {
+        return Err(anyhow!("Unsupported document location: {}", location));
+    };
+    network::get(&format!("Fetching {}", url), &url, &[])
+}
@@ -0,0 +1,136 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::{json, Value};
+use solana_sdk::{pubkey, pubkey::Pubkey, signature::Signer, transaction::Transaction};
+use std::str::FromStr;
+
+use crate::envelope::{cluster, MAINNET};
+use crate::network;
+use crate::totp;
+use crate::{build_confirm_escrow, interrupt, simulate_and_send, Context};
+
+// Jupiter's keyless Swap API; the v6 quote-api host is deprecated
+const JUPITER_API: &str = "https://lite-api.jup.ag/swap/v1";
+const WRAPPED_SOL: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
+// Well-known mainnet mints accepted by name
+const KNOWN_MINTS: [(&str, &str); 2] = [
+    ("USDC", "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"),
+    ("USDT", "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB"),
+];
+
+// Confirm the escrow as seller, then swap the released SOL into `mint`
+// through Jupiter. The quote is shown before anything is signed and the swap
+// is refused if the price has moved past the slippage bound since.
+pub fn confirm_and_swap(
+    ctx: &Context,
+    seller_keypair_path: &str,
+    escrow_account: &str,
+    mint: &str,
+    slippage_bps: u16,
+) -> Result<()> {
+    let output_mint = resolve_mint(mint)?;
+    // Jupiter only routes mainnet liquidity
+    let (network, _) = cluster(ctx)?;
+    if network != MAINNET {
+        return Err(anyhow!(
+            "--settle-in swaps through Jupiter, which only works on {}; this RPC node \
+             serves {}",
+            MAINNET,
+            network
+        ));
+    }
+    let seller_keypair = ctx.read_keypair(seller_keypair_path, "seller")?;
+    let escrow_pubkey = Pubkey::from_str(escrow_account)?;
+    let escrow = ctx.get_escrow(&escrow_pubkey)?;
+    if escrow.seller != seller_keypair.pubkey() {
+        return Err(anyhow!("{} is not the seller of this escrow", seller_keypair.pubkey()));
+    }
+
+    let upfront = quote(escrow.amount.lamports(), &output_mint, slippage_bps)?;
+    let minimum = quote_amount(&upfront, "otherAmountThreshold")?;
+    println!("Swap quote for the released {} SOL:", escrow.amount);
+    println!("  Expected:     {} (base units of {})", quote_amount(&upfront, "outAmount")?, mint);
+    println!("  Minimum:      {} at {} bps slippage", minimum, slippage_bps);
+    println!(
+        "  Price impact: {}%",
+        upfront["priceImpactPct"].as_str().unwrap_or("?")
+    );
+
+    let mut release = build_confirm_escrow(ctx, &seller_keypair.pubkey(), escrow_account)?;
+    totp::check(ctx, "confirm", escrow.amount)?;
+    release.sign(&[&seller_keypair])?;
+    let Some(signature) = release.execute(ctx)? else {
+        // Nothing was released, so Jupiter has nothing to build a swap from
+        println!("Dry run: the swap was not built");
+        return Ok(());
+    };
+    println!("Transaction confirmed! Signature: {}", signature);
+    interrupt::building();
+
+    // Quote again: the first one is stale by the time the release lands
+    let current = quote(escrow.amount.lamports(), &output_mint, slippage_bps)?;
+    let expected = quote_amount(&current, "outAmount")?;
+    if expected < minimum {
+        return Err(anyhow!(
+            "Price moved past the slippage bound ({} < {}); funds were released as SOL",
+            expected,
+            minimum
+        ));
+    }
+    let response: Value = network::post_json(
+        "Jupiter swap",
+        &format!("{}/swap", JUPITER_API),
+        json!({
+            "quoteResponse": current,
+            "userPublicKey": seller_keypair.pubkey().to_string(),
+            "wrapAndUnwrapSol": true,
+            // A legacy transaction goes through the same send path as
+            // every other command
+            "asLegacyTransaction": true,
+        }),
+    )?;
+    let encoded = response["swapTransaction"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Jupiter returned no swap transaction"))?;
+    let mut transaction: Transaction = bincode::deserialize(&STANDARD.decode(encoded)?)?;
+    let blockhash = transaction.message.recent_blockhash;
+    transaction.try_sign(&[&seller_keypair], blockhash)?;
+    let Some(signature) = simulate_and_send(ctx, &transaction, &escrow_pubkey, "swap")
+        .map_err(|e| anyhow!("{}; funds were released as SOL", e))?
+    else {
+        return Ok(());
+    };
+    println!("Swapped to {} successfully! Signature: {}", mint, signature);
+    Ok(())
+}
+
+fn quote(lamports: u64, output_mint: &Pubkey, slippage_bps: u16) -> Result<Value> {
+    network::get_json(
+        "Jupiter quote",
+        &format!("{}/quote", JUPITER_API),
+        &[
+            ("inputMint", WRAPPED_SOL.to_string()),
+            ("outputMint", output_mint.to_string()),
+            ("amount", lamports.to_string()),
+            ("slippageBps", slippage_bps.to_string()),
+            ("asLegacyTransaction", "true".to_string()),
+        ],
+    )
+}
+
+// Jupiter encodes token amounts as decimal strings
+fn quote_amount(quote: &Value, field: &str) -> Result<u64> {
+    quote[field]
+        .as_str()
+        .and_then(|amount| amount.parse().ok())
+        .ok_or_else(|| anyhow!("Jupiter quote is missing {}", field))
+}
+
+fn resolve_mint(mint: &str) -> Result<Pubkey> {
+    let address = KNOWN_MINTS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(mint))
+        .map(|(_, address)| *address)
+        .unwrap_or(mint);
+    Pubkey::from_str(address).map_err(|_| anyhow!("Unknown token '{}': pass a mint address", mint))
+}
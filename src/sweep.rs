@@ -0,0 +1,166 @@
+use anyhow::Result;
+use serde_json::json;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_config::RpcAccountInfoConfig;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signer};
+use std::collections::HashMap;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use crate::account::{EscrowAccount, EscrowState};
+use crate::amount::Lamports;
+use crate::search::escrows_with_party;
+use crate::{close_escrow, hooks, sla, Context};
+
+const SUMMARY_EVERY: Duration = Duration::from_secs(24 * 60 * 60);
+
+// What a sweep has done since the last summary
+#[derive(Default)]
+struct Totals {
+    closed: usize,
+    failed: usize,
+    reclaimed: Lamports,
+}
+
+// Close attempts on one escrow before the sweep leaves it alone
+const MAX_CLOSE_ATTEMPTS: u32 = 5;
+
+// Close the closer's Completed and Cancelled escrows once that state has
+// been finalized for at least `after`, reclaiming their rent. With
+// `interval`, keep sweeping and send a summary to the notify hook once a
+// day; a single pass sends one when it closed or failed anything. RPC errors
+// skip an escrow, or the whole pass, until the next one. An escrow whose
+// close fails waits twice as many passes after each failure, and is given up
+// on after MAX_CLOSE_ATTEMPTS.
+pub fn sweep(
+    ctx: &Context,
+    closer_keypair_path: &str,
+    after: Duration,
+    interval: Option<u64>,
+) -> Result<()> {
+    let closer = ctx.read_keypair(closer_keypair_path, "closer")?.pubkey();
+    let mut totals = Totals::default();
+    let mut last_summary = Instant::now();
+    // Escrows whose close failed: attempts so far and passes left to skip
+    let mut failing: HashMap<Pubkey, (u32, u32)> = HashMap::new();
+    loop {
+        let escrows = match escrows_with_party(ctx, &closer) {
+            Ok(escrows) => escrows,
+            Err(e) if interval.is_none() => return Err(e),
+            Err(e) => {
+                eprintln!("Warning: failed to list escrows, retrying next pass: {}", e);
+                Vec::new()
+            }
+        };
+        for (escrow_pubkey, escrow) in escrows {
+            if !matches!(
+                escrow.state,
+                EscrowState::Completed | EscrowState::Cancelled
+            ) {
+                continue;
+            }
+            match failing.get_mut(&escrow_pubkey) {
+                Some((attempts, _)) if *attempts >= MAX_CLOSE_ATTEMPTS => continue,
+                Some((_, skip)) if *skip > 0 => {
+                    *skip -= 1;
+                    continue;
+                }
+                _ => {}
+            }
+            let rent: Result<u64> = match settled_for(ctx, &escrow_pubkey, after) {
+                Ok(true) => ctx.client.get_balance(&escrow_pubkey).map_err(Into::into),
+                Ok(false) => continue,
+                Err(e) => Err(e),
+            };
+            let rent = match rent {
+                Ok(rent) => rent,
+                Err(e) => {
+                    eprintln!("Warning: skipping {} this pass: {}", escrow_pubkey, e);
+                    continue;
+                }
+            };
+            println!(
+                "Closing {} ({}, {} SOL)",
+                escrow_pubkey, escrow.state, escrow.amount
+            );
+            match close_escrow(ctx, closer_keypair_path, &escrow_pubkey.to_string(), false) {
+                Ok(()) => {
+                    failing.remove(&escrow_pubkey);
+                    totals.closed += 1;
+                    totals.reclaimed = totals.reclaimed.checked_add(Lamports::new(rent))?;
+                }
+                Err(e) => {
+                    let (attempts, skip) = failing.entry(escrow_pubkey).or_default();
+                    *attempts += 1;
+                    *skip = 2u32.saturating_pow(*attempts) - 1;
+                    if *attempts >= MAX_CLOSE_ATTEMPTS {
+                        eprintln!(
+                            "Failed to close {} ({} attempts), giving up on it: {}",
+                            escrow_pubkey, attempts, e
+                        );
+                    } else {
+                        eprintln!("Failed to close {}: {}", escrow_pubkey, e);
+                    }
+                    totals.failed += 1;
+                }
+            }
+        }
+        let Some(interval) = interval else {
+            if totals.closed + totals.failed > 0 {
+                summarize(ctx, &closer, &totals);
+            }
+            return Ok(());
+        };
+        if last_summary.elapsed() >= SUMMARY_EVERY {
+            summarize(ctx, &closer, &totals);
+            totals = Totals::default();
+            last_summary = Instant::now();
+        }
+        sleep(Duration::from_secs(interval));
+    }
+}
+
+// Whether the finalized account is settled too, and the transaction that
+// settled it is at least `after` old
+fn settled_for(ctx: &Context, escrow: &Pubkey, after: Duration) -> Result<bool> {
+    let finalized = ctx
+        .client
+        .get_account_with_config(
+            escrow,
+            RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(CommitmentConfig::finalized()),
+                ..RpcAccountInfoConfig::default()
+            },
+        )?
+        .value;
+    let settled = finalized
+        .and_then(|account| EscrowAccount::unpack(&account.data).ok())
+        .is_some_and(|escrow| {
+            matches!(
+                escrow.state,
+                EscrowState::Completed | EscrowState::Cancelled
+            )
+        });
+    if !settled {
+        return Ok(false);
+    }
+    Ok(sla::state_age(ctx, escrow)?.is_some_and(|age| age >= after))
+}
+
+fn summarize(ctx: &Context, closer: &Pubkey, totals: &Totals) {
+    println!(
+        "Sweep: closed {} escrows, reclaimed {} SOL, {} failed",
+        totals.closed, totals.reclaimed, totals.failed
+    );
+    hooks::notify(
+        &ctx.config,
+        &json!({
+            "event": "sweep_summary",
+            "closer": closer.to_string(),
+            "closed": totals.closed,
+            "failed": totals.failed,
+            "reclaimed": totals.reclaimed.lamports(),
+        }),
+    );
+}
@@ -0,0 +1,166 @@
+use anyhow::{anyhow, Result};
+use solana_sdk::{
+    hash::hash,
+    message::Message,
+    pubkey::Pubkey,
+    signature::Signer,
+    transaction::Transaction,
+};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::account::EscrowAccount;
+use crate::memo::{find_memos, memo_instruction, transaction_signers};
+use crate::storage;
+use crate::{simulate_and_send, Context};
+
+const TERMS_TAG: &str = "escrow-terms:v1";
+
+// A terms hash committed on-chain by one of the escrow parties
+#[derive(Debug, Clone)]
+pub struct TermsCommitment {
+    pub signature: String,
+    pub digest: String,
+    // ipfs:// or ar:// location of the document, when it was uploaded
+    pub location: Option<String>,
+    pub signer: Pubkey,
+    pub block_time: Option<i64>,
+}
+
+// Publish the SHA-256 of a terms document in a memo signed by an escrow party,
+// optionally together with where the document itself can be fetched
+pub fn attach_terms(
+    ctx: &Context,
+    keypair_path: &str,
+    escrow_account: &str,
+    file: &str,
+    ipfs: bool,
+    arweave_id: Option<&str>,
+) -> Result<()> {
+    let keypair = ctx.read_keypair(keypair_path, "signer")?;
+    let escrow_pubkey = Pubkey::from_str(escrow_account)?;
+    let escrow = ctx.get_escrow(&escrow_pubkey)?;
+    if !escrow.parties().contains(&keypair.pubkey()) {
+        return Err(anyhow!(
+            "{} is not the buyer, seller or arbiter of this escrow",
+            keypair.pubkey()
+        ));
+    }
+    let contents = fs::read(file).map_err(|e| anyhow!("Failed to read {}: {}", file, e))?;
+    let digest = digest_bytes(&contents);
+    let location = match (ipfs, arweave_id) {
+        (true, _) => {
+            let file_name = Path::new(file)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "terms".to_string());
+            let location = storage::upload_ipfs(&ctx.config.storage, &file_name, contents)?;
+            println!("Uploaded terms to {}", location);
+            Some(location)
+        }
+        (false, Some(id)) => Some(format!("ar://{}", id)),
+        (false, None) => None,
+    };
+    let mut memo = format!("{} {} sha256:{}", TERMS_TAG, escrow_pubkey, digest);
+    if let Some(location) = &location {
+        memo.push(' ');
+        memo.push_str(location);
+    }
+    let memo_ix = memo_instruction(&memo, &[&keypair.pubkey()]);
+    let blockhash = ctx.client
+        .get_latest_blockhash()
+        .map_err(|e| anyhow!("Blockhash error: {}", e))?;
+    let message = Message::new(&[memo_ix], Some(&keypair.pubkey()));
+    let transaction = Transaction::new(&[&keypair], message, blockhash);
+    let Some(signature) = simulate_and_send(ctx, &transaction, &escrow_pubkey, "attach_terms")?
+    else {
+        return Ok(());
+    };
+    println!("Terms attached! SHA-256: {}", digest);
+    println!("Signature: {}", signature);
+    Ok(())
+}
+
+// Check a local document against the terms hashes committed for the escrow
+pub fn verify_terms(ctx: &Context, escrow_account: &str, file: &str) -> Result<()> {
+    let escrow_pubkey = Pubkey::from_str(escrow_account)?;
+    let escrow = ctx.get_escrow(&escrow_pubkey)?;
+    let digest = file_digest(file)?;
+    let commitments = committed_terms(ctx, &escrow_pubkey, &escrow)?;
+    if commitments.is_empty() {
+        return Err(anyhow!("No terms have been attached to escrow {}", escrow_pubkey));
+    }
+    match commitments.iter().find(|c| c.digest == digest) {
+        Some(commitment) => {
+            println!("Terms match! SHA-256: {}", digest);
+            println!("Committed by: {}", commitment.signer);
+            println!("Signature: {}", commitment.signature);
+            if let Some(block_time) = commitment.block_time {
+                println!("Block time: {}", block_time);
+            }
+            Ok(())
+        }
+        None => {
+            println!("Committed terms hashes:");
+            for commitment in &commitments {
+                println!("  {} by {} ({})", commitment.digest, commitment.signer, commitment.signature);
+            }
+            Err(anyhow!("File hash {} does not match any committed terms", digest))
+        }
+    }
+}
+
+// Terms memos for the escrow found in its parties' histories. Only memos
+// actually signed by a party count, so a stray transfer carrying a forged
+// memo into a party's history is ignored.
+pub fn committed_terms(
+    ctx: &Context,
+    escrow_pubkey: &Pubkey,
+    escrow: &EscrowAccount,
+) -> Result<Vec<TermsCommitment>> {
+    let parties = escrow.parties();
+    let prefix = format!("{} {} sha256:", TERMS_TAG, escrow_pubkey);
+    let mut seen = HashSet::new();
+    let mut commitments = Vec::new();
+    for party in &parties {
+        for record in find_memos(&ctx.client, party)? {
+            let Some(rest) = record.memo.strip_prefix(&prefix) else {
+                continue;
+            };
+            let mut fields = rest.split_whitespace();
+            let Some(digest) = fields.next() else {
+                continue;
+            };
+            if !seen.insert(record.signature.clone()) {
+                continue;
+            }
+            let signers = transaction_signers(&ctx.client, &record.signature)?;
+            if let Some(signer) = signers.iter().find(|s| parties.contains(*s)) {
+                commitments.push(TermsCommitment {
+                    signature: record.signature.clone(),
+                    digest: digest.to_string(),
+                    location: fields.next().map(str::to_string),
+                    signer: *signer,
+                    block_time: record.block_time,
+                });
+            }
+        }
+    }
+    Ok(commitments)
+}
+
+// Hex-encoded SHA-256 of a file, comparable with `sha256sum`
+pub fn file_digest(path: &str) -> Result<String> {
+    let contents = fs::read(path).map_err(|e| anyhow!("Failed to read {}: {}", path, e))?;
+    Ok(digest_bytes(&contents))
+}
+
+pub fn digest_bytes(contents: &[u8]) -> String {
+    to_hex(hash(contents).as_ref())
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
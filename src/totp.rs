@@ -0,0 +1,141 @@
+use anyhow::{anyhow, Result};
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use solana_sdk::signature::Keypair;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::amount::{parse_amount, Lamports};
+use crate::config::{config_dir, expand_home, Config};
+use crate::onboarding::{confirm, prompt};
+use crate::{seal, Context};
+
+// RFC 6238 defaults, which every authenticator app understands
+const STEP_SECS: u64 = 30;
+const DIGITS: u32 = 6;
+// Accept the previous and next code too, for clock drift
+const WINDOW: u64 = 1;
+const SECRET_FILE: &str = "totp.age";
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Totp {
+    // Ask for a code before signing confirm, arbiter-confirm, arbiter-cancel
+    // or mutual-cancel on escrows holding more than this, e.g. "5 SOL"
+    pub above: Option<String>,
+}
+
+impl Totp {
+    pub fn validate(&self) -> Result<()> {
+        self.threshold().map(|_| ())
+    }
+
+    fn threshold(&self) -> Result<Option<Lamports>> {
+        self.above
+            .as_deref()
+            .map(|amount| parse_amount(amount).map_err(|e| anyhow!("totp.above: {}", e)))
+            .transpose()
+    }
+}
+
+// The encrypted secret lives next to the keypairs: in the keystore, or the
+// config directory without one
+fn secret_path(config: &Config) -> Result<PathBuf> {
+    match &config.keystore {
+        Some(keystore) => Ok(expand_home(keystore).join(SECRET_FILE)),
+        None => config_dir()
+            .map(|dir| dir.join(SECRET_FILE))
+            .ok_or_else(|| {
+                anyhow!("Cannot locate a config directory; set `keystore` in the config")
+            }),
+    }
+}
+
+fn code(secret: &[u8], counter: u64) -> Result<u32> {
+    let mut mac =
+        Hmac::<Sha1>::new_from_slice(secret).map_err(|e| anyhow!("Invalid TOTP secret: {}", e))?;
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    // Dynamic truncation: four bytes at the offset named by the last nibble
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let bytes: [u8; 4] = digest[offset..offset + 4].try_into()?;
+    Ok((u32::from_be_bytes(bytes) & 0x7fff_ffff) % 10u32.pow(DIGITS))
+}
+
+fn verify(secret: &[u8], answer: &str) -> Result<bool> {
+    let Ok(answer) = answer.trim().parse::<u32>() else {
+        return Ok(false);
+    };
+    let counter = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() / STEP_SECS;
+    for step in counter.saturating_sub(WINDOW)..=counter + WINDOW {
+        if code(secret, step)? == answer {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+// Create a secret, show it for an authenticator app, and save it encrypted
+// once the app's first code checks out
+pub fn setup(ctx: &Context) -> Result<()> {
+    let path = secret_path(&ctx.config)?;
+    ctx.config.sandbox.check_write(&path)?;
+    if path.exists() && !confirm("Replace the existing TOTP secret?", false)? {
+        return Err(anyhow!(
+            "TOTP setup aborted, {} left untouched",
+            path.display()
+        ));
+    }
+    // 160 bits from the OS random source, via a fresh keypair's secret half
+    let secret = Keypair::new().to_bytes()[..20].to_vec();
+    let encoded = BASE32_NOPAD.encode(&secret);
+    println!("Add this account to your authenticator app:");
+    println!(
+        "  otpauth://totp/escrow-cli?secret={}&issuer=escrow-cli",
+        encoded
+    );
+    println!("or enter the key by hand: {}", encoded);
+    if !verify(&secret, &prompt("Code shown by the app", "")?)? {
+        return Err(anyhow!("Wrong code, TOTP was not set up"));
+    }
+    let encrypted = seal::encrypt(&secret, "TOTP secret")?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, encrypted)?;
+    println!("TOTP secret saved to {}", path.display());
+    if ctx.config.totp.above.is_none() {
+        println!(
+            "Codes are required once `totp.above` is set, e.g. `config set totp.above \"5 SOL\"`"
+        );
+    }
+    Ok(())
+}
+
+// Before signing `command` on an escrow holding `amount`, ask for a code when
+// the amount is above `totp.above`
+pub fn check(ctx: &Context, command: &str, amount: Lamports) -> Result<()> {
+    let Some(above) = ctx.config.totp.threshold()? else {
+        return Ok(());
+    };
+    // A dry run never signs
+    if amount <= above || ctx.dry_run {
+        return Ok(());
+    }
+    let path = secret_path(&ctx.config)?;
+    let encrypted = fs::read(&path).map_err(|_| {
+        anyhow!(
+            "{} SOL is above totp.above but no TOTP secret is set up; run `totp setup`",
+            amount
+        )
+    })?;
+    let secret = seal::decrypt(&encrypted, &path.display().to_string(), "TOTP secret")?;
+    let answer = prompt(&format!("TOTP code to {} {} SOL", command, amount), "")?;
+    if !verify(&secret, &answer)? {
+        return Err(anyhow!("Wrong TOTP code, {} was not signed", command));
+    }
+    Ok(())
+}
@@ -0,0 +1,207 @@
+use anyhow::{anyhow, Result};
+use std::fmt::Display;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::config::redact_url;
+use crate::sandbox::Sandbox;
+
+// Flags whose value is free text, e.g. a note or memo to the other party
+const TEXT_FLAGS: [&str; 3] = ["--message", "--memo", "--text"];
+// Short flags that are free text only under one subcommand; elsewhere `-m`
+// is the amount
+const SHORT_TEXT_FLAGS: [(&str, &str); 1] = [("note", "-m")];
+
+// The session log written with --transcript, for attaching to a support
+// request: the command line, RPC endpoint, every transaction simulated or
+// sent with its signature, everything the command printed, and how the
+// session ended. Unless asked not to, keypair and other file paths, URL paths
+// and queries (where API keys live), free text on the command line and memo
+// text the command printed (arbiter notes, search matches, verdicts) are
+// replaced before anything is written.
+struct Transcript {
+    file: File,
+    started: Instant,
+    redact: bool,
+}
+
+static TRANSCRIPT: Mutex<Option<Transcript>> = Mutex::new(None);
+
+// Memo text shown so far, replaced in redacted output lines
+static MEMOS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+pub fn start(sandbox: &Sandbox, path: &str, redact: bool) -> Result<()> {
+    sandbox.check_write(Path::new(path))?;
+    let mut file =
+        File::create(path).map_err(|e| anyhow!("Failed to create transcript {}: {}", path, e))?;
+    let args: Vec<String> = std::env::args().collect();
+    let command = if redact { redact_args(&args) } else { args };
+    writeln!(file, "escrow-cli {} transcript", env!("CARGO_PKG_VERSION"))?;
+    writeln!(
+        file,
+        "Started: {} (unix time)",
+        SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs()
+    )?;
+    writeln!(file, "Redacted: {}", if redact { "yes" } else { "no" })?;
+    writeln!(file, "Command: {}", command.join(" "))?;
+    if let Ok(mut guard) = TRANSCRIPT.lock() {
+        *guard = Some(Transcript {
+            file,
+            started: Instant::now(),
+            redact,
+        });
+    }
+    Ok(())
+}
+
+// Add a line, stamped with the seconds since the session started
+pub fn record(line: impl Display) {
+    if let Ok(mut guard) = TRANSCRIPT.lock() {
+        if let Some(transcript) = guard.as_mut() {
+            let elapsed = transcript.started.elapsed().as_secs_f64();
+            // A transcript that can't be written must not fail the command
+            let _ = writeln!(transcript.file, "[{:>8.2}s] {}", elapsed, line);
+        }
+    }
+}
+
+fn redacting() -> bool {
+    TRANSCRIPT
+        .lock()
+        .map(|guard| !guard.as_ref().is_some_and(|transcript| !transcript.redact))
+        .unwrap_or(true)
+}
+
+pub fn record_url(what: &str, url: &str) {
+    let url = if redacting() {
+        redact_url(url)
+    } else {
+        url.to_string()
+    };
+    record(format!("{}: {}", what, url));
+}
+
+// Memo text about to be printed; a redacted transcript shows <memo> instead
+pub fn memo(text: &str) {
+    if text.is_empty() || active() != Some(true) {
+        return;
+    }
+    if let Ok(mut memos) = MEMOS.lock() {
+        memos.push(text.to_string());
+    }
+}
+
+// A line the command printed, on stdout or stderr
+pub fn output(text: &str) {
+    let Some(redact) = active() else {
+        return;
+    };
+    for line in text.lines() {
+        if redact {
+            record(format!("> {}", redact_words(&hide_memos(line))));
+        } else {
+            record(format!("> {}", line));
+        }
+    }
+}
+
+// Whether a transcript is being written, and if so whether it is redacted
+fn active() -> Option<bool> {
+    TRANSCRIPT
+        .lock()
+        .ok()
+        .and_then(|guard| guard.as_ref().map(|transcript| transcript.redact))
+}
+
+fn hide_memos(line: &str) -> String {
+    let mut line = line.to_string();
+    if let Ok(memos) = MEMOS.lock() {
+        for memo in memos.iter() {
+            line = line.replace(memo.as_str(), "<memo>");
+        }
+    }
+    line
+}
+
+// End the process with `code`, noting it first since process::exit skips the
+// `finish` in main
+pub fn exit(code: i32) -> ! {
+    record(format!("Exited with code {}", code));
+    std::process::exit(code)
+}
+
+pub fn finish(result: &Result<()>) {
+    match result {
+        Ok(()) => record("Finished successfully"),
+        Err(e) => {
+            let message = e.to_string();
+            let message = if redacting() {
+                redact_words(&message)
+            } else {
+                message
+            };
+            record(format!("Failed: {}", message));
+        }
+    }
+}
+
+fn redact_args(args: &[String]) -> Vec<String> {
+    let short_flags: Vec<&str> = SHORT_TEXT_FLAGS
+        .iter()
+        .filter(|(subcommand, _)| args.iter().any(|arg| arg == subcommand))
+        .map(|(_, flag)| *flag)
+        .collect();
+    let mut redacted = Vec::with_capacity(args.len());
+    let mut text_follows = false;
+    for arg in args {
+        if text_follows {
+            redacted.push("<text>".to_string());
+            text_follows = false;
+            continue;
+        }
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value)),
+            _ => (arg.as_str(), None),
+        };
+        if TEXT_FLAGS.contains(&flag) || short_flags.contains(&flag) {
+            match value {
+                Some(_) => redacted.push(format!("{}=<text>", flag)),
+                None => {
+                    redacted.push(arg.clone());
+                    text_follows = true;
+                }
+            }
+            continue;
+        }
+        redacted.push(match value {
+            Some(value) => format!("{}={}", flag, redact_word(value)),
+            None => redact_word(arg),
+        });
+    }
+    redacted
+}
+
+fn redact_words(text: &str) -> String {
+    text.split(' ')
+        .map(redact_word)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// URLs keep scheme and host; anything that names a file becomes <path>
+fn redact_word(word: &str) -> String {
+    if word.contains("://") {
+        return redact_url(word);
+    }
+    let looks_like_path = word.contains('/')
+        || word.ends_with(".json")
+        || (!word.starts_with('-') && Path::new(word).exists());
+    if looks_like_path {
+        "<path>".to_string()
+    } else {
+        word.to_string()
+    }
+}
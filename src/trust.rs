@@ -0,0 +1,118 @@
+use anyhow::{anyhow, Result};
+use solana_sdk::{
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    pubkey::Pubkey,
+};
+use std::io::IsTerminal;
+
+use crate::{config, onboarding, Context};
+
+const IMMUTABLE: &str = "immutable";
+
+// Who can change the program's code, and when it was last deployed
+struct ProgramInfo {
+    upgrade_authority: Option<Pubkey>,
+    deploy_slot: Option<u64>,
+}
+
+impl ProgramInfo {
+    fn authority(&self) -> String {
+        self.upgrade_authority
+            .map_or_else(|| IMMUTABLE.to_string(), |authority| authority.to_string())
+    }
+}
+
+fn program_info(ctx: &Context) -> Result<ProgramInfo> {
+    let program = ctx
+        .client
+        .get_account(&ctx.program_id)
+        .map_err(|e| anyhow!("Failed to fetch program {}: {}", ctx.program_id, e))?;
+    if !program.executable {
+        return Err(anyhow!("{} is not an executable program account", ctx.program_id));
+    }
+    if program.owner != bpf_loader_upgradeable::id() {
+        // Programs under the older loaders can't be upgraded at all
+        return Ok(ProgramInfo {
+            upgrade_authority: None,
+            deploy_slot: None,
+        });
+    }
+    let UpgradeableLoaderState::Program {
+        programdata_address,
+    } = bincode::deserialize(&program.data)?
+    else {
+        return Err(anyhow!("Unexpected program account layout for {}", ctx.program_id));
+    };
+    let programdata = ctx.client.get_account(&programdata_address)?;
+    let metadata = UpgradeableLoaderState::size_of_programdata_metadata();
+    match bincode::deserialize(&programdata.data[..metadata.min(programdata.data.len())])? {
+        UpgradeableLoaderState::ProgramData {
+            slot,
+            upgrade_authority_address,
+        } => Ok(ProgramInfo {
+            upgrade_authority: upgrade_authority_address,
+            deploy_slot: Some(slot),
+        }),
+        _ => Err(anyhow!("Unexpected program data layout for {}", ctx.program_id)),
+    }
+}
+
+// Trust is per program and cluster: the same address can hold different
+// code on devnet and mainnet
+fn trust_key(ctx: &Context) -> Result<String> {
+    Ok(format!("{}@{}", ctx.program_id, ctx.client.get_genesis_hash()?))
+}
+
+fn describe(ctx: &Context, info: &ProgramInfo) {
+    println!("Program:           {}", ctx.program_id);
+    println!("Upgrade authority: {}", info.authority());
+    match info.deploy_slot {
+        Some(slot) => println!("Last deployed:     slot {}", slot),
+        None => println!("Last deployed:     unknown (non-upgradeable loader)"),
+    }
+}
+
+// Refuse to sign for a program the user hasn't explicitly trusted on this
+// cluster, or whose upgrade authority changed since they did. A lookalike
+// `program_id` then shows up as an unfamiliar authority before any funds move.
+pub fn check(ctx: &Context, config_path: Option<&str>) -> Result<()> {
+    let key = trust_key(ctx)?;
+    let info = program_info(ctx)?;
+    let trusted = ctx.config.trusted_programs.get(&key);
+    if trusted.map(String::as_str) == Some(info.authority().as_str()) {
+        return Ok(());
+    }
+    match trusted {
+        Some(previous) => {
+            eprintln!("WARNING: the program's upgrade authority changed since you trusted it");
+            eprintln!("  Trusted authority: {}", previous);
+        }
+        None => println!("First use of this program on this cluster:"),
+    }
+    describe(ctx, &info);
+    if !std::io::stdin().is_terminal() {
+        return Err(anyhow!(
+            "Program {} is not trusted; review it with `escrow-cli trust-program`",
+            ctx.program_id
+        ));
+    }
+    if !onboarding::confirm("Trust this program and continue?", false)? {
+        return Err(anyhow!("Program {} not trusted", ctx.program_id));
+    }
+    config::set(config_path, &format!("trusted_programs.{}", key), &info.authority())
+}
+
+// Show the program's authority and deploy slot and record trust in it
+pub fn trust_program(ctx: &Context, config_path: Option<&str>, yes: bool) -> Result<()> {
+    let key = trust_key(ctx)?;
+    let info = program_info(ctx)?;
+    describe(ctx, &info);
+    if ctx.config.trusted_programs.get(&key) == Some(&info.authority()) {
+        println!("Already trusted");
+        return Ok(());
+    }
+    if !yes && !onboarding::confirm("Trust this program?", false)? {
+        return Err(anyhow!("Program {} not trusted", ctx.program_id));
+    }
+    config::set(config_path, &format!("trusted_programs.{}", key), &info.authority())
+}
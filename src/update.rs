@@ -0,0 +1,80 @@
+use anyhow::{anyhow, Result};
+use minisign_verify::{PublicKey, Signature};
+use serde_json::Value;
+use std::fs;
+
+use crate::config::Config;
+use crate::network;
+
+const RELEASES_API: &str = "https://api.github.com/repos/Vlad1k3/escrow-cli/releases/latest";
+const TARGET: &str = env!("ESCROW_CLI_TARGET");
+
+// Replace this binary with the latest GitHub release once its minisign
+// signature checks out against `release_public_key`. Old binaries may encode
+// instruction layouts the deployed program no longer accepts.
+pub fn self_update(config: &Config, check_only: bool) -> Result<()> {
+    let release: Value = network::get_json("Release query", RELEASES_API, &[])?;
+    let tag = release["tag_name"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Release has no tag"))?;
+    let current = env!("CARGO_PKG_VERSION");
+    if !is_newer(tag, current) {
+        println!("escrow-cli {} is up to date", current);
+        return Ok(());
+    }
+    println!("escrow-cli {} is available (installed: {})", tag, current);
+    if check_only {
+        return Ok(());
+    }
+
+    let key = config.release_public_key.as_deref().ok_or_else(|| {
+        anyhow!("Set `release_public_key` in the config to the maintainers' minisign key first")
+    })?;
+    let public_key =
+        PublicKey::from_base64(key).map_err(|e| anyhow!("Invalid release_public_key: {}", e))?;
+    let asset = format!("escrow-cli-{}", TARGET);
+    let binary = download(&release, &asset)?;
+    let signature = String::from_utf8(download(&release, &format!("{}.minisig", asset))?)?;
+    let signature =
+        Signature::decode(&signature).map_err(|e| anyhow!("Invalid release signature: {}", e))?;
+    public_key
+        .verify(&binary, &signature, false)
+        .map_err(|e| anyhow!("Release signature does not verify, not installing: {}", e))?;
+
+    // Rename over the running binary so an interrupted update can't leave a
+    // truncated one behind
+    let exe = std::env::current_exe()?;
+    let staged = exe.with_extension("update");
+    config.sandbox.check_write(&staged)?;
+    fs::write(&staged, &binary)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))?;
+    }
+    fs::rename(&staged, &exe)
+        .map_err(|e| anyhow!("Failed to replace {}: {}", exe.display(), e))?;
+    println!("Updated to {} (signature verified)", tag);
+    Ok(())
+}
+
+fn download(release: &Value, name: &str) -> Result<Vec<u8>> {
+    let url = release["assets"]
+        .as_array()
+        .and_then(|assets| assets.iter().find(|asset| asset["name"] == name))
+        .and_then(|asset| asset["browser_download_url"].as_str())
+        .ok_or_else(|| anyhow!("Release has no {} asset", name))?;
+    network::get(&format!("Download of {}", name), url, &[])
+}
+
+// Compare dotted versions numerically, ignoring a leading `v`
+fn is_newer(tag: &str, current: &str) -> bool {
+    let parse = |version: &str| -> Vec<u64> {
+        version
+            .trim_start_matches('v')
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    };
+    parse(tag) > parse(current)
+}
@@ -0,0 +1,149 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use solana_sdk::{message::Message, pubkey::Pubkey, signature::Signer, transaction::Transaction};
+use std::str::FromStr;
+use std::thread::sleep;
+use std::time::Duration;
+
+use crate::account::EscrowState;
+use crate::memo::{find_memos, memo_instruction};
+use crate::search::escrows_with_party;
+use crate::sla::now;
+use crate::{hooks, interrupt, simulate_and_send, Context};
+
+const MEMO_PREFIX: &str = "escrow-away:v1";
+
+// An arbiter's absence: Funded escrows naming them get a memo pointing to
+// the backup while it lasts
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Vacation {
+    // YYYY-MM-DD (UTC) or a unix timestamp
+    pub start: Option<String>,
+    pub end: Option<String>,
+    pub backup_arbiter: Option<String>,
+    pub message: Option<String>,
+}
+
+impl Vacation {
+    pub fn validate(&self) -> Result<()> {
+        let start = self.start.as_deref().map(parse_date).transpose()?;
+        let end = self.end.as_deref().map(parse_date).transpose()?;
+        if let (Some(start), Some(end)) = (start, end) {
+            if end <= start {
+                return Err(anyhow!("vacation.end must be after vacation.start"));
+            }
+        }
+        if let Some(backup) = &self.backup_arbiter {
+            Pubkey::from_str(backup)
+                .map_err(|_| anyhow!("vacation.backup_arbiter: '{}' is not a pubkey", backup))?;
+        }
+        Ok(())
+    }
+
+    fn is_active(&self, now: i64) -> Result<bool> {
+        let (Some(start), Some(end)) = (&self.start, &self.end) else {
+            return Ok(false);
+        };
+        Ok(parse_date(start)? <= now && now < parse_date(end)?)
+    }
+}
+
+// Post a forwarding memo on every Funded escrow naming this arbiter that
+// hasn't had one yet, and notify the parties through the notify hook. With
+// `interval`, keep checking until the vacation ends.
+pub fn arbiter_away(
+    ctx: &Context,
+    arbiter_keypair_path: &str,
+    interval: Option<u64>,
+) -> Result<()> {
+    let vacation = &ctx.config.vacation;
+    let backup = vacation
+        .backup_arbiter
+        .as_deref()
+        .ok_or_else(|| anyhow!("Set vacation.backup_arbiter in the config"))?;
+    let arbiter_keypair = ctx.read_keypair(arbiter_keypair_path, "arbiter")?;
+    let arbiter = arbiter_keypair.pubkey();
+    loop {
+        if !vacation.is_active(now())? {
+            println!("Not within the configured vacation (vacation.start/end)");
+            return Ok(());
+        }
+        // Memos already posted, so reruns don't repeat themselves
+        let posted: Vec<String> = find_memos(&ctx.client, &arbiter)?
+            .into_iter()
+            .map(|record| record.memo)
+            .filter(|memo| memo.starts_with(MEMO_PREFIX))
+            .collect();
+        for (escrow_pubkey, escrow) in escrows_with_party(ctx, &arbiter)? {
+            let marker = format!("{} {}", MEMO_PREFIX, escrow_pubkey);
+            if escrow.arbiter != arbiter
+                || escrow.state != EscrowState::Funded
+                || posted.iter().any(|memo| memo.starts_with(&marker))
+            {
+                continue;
+            }
+            interrupt::building();
+            let until = vacation.end.as_deref().unwrap_or_default();
+            let mut memo = format!("{} backup {} until {}", marker, backup, until);
+            if let Some(message) = &vacation.message {
+                memo = format!("{}: {}", memo, message);
+            }
+            let memo_ix = memo_instruction(&memo, &[&arbiter]);
+            let blockhash = ctx.client.get_latest_blockhash()?;
+            let message = Message::new(&[memo_ix], Some(&arbiter));
+            let transaction = Transaction::new(&[&arbiter_keypair], message, blockhash);
+            let Some(signature) =
+                simulate_and_send(ctx, &transaction, &escrow_pubkey, "arbiter_away")?
+            else {
+                continue;
+            };
+            println!("Forwarding memo posted for {}! Signature: {}", escrow_pubkey, signature);
+            hooks::notify(
+                &ctx.config,
+                &json!({
+                    "event": "arbiter_away",
+                    "escrow": escrow_pubkey.to_string(),
+                    "buyer": escrow.buyer.to_string(),
+                    "seller": escrow.seller.to_string(),
+                    "arbiter": arbiter.to_string(),
+                    "backup_arbiter": backup,
+                    "until": until,
+                    "message": vacation.message,
+                    "signature": signature.to_string(),
+                }),
+            );
+        }
+        match interval {
+            // A dry run previews one pass
+            Some(interval) if !ctx.dry_run => sleep(Duration::from_secs(interval)),
+            _ => return Ok(()),
+        }
+    }
+}
+
+// Unix timestamp, or a YYYY-MM-DD date at 00:00 UTC
+fn parse_date(input: &str) -> Result<i64> {
+    if let Ok(timestamp) = input.parse() {
+        return Ok(timestamp);
+    }
+    let invalid = || anyhow!("Invalid date '{}': expected YYYY-MM-DD or a unix timestamp", input);
+    let parts: Vec<i64> = input
+        .split('-')
+        .map(|part| part.parse().map_err(|_| invalid()))
+        .collect::<Result<_>>()?;
+    let [year, month, day] = parts[..] else {
+        return Err(invalid());
+    };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(invalid());
+    }
+    // Days since the epoch in the proleptic Gregorian calendar
+    let (year, month) = if month <= 2 { (year - 1, month + 9) } else { (year, month - 3) };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Ok((era * 146_097 + day_of_era - 719_468) * 86_400)
+}
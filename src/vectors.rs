@@ -0,0 +1,185 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::{json, Value};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+
+use crate::account::{EscrowAccount, EscrowState};
+use crate::amount::Lamports;
+use crate::pipe::write_output;
+use crate::{
+    arbiter_instruction, close_instruction, confirm_instruction, create_offer_instruction,
+    find_vault_pda, fund_instruction, join_instruction, mutual_cancel_instruction, ArbiterDecision,
+    Context,
+};
+
+pub const VECTORS_VERSION: u32 = 1;
+
+// Fixed keys so every run, on every machine, emits the same file
+fn key(byte: u8) -> Pubkey {
+    Pubkey::new_from_array([byte; 32])
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn account_vector(escrow: &EscrowAccount) -> Result<Value> {
+    let data = escrow.pack();
+    // Round-trip through the decoder the CLI itself uses
+    let decoded = EscrowAccount::unpack(&data)?;
+    if &decoded != escrow {
+        return Err(anyhow!("Escrow account layout does not round-trip"));
+    }
+    Ok(json!({
+        "data_hex": hex(&data),
+        "data_base64": STANDARD.encode(data),
+        "decoded": decoded.to_json(),
+    }))
+}
+
+pub fn instruction_vector(name: &str, params: Value, instruction: &Instruction) -> Value {
+    let accounts: Vec<Value> = instruction
+        .accounts
+        .iter()
+        .map(|meta| {
+            json!({
+                "pubkey": meta.pubkey.to_string(),
+                "is_signer": meta.is_signer,
+                "is_writable": meta.is_writable,
+            })
+        })
+        .collect();
+    json!({
+        "instruction": name,
+        "params": params,
+        "program_id": instruction.program_id.to_string(),
+        "accounts": accounts,
+        "data_hex": hex(&instruction.data),
+    })
+}
+
+// Emit canonical encodings (account bytes <-> decoded fields, parameters ->
+// instruction data) as JSON, so other clients can check they produce
+// byte-identical accounts and instructions
+pub fn generate(ctx: &Context, out: &str) -> Result<()> {
+    let vectors = canonical(&ctx.program_id)?;
+    write_output(
+        &ctx.config.sandbox,
+        out,
+        serde_json::to_string_pretty(&vectors)?.as_bytes(),
+    )
+}
+
+// The vectors for `program_id`, built from fixed inputs
+pub fn canonical(program_id: &Pubkey) -> Result<Value> {
+    let program_id = *program_id;
+    let (buyer, seller, arbiter, escrow) = (key(1), key(2), key(3), key(4));
+    let (vault, vault_bump) = find_vault_pda(&escrow, &program_id);
+    let amount = Lamports::new(1_500_000_000);
+
+    let states = [
+        EscrowState::Uninitialized,
+        EscrowState::Created,
+        EscrowState::Initialized,
+        EscrowState::Funded,
+        EscrowState::Completed,
+        EscrowState::Cancelled,
+    ];
+    let accounts = states
+        .iter()
+        .map(|state| {
+            let joined = *state >= EscrowState::Initialized;
+            account_vector(&EscrowAccount {
+                buyer,
+                seller: if joined { seller } else { Pubkey::default() },
+                arbiter,
+                amount,
+                state: *state,
+                vault_bump,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let party = |pubkey: &Pubkey| Value::String(pubkey.to_string());
+    let instructions = vec![
+        instruction_vector(
+            "create_offer",
+            json!({
+                "buyer": party(&buyer),
+                "escrow": party(&escrow),
+                "arbiter": party(&arbiter),
+                "amount": amount.lamports(),
+            }),
+            &create_offer_instruction(&program_id, &buyer, &escrow, &arbiter, amount),
+        ),
+        instruction_vector(
+            "join_offer",
+            json!({ "seller": party(&seller), "escrow": party(&escrow) }),
+            &join_instruction(&program_id, &seller, &escrow),
+        ),
+        instruction_vector(
+            "fund_escrow",
+            json!({ "buyer": party(&buyer), "escrow": party(&escrow) }),
+            &fund_instruction(&program_id, &buyer, &escrow),
+        ),
+        instruction_vector(
+            "confirm",
+            json!({ "seller": party(&seller), "escrow": party(&escrow) }),
+            &confirm_instruction(&program_id, &seller, &escrow),
+        ),
+        instruction_vector(
+            "arbiter_confirm",
+            json!({
+                "arbiter": party(&arbiter),
+                "escrow": party(&escrow),
+                "seller": party(&seller),
+            }),
+            &arbiter_instruction(
+                &program_id,
+                ArbiterDecision::Release,
+                &arbiter,
+                &escrow,
+                &seller,
+            ),
+        ),
+        instruction_vector(
+            "arbiter_cancel",
+            json!({
+                "arbiter": party(&arbiter),
+                "escrow": party(&escrow),
+                "buyer": party(&buyer),
+            }),
+            &arbiter_instruction(
+                &program_id,
+                ArbiterDecision::Refund,
+                &arbiter,
+                &escrow,
+                &buyer,
+            ),
+        ),
+        instruction_vector(
+            "close_escrow",
+            json!({ "closer": party(&buyer), "escrow": party(&escrow) }),
+            &close_instruction(&program_id, &buyer, &escrow),
+        ),
+        instruction_vector(
+            "mutual_cancel",
+            json!({ "buyer": party(&buyer), "seller": party(&seller), "escrow": party(&escrow) }),
+            &mutual_cancel_instruction(&program_id, &buyer, &seller, &escrow),
+        ),
+    ];
+
+    Ok(json!({
+        "version": VECTORS_VERSION,
+        "cli_version": env!("CARGO_PKG_VERSION"),
+        "program_id": program_id.to_string(),
+        "vault_pda": {
+            "seeds": ["vault", party(&escrow)],
+            "escrow": party(&escrow),
+            "address": vault.to_string(),
+            "bump": vault_bump,
+        },
+        "accounts": accounts,
+        "instructions": instructions,
+    }))
+}
@@ -0,0 +1,41 @@
+use anyhow::{anyhow, Result};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+
+use crate::memo::memo_instruction;
+use crate::{transcript, Context};
+
+const VERDICT_TAG: &str = "escrow-verdict:v1";
+
+// Render a verdict template from the config's [verdicts] table into a memo
+// instruction signed by the arbiter. Templates may use {escrow}, {buyer},
+// {seller}, {arbiter}, {amount} and {decision}.
+pub fn verdict_instruction(
+    ctx: &Context,
+    template: &str,
+    escrow_pubkey: &Pubkey,
+    decision: &str,
+    arbiter: &Pubkey,
+) -> Result<Instruction> {
+    let text = ctx.config.verdicts.get(template).ok_or_else(|| {
+        anyhow!(
+            "No verdict template '{}' in [verdicts], configured templates: {}",
+            template,
+            ctx.config.verdicts.keys().cloned().collect::<Vec<_>>().join(", ")
+        )
+    })?;
+    let escrow = ctx.get_escrow(escrow_pubkey)?;
+    let rendered = text
+        .replace("{escrow}", &escrow_pubkey.to_string())
+        .replace("{buyer}", &escrow.buyer.to_string())
+        .replace("{seller}", &escrow.seller.to_string())
+        .replace("{arbiter}", &escrow.arbiter.to_string())
+        .replace("{amount}", &format!("{} SOL", escrow.amount))
+        .replace("{decision}", decision);
+    let memo = format!(
+        "{} {} {} {}: {}",
+        VERDICT_TAG, escrow_pubkey, decision, template, rendered
+    );
+    transcript::memo(&rendered);
+    println!("Verdict memo: {}", memo);
+    Ok(memo_instruction(&memo, &[arbiter]))
+}
@@ -0,0 +1,33 @@
+use anyhow::{anyhow, Result};
+use std::fs;
+
+use crate::terms::digest_bytes;
+use crate::{Context, PROGRAM_ID};
+
+const GIT_COMMIT: &str = env!("ESCROW_CLI_GIT_COMMIT");
+
+// Print the version and commit; with `verify`, also the program this binary
+// talks to and the SHA-256 of the binary itself, for counterparties to
+// compare before co-signing
+pub fn version(ctx: &Context, verify: bool) -> Result<()> {
+    println!("escrow-cli {}", env!("CARGO_PKG_VERSION"));
+    println!("Git commit:  {}", GIT_COMMIT);
+    if !verify {
+        return Ok(());
+    }
+    let program = if ctx.program_id.to_string() == PROGRAM_ID {
+        "built-in default".to_string()
+    } else {
+        format!("from config; built-in default is {}", PROGRAM_ID)
+    };
+    println!("Program ID:  {} ({})", ctx.program_id, program);
+    let exe = std::env::current_exe()?;
+    let binary = fs::read(&exe).map_err(|e| anyhow!("Failed to read {}: {}", exe.display(), e))?;
+    println!("Build hash:  {}", digest_bytes(&binary));
+    if GIT_COMMIT.ends_with("-dirty") || GIT_COMMIT == "unknown" {
+        eprintln!("Warning: not built from a clean checkout; the build hash can't be reproduced");
+    } else {
+        println!("Rebuild this commit with scripts/reproducible-build.sh to reproduce the hash.");
+    }
+    Ok(())
+}
@@ -0,0 +1,342 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::Args;
+use serde_json::json;
+use solana_client::nonce_utils;
+use solana_sdk::{
+    message::Message, pubkey::Pubkey, signature::Signer, system_instruction,
+    transaction::Transaction,
+};
+use std::str::FromStr;
+use std::thread::sleep;
+use std::time::Duration;
+
+use crate::account::{EscrowAccount, EscrowState};
+use crate::build::PartiallySignedTx;
+use crate::duration::format_duration;
+use crate::envelope::{self, TxEncoding};
+use crate::inspect;
+use crate::sla::{now, state_since};
+use crate::totp;
+use crate::{check_state, hooks, mutual_cancel_instruction, pipe, Context};
+
+// How the mutual-cancel transaction offered with a nudge is prepared
+#[derive(Args, Debug)]
+pub struct PrepareCancel {
+    /// Partially sign the mutual-cancel transaction with this buyer or
+    /// seller keypair when nudging
+    #[arg(long, requires = "nudge_after", requires = "cancel_nonce")]
+    pub prepare_cancel: Option<String>,
+    /// Durable nonce account, with the --prepare-cancel keypair as its
+    /// authority, that keeps the prepared transaction valid until the other
+    /// party co-signs it
+    #[arg(long, requires = "prepare_cancel")]
+    pub cancel_nonce: Option<String>,
+    /// Encoding of the prepared transaction
+    #[arg(long, value_enum, default_value_t = TxEncoding::Base64)]
+    pub cancel_encoding: TxEncoding,
+    /// Write the prepared transaction to a file instead of printing it
+    #[arg(long, requires = "prepare_cancel")]
+    pub cancel_out: Option<String>,
+}
+
+// A single field that differs between two observations of the escrow
+struct FieldChange {
+    field: &'static str,
+    old: String,
+    new: String,
+}
+
+// Poll an escrow and report every field-level change, alerting when a write
+// touches a field the observed state transition should leave alone. With
+// `nudge_after`, an escrow left unfunded that long after the seller joined
+// gets a one-time suggestion to mutual-cancel.
+pub fn watch(
+    ctx: &Context,
+    escrow_account: &str,
+    interval: u64,
+    exit_on_alert: bool,
+    nudge_after: Option<Duration>,
+    cancel: &PrepareCancel,
+) -> Result<()> {
+    let escrow_pubkey = Pubkey::from_str(escrow_account)?;
+    if cancel.prepare_cancel.is_some() && ctx.config.read_only {
+        return Err(anyhow!("Read-only mode: refusing to sign the cancel transaction"));
+    }
+    if cancel.cancel_out.is_none() {
+        envelope::printable(cancel.cancel_encoding)?;
+    }
+    let mut previous: Option<EscrowAccount> = None;
+    // Block time at which the escrow entered Initialized, once known
+    let mut initialized_at: Option<i64> = None;
+    let mut nudged = false;
+    println!("Watching escrow {} every {}s (Ctrl-C to stop)", escrow_pubkey, interval);
+    loop {
+        // Unchanged bytes are not decoded again, and a lagging node can't
+        // hand back an older view than the last poll saw
+        let (slot, read) = ctx.refresh_escrow(&escrow_pubkey)?;
+        let Some((current, changed)) = read else {
+            if previous.is_some() {
+                println!("[slot {}] Escrow account closed", slot);
+                return Ok(());
+            }
+            return Err(anyhow!("Escrow account {} not found", escrow_pubkey));
+        };
+        match &previous {
+            None => println!("[slot {}] State: {}", slot, current.state),
+            Some(prev) if changed => {
+                let alerted = report_changes(slot, prev, &current);
+                if alerted && exit_on_alert {
+                    return Err(anyhow!("Unexpected write to escrow {}", escrow_pubkey));
+                }
+            }
+            Some(_) => {}
+        }
+        if current.state != EscrowState::Initialized {
+            initialized_at = None;
+        } else if let Some(after) = nudge_after {
+            if initialized_at.is_none() {
+                // While it sits in Initialized, the last activity is the
+                // seller's join
+                initialized_at = state_since(ctx, &escrow_pubkey)?;
+            }
+            if let (false, Some(since)) = (nudged, initialized_at) {
+                let waited = Duration::from_secs((now() - since).max(0) as u64);
+                if waited >= after {
+                    nudge(ctx, &escrow_pubkey, &current, waited, cancel)?;
+                    nudged = true;
+                }
+            }
+        }
+        previous = Some(current);
+        sleep(Duration::from_secs(interval));
+    }
+}
+
+// Tell both parties the escrow is stuck waiting for funds and how to unwind
+// it, optionally with a mutual-cancel transaction already signed by one side
+fn nudge(
+    ctx: &Context,
+    escrow_pubkey: &Pubkey,
+    escrow: &EscrowAccount,
+    waited: Duration,
+    cancel: &PrepareCancel,
+) -> Result<()> {
+    let suggestion = format!(
+        "escrow-cli mutual-cancel -b <BUYER_KEYPAIR> -s <SELLER_KEYPAIR> -e {}",
+        escrow_pubkey
+    );
+    eprintln!(
+        "NUDGE: escrow {} has waited {} for the buyer to fund since the seller joined",
+        escrow_pubkey,
+        format_duration(waited)
+    );
+    eprintln!("  Buyer {} and seller {} can unwind it with:", escrow.buyer, escrow.seller);
+    eprintln!("  {}", suggestion);
+    let partial = match (&cancel.prepare_cancel, &cancel.cancel_nonce) {
+        (Some(path), Some(nonce)) => Some(partially_signed_cancel(
+            ctx,
+            escrow_pubkey,
+            escrow,
+            path,
+            nonce,
+        )?),
+        _ => None,
+    };
+    if let (Some(transaction), Some(nonce)) = (&partial, &cancel.cancel_nonce) {
+        let exported = envelope::export_transaction(
+            ctx,
+            transaction,
+            cancel.cancel_encoding,
+            &format!("Mutual cancel of escrow {}", escrow_pubkey),
+            None,
+        )?;
+        let file = match &cancel.cancel_out {
+            Some(out) => {
+                pipe::write_output(&ctx.config.sandbox, out, &exported)?;
+                println!("Partially signed mutual-cancel transaction saved to {}", out);
+                out.as_str()
+            }
+            None => {
+                println!("Partially signed mutual-cancel transaction:");
+                println!("{}", String::from_utf8_lossy(&exported));
+                "<FILE>"
+            }
+        };
+        println!("The other party co-signs and sends it with:");
+        println!(
+            "  escrow-cli cosign-cancel -k <KEYPAIR> -e {} -f {}",
+            escrow_pubkey, file
+        );
+        println!(
+            "It stays valid until nonce account {} is used for anything else.",
+            nonce
+        );
+    }
+    let partial = match partial {
+        Some(transaction) => Some(STANDARD.encode(bincode::serialize(&transaction)?)),
+        None => None,
+    };
+    hooks::notify(
+        &ctx.config,
+        &json!({
+            "event": "cancel_nudge",
+            "escrow": escrow_pubkey.to_string(),
+            "buyer": escrow.buyer.to_string(),
+            "seller": escrow.seller.to_string(),
+            "waited_seconds": waited.as_secs(),
+            "suggestion": suggestion,
+            "partially_signed_transaction": partial,
+        }),
+    );
+    Ok(())
+}
+
+// The mutual cancel signed by one party on a durable nonce, so it doesn't
+// expire with a recent blockhash before the other party gets to it
+fn partially_signed_cancel(
+    ctx: &Context,
+    escrow_pubkey: &Pubkey,
+    escrow: &EscrowAccount,
+    keypair_path: &str,
+    nonce_account: &str,
+) -> Result<Transaction> {
+    let keypair = ctx.read_keypair(keypair_path, "cancelling party")?;
+    if keypair.pubkey() != escrow.buyer && keypair.pubkey() != escrow.seller {
+        return Err(anyhow!(
+            "{} is neither the buyer nor the seller of this escrow",
+            keypair.pubkey()
+        ));
+    }
+    totp::check(ctx, "mutual-cancel", escrow.amount)?;
+    let nonce_pubkey = Pubkey::from_str(nonce_account)?;
+    let account = nonce_utils::get_account_with_commitment(
+        &ctx.client,
+        &nonce_pubkey,
+        ctx.client.commitment(),
+    )?;
+    let nonce = nonce_utils::data_from_account(&account)?;
+    if nonce.authority != keypair.pubkey() {
+        return Err(anyhow!(
+            "Nonce authority is {}, not the signing keypair {}",
+            nonce.authority,
+            keypair.pubkey()
+        ));
+    }
+    let advance_ix = system_instruction::advance_nonce_account(&nonce_pubkey, &keypair.pubkey());
+    let cancel_ix =
+        mutual_cancel_instruction(&ctx.program_id, &escrow.buyer, &escrow.seller, escrow_pubkey);
+    let message = Message::new(&[advance_ix, cancel_ix], Some(&escrow.buyer));
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction.try_partial_sign(&[&keypair], nonce.blockhash())?;
+    Ok(transaction)
+}
+
+// Add the second signature to a mutual cancel prepared by `watch
+// --prepare-cancel` and send it
+pub fn cosign_cancel(
+    ctx: &Context,
+    keypair_path: &str,
+    escrow_account: &str,
+    file: &str,
+) -> Result<()> {
+    let keypair = ctx.read_keypair(keypair_path, "co-signing party")?;
+    let escrow_pubkey = Pubkey::from_str(escrow_account)?;
+    let transaction = envelope::import_transaction(&pipe::read_input(file)?)?;
+    // Only the escrow program on this escrow, the nonce advance and the
+    // like; no unknown programs
+    inspect::check_imported(ctx, &transaction, &escrow_pubkey, false)?;
+    let message = &transaction.message;
+    // Instruction index 8: mutual_cancel
+    let is_cancel = message.instructions.iter().any(|instruction| {
+        *instruction.program_id(&message.account_keys) == ctx.program_id && instruction.data == [8]
+    });
+    if !is_cancel {
+        return Err(anyhow!("{} is not a mutual-cancel transaction", file));
+    }
+    match check_state(ctx, escrow_account)? {
+        EscrowState::Initialized | EscrowState::Funded => {}
+        other_state => {
+            return Err(anyhow!(
+                "Escrow must be in Initialized or Funded state, current state: {:?}",
+                other_state
+            ))
+        }
+    }
+    let mut transaction = PartiallySignedTx {
+        transaction,
+        escrow: escrow_pubkey,
+        action: "mutual_cancel",
+    };
+    if !transaction.missing_signers().contains(&keypair.pubkey()) {
+        return Err(anyhow!(
+            "{} is not a missing signer of this transaction",
+            keypair.pubkey()
+        ));
+    }
+    totp::check(ctx, "mutual-cancel", ctx.get_escrow(&escrow_pubkey)?.amount)?;
+    transaction.sign(&[&keypair])?;
+    let Some(signature) = transaction.execute(ctx)? else {
+        return Ok(());
+    };
+    println!("Mutual cancel successful! Signature: {}", signature);
+    Ok(())
+}
+
+// Print the changes between two observations; returns true if any alert fired
+fn report_changes(slot: u64, prev: &EscrowAccount, current: &EscrowAccount) -> bool {
+    let expected = expected_fields(prev.state, current.state);
+    let mut alerted = false;
+    if prev.state != current.state && expected.is_empty() {
+        eprintln!(
+            "[slot {}] ALERT: unexpected state transition {} -> {}",
+            slot, prev.state, current.state
+        );
+        alerted = true;
+    }
+    for change in diff(prev, current) {
+        if expected.contains(&change.field) {
+            println!("[slot {}] {}: {} -> {}", slot, change.field, change.old, change.new);
+        } else {
+            eprintln!(
+                "[slot {}] ALERT: {} changed {} -> {} during {} -> {}",
+                slot, change.field, change.old, change.new, prev.state, current.state
+            );
+            alerted = true;
+        }
+    }
+    alerted
+}
+
+// Fields each legitimate transition is expected to write
+fn expected_fields(from: EscrowState, to: EscrowState) -> &'static [&'static str] {
+    use EscrowState::*;
+    match (from, to) {
+        (Created, Initialized) => &["state", "seller"],
+        (Initialized, Funded)
+        | (Initialized, Cancelled)
+        | (Funded, Completed)
+        | (Funded, Cancelled) => &["state"],
+        _ => &[],
+    }
+}
+
+fn diff(prev: &EscrowAccount, current: &EscrowAccount) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+    let mut check = |field: &'static str, old: String, new: String| {
+        if old != new {
+            changes.push(FieldChange { field, old, new });
+        }
+    };
+    check("state", prev.state.to_string(), current.state.to_string());
+    check("buyer", prev.buyer.to_string(), current.buyer.to_string());
+    check("seller", prev.seller.to_string(), current.seller.to_string());
+    check("arbiter", prev.arbiter.to_string(), current.arbiter.to_string());
+    check("amount", prev.amount.lamports().to_string(), current.amount.lamports().to_string());
+    check(
+        "vault_bump",
+        prev.vault_bump.to_string(),
+        current.vault_bump.to_string(),
+    );
+    changes
+}